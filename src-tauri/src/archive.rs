@@ -0,0 +1,224 @@
+use std::io::Read;
+use std::path::Path;
+
+pub fn sha256_file(path: &Path) -> Result<String, Box<dyn std::error::Error>> {
+    use sha2::{Digest, Sha256};
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 512 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Archive paths must stay inside `dest_dir`: only plain components, no `..`, no roots.
+pub fn safe_archive_path(path: &Path) -> Option<&Path> {
+    let plain = path.components().all(|c| matches!(c, std::path::Component::Normal(_)));
+    (plain && !path.as_os_str().is_empty()).then_some(path)
+}
+
+// macOS/Linux: streaming tar.gz extraction, strips `strip` leading path components
+#[cfg(not(target_os = "windows"))]
+pub fn extract_tar_gz(
+    reader: impl Read,
+    dest_dir: &Path,
+    strip: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    use flate2::read::GzDecoder;
+    use tar::{Archive, EntryType};
+
+    let gz = GzDecoder::new(reader);
+    let mut archive = Archive::new(gz);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = entry.path()?.into_owned();
+        // Strip e.g. "llama-b9496/" prefix
+        let stripped: std::path::PathBuf = path.components().skip(strip).collect();
+        if stripped.as_os_str().is_empty() {
+            continue;
+        }
+        let rel = safe_archive_path(&stripped)
+            .ok_or_else(|| format!("Unsafe path in archive: {}", path.display()))?;
+        match entry.header().entry_type() {
+            EntryType::Regular | EntryType::Directory => {}
+            // Versioned .so/.dylib links: the target must stay next to the link
+            EntryType::Symlink => {
+                let target = entry.link_name()?.ok_or("Symlink without target in archive")?;
+                if safe_archive_path(&target).is_none() {
+                    return Err(format!("Unsafe symlink in archive: {} -> {}", path.display(), target.display()).into());
+                }
+            }
+            other => {
+                log::debug!("skipping archive entry {} ({:?})", path.display(), other);
+                continue;
+            }
+        }
+        let dest = dest_dir.join(rel);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        entry.unpack(&dest)?;
+    }
+    Ok(())
+}
+
+// Zip extraction (must buffer — ZipArchive requires Seek)
+pub fn extract_zip(
+    reader: impl Read,
+    dest_dir: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut data = Vec::new();
+    let mut r = reader;
+    r.read_to_end(&mut data)?;
+    let cursor = std::io::Cursor::new(data);
+    let mut archive = zip::ZipArchive::new(cursor)?;
+    for i in 0..archive.len() {
+        let mut file = archive.by_index(i)?;
+        if file.is_dir() {
+            continue;
+        }
+        let name = file.enclosed_name().ok_or("Invalid zip entry")?.to_owned();
+        let rel = safe_archive_path(&name)
+            .ok_or_else(|| format!("Unsafe path in archive: {}", name.display()))?;
+        let dest = dest_dir.join(rel);
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut outfile = std::fs::File::create(&dest)?;
+        std::io::copy(&mut file, &mut outfile)?;
+    }
+    Ok(())
+}
+
+/// Staged install: moves `staging_dir` over `install_dir` with renames, keeping the
+/// previous contents until the swap succeeds.
+pub fn swap_dirs(staging_dir: &Path, install_dir: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut old_name = install_dir.file_name().unwrap_or_default().to_os_string();
+    old_name.push(".old");
+    let old_dir = install_dir.with_file_name(old_name);
+    let _ = std::fs::remove_dir_all(&old_dir);
+    if install_dir.exists() {
+        std::fs::rename(install_dir, &old_dir)?;
+    }
+    if let Err(e) = std::fs::rename(staging_dir, install_dir) {
+        // Put the previous release back before reporting
+        let _ = std::fs::rename(&old_dir, install_dir);
+        return Err(e.into());
+    }
+    let _ = std::fs::remove_dir_all(&old_dir);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workspace::Workspace;
+    use std::io::Write;
+
+    #[test]
+    fn safe_archive_path_accepts_only_plain_relative_paths() {
+        assert!(safe_archive_path(Path::new("bin/llama-completion")).is_some());
+        assert!(safe_archive_path(Path::new("libggml.so")).is_some());
+        assert!(safe_archive_path(Path::new("")).is_none());
+        assert!(safe_archive_path(Path::new("../evil")).is_none());
+        assert!(safe_archive_path(Path::new("bin/../../evil")).is_none());
+        assert!(safe_archive_path(Path::new("./bin")).is_none());
+        assert!(safe_archive_path(Path::new("/etc/passwd")).is_none());
+    }
+
+    // `Header::set_path` refuses `..`, so the name goes into the raw header like a hostile archive
+    #[cfg(not(target_os = "windows"))]
+    fn tar_gz(entries: &[(&str, tar::EntryType, &str)]) -> Vec<u8> {
+        let gz = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        let mut builder = tar::Builder::new(gz);
+        for (name, kind, content) in entries {
+            let mut header = tar::Header::new_old();
+            header.set_entry_type(*kind);
+            header.set_mode(0o644);
+            let raw = header.as_old_mut();
+            raw.name[..name.len()].copy_from_slice(name.as_bytes());
+            let data = if *kind == tar::EntryType::Symlink {
+                raw.linkname[..content.len()].copy_from_slice(content.as_bytes());
+                &[][..]
+            } else {
+                content.as_bytes()
+            };
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            builder.append(&header, data).unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn tar_gz_extracts_and_strips_the_release_dir() {
+        let ws = Workspace::create("archive-test").unwrap();
+        let archive = tar_gz(&[
+            ("llama-b9496/llama-completion", tar::EntryType::Regular, "bin"),
+            ("llama-b9496/libggml.so.1", tar::EntryType::Regular, "lib"),
+            ("llama-b9496/libggml.so", tar::EntryType::Symlink, "libggml.so.1"),
+        ]);
+        extract_tar_gz(archive.as_slice(), ws.path(), 1).unwrap();
+        assert_eq!(std::fs::read_to_string(ws.path().join("llama-completion")).unwrap(), "bin");
+        assert_eq!(std::fs::read_to_string(ws.path().join("libggml.so")).unwrap(), "lib");
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn tar_gz_rejects_traversal() {
+        let ws = Workspace::create("archive-test").unwrap();
+        let dest = ws.path().join("dest");
+        let archive = tar_gz(&[("llama-b9496/../../evil", tar::EntryType::Regular, "x")]);
+        assert!(extract_tar_gz(archive.as_slice(), &dest, 1).is_err());
+        assert!(!ws.path().join("evil").exists());
+    }
+
+    #[cfg(not(target_os = "windows"))]
+    #[test]
+    fn tar_gz_rejects_symlinks_leaving_the_dir() {
+        let ws = Workspace::create("archive-test").unwrap();
+        for target in ["../../etc", "/etc/passwd"] {
+            let archive = tar_gz(&[("llama-b9496/libggml.so", tar::EntryType::Symlink, target)]);
+            let err = extract_tar_gz(archive.as_slice(), ws.path(), 1).unwrap_err();
+            assert!(err.to_string().contains("Unsafe symlink"), "{}", err);
+            assert!(std::fs::symlink_metadata(ws.path().join("libggml.so")).is_err());
+        }
+    }
+
+    fn zip(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        for (name, content) in entries {
+            writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn zip_extracts_nested_files() {
+        let ws = Workspace::create("archive-test").unwrap();
+        let archive = zip(&[("llama-completion.exe", "bin"), ("lib/ggml.dll", "lib")]);
+        extract_zip(archive.as_slice(), ws.path()).unwrap();
+        assert_eq!(std::fs::read_to_string(ws.path().join("llama-completion.exe")).unwrap(), "bin");
+        assert_eq!(std::fs::read_to_string(ws.path().join("lib/ggml.dll")).unwrap(), "lib");
+    }
+
+    #[test]
+    fn zip_rejects_traversal() {
+        let ws = Workspace::create("archive-test").unwrap();
+        let dest = ws.path().join("dest");
+        for name in ["../evil.txt", "/evil.txt"] {
+            let archive = zip(&[(name, "x")]);
+            assert!(extract_zip(archive.as_slice(), &dest).is_err(), "{}", name);
+        }
+        assert!(!ws.path().join("evil.txt").exists());
+    }
+}
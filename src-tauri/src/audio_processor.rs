@@ -1,248 +1,430 @@
-use std::{fs::File, time::Instant};
+use std::time::Instant;
+use std::path::Path;
 use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+pub mod audio_decoder;
+mod preprocess;
+mod whisper_cli;
+mod remote;
+pub mod whisper_models;
+#[cfg(feature = "in-process")]
+mod in_process;
 
-use nnnoiseless::DenoiseState;
-use whisper_rs::{WhisperContext, WhisperContextParameters, FullParams, SamplingStrategy, WhisperVadParams};
-use rubato::{Resampler, SincFixedIn, SincInterpolationType, SincInterpolationParameters, WindowFunction};
-use indicatif::{ProgressBar, ProgressStyle};
-mod audio_decoder;
+pub use preprocess::PreprocessOptions;
+pub use remote::RemoteConfig;
 
 pub type EmitType = Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync>;
 
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TranscriptSegment {
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub text: String,
+}
+
+/// Agrupa segmentos consecutivos en bloques de hasta `max_ms` y `max_chars`. Whisper
+/// corta cada ~50 caracteres, demasiado poco contexto para búsquedas o citas.
+pub fn merge_segments(segments: &[TranscriptSegment], max_ms: u64, max_chars: usize) -> Vec<TranscriptSegment> {
+    let mut merged: Vec<TranscriptSegment> = Vec::new();
+    for segment in segments.iter().filter(|s| !s.text.trim().is_empty()) {
+        let fits = merged.last().is_some_and(|m| {
+            segment.to_ms.saturating_sub(m.from_ms) <= max_ms && m.text.len() + segment.text.len() < max_chars
+        });
+        match merged.last_mut() {
+            Some(last) if fits => {
+                last.to_ms = segment.to_ms;
+                last.text.push(' ');
+                last.text.push_str(segment.text.trim());
+            }
+            _ => merged.push(TranscriptSegment {
+                from_ms: segment.from_ms,
+                to_ms: segment.to_ms,
+                text: segment.text.trim().to_string(),
+            }),
+        }
+    }
+    merged
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptionResult {
+    pub text: String,
+    pub segments: Vec<TranscriptSegment>,
+}
+
+/// Motor de transcripción seleccionable en tiempo de ejecución.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    /// Ejecuta el binario whisper-cli bundleado.
+    #[default]
+    WhisperCli,
+    /// whisper.cpp enlazado vía whisper-rs (feature `in-process`, activa por defecto).
+    /// Siempre reduce ruido antes de transcribir.
+    InProcess,
+    /// Servidor whisper.cpp / OpenAI-compatible en la red local.
+    Remote,
+}
+
+pub struct TranscriptionRequest<'a> {
+    pub audio_path: &'a str,
+    pub model_path: &'a Path,
+    pub vad_model_path: Option<&'a Path>,
+    /// Carpeta temporal del trabajo para los archivos intermedios del backend.
+    pub work_dir: &'a Path,
+    /// `auto` deja que whisper detecte el idioma.
+    pub language: &'a str,
+    pub initial_prompt: &'a str,
+    pub beam_size: u32,
+    pub threads: usize,
+    /// El audio ya pasó por `preprocess::denoise`; el backend no debe repetirlo.
+    #[cfg_attr(not(feature = "in-process"), allow(dead_code))]
+    pub denoised: bool,
+}
+
+pub trait TranscriptionBackend {
+    /// Extensiones que el backend lee directamente, sin convertir a WAV temporal.
+    fn supports_format(&self, ext: &str) -> bool;
+    fn transcribe(&self, request: &TranscriptionRequest) -> Result<TranscriptionResult, String>;
+}
+
 const VAD_MODEL_NAME: &str = "ggml-silero-v6.2.0.bin";
 const VAD_MODEL_URL: &str = "https://huggingface.co/ggml-org/whisper-vad/resolve/main/ggml-silero-v6.2.0.bin";
 
-pub struct AudioProcessor{
+pub struct AudioProcessor {
     emit: EmitType,
     file_path: String,
     whisper_model: String,
+    preprocess: PreprocessOptions,
+    backend: BackendKind,
+    remote: Option<RemoteConfig>,
+    settings: crate::settings::TranscriptionSettings,
 }
 
-impl AudioProcessor{
+impl AudioProcessor {
+    /// Preprocesamiento, backend, idioma y demás parámetros salen de la configuración
+    /// guardada; los `with_*` los sobrescriben para esta llamada.
     pub fn new(emit: EmitType, file_path: String, whisper_model: String) -> Self {
-        whisper_rs::install_logging_hooks();
+        let settings = crate::settings::load().transcription;
         AudioProcessor {
             emit,
             file_path,
             whisper_model,
+            preprocess: settings.preprocess.clone(),
+            backend: settings.backend,
+            remote: settings.remote.clone(),
+            settings,
+        }
+    }
+
+    pub fn with_preprocess(mut self, preprocess: PreprocessOptions) -> Self {
+        self.preprocess = preprocess;
+        self
+    }
+
+    pub fn with_backend(mut self, backend: BackendKind) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_remote(mut self, remote: Option<RemoteConfig>) -> Self {
+        self.remote = remote;
+        self
+    }
+
+    /// whisper-cli. Si falta, el trabajo falla en vez de pasar en silencio al motor en
+    /// proceso, que usa otra GPU y otros parámetros: ese se elige con `BackendKind::InProcess`.
+    /// El binario se resuelve aquí una vez por trabajo, no en cada fragmento.
+    fn local_backend(&self) -> Result<Box<dyn TranscriptionBackend>, String> {
+        let whisper_bin = crate::tools::ensure(&*self.emit, crate::tools::Tool::WhisperCli)?;
+        Ok(Box::new(whisper_cli::WhisperCliBackend::new(self.emit.clone(), whisper_bin)))
+    }
+
+    fn make_backend(&self) -> Result<Box<dyn TranscriptionBackend + '_>, String> {
+        match self.backend {
+            BackendKind::WhisperCli => self.local_backend(),
+            #[cfg(feature = "in-process")]
+            BackendKind::InProcess => Ok(Box::new(in_process::InProcessBackend::new(self.emit.clone()))),
+            #[cfg(not(feature = "in-process"))]
+            BackendKind::InProcess => Err("el backend en proceso no está incluido en esta compilación".into()),
+            BackendKind::Remote => {
+                let config = self.remote.clone().ok_or("falta la configuración del servidor remoto")?;
+                let fallback: Option<Box<dyn TranscriptionBackend + '_>> = if config.fallback_to_local {
+                    Some(Box::new(LocalFallback { processor: self, local: std::cell::OnceCell::new() }))
+                } else {
+                    None
+                };
+                Ok(Box::new(remote::RemoteBackend::new(self.emit.clone(), config, fallback)))
+            }
         }
     }
-    
+
+    /// Transcribe el archivo y devuelve el texto; en caso de error, el mensaje.
     pub fn process(&self) -> String {
+        match self.run() {
+            Ok(transcription) => transcription.text,
+            Err(e) => e,
+        }
+    }
+
+    /// Como `process`, pero distingue el error del texto y devuelve también los segmentos.
+    pub fn run(&self) -> Result<TranscriptionResult, String> {
+        log::info!("process() start — file={} model={} backend={:?}", self.file_path, self.whisper_model, self.backend);
+        let backend = match self.make_backend() {
+            Ok(b) => b,
+            Err(e) => {
+                (self.emit)("process", &e, None);
+                return Err(format!("error: {}", e));
+            }
+        };
+
+        // El remoto no usa modelos locales: sólo su respaldo los prepara, y sólo si hace falta
+        let (model_path, vad_path) = if self.backend == BackendKind::Remote {
+            (whisper_models::local_path(&whisper_models::resolve(&self.whisper_model)), None)
+        } else {
+            self.local_models()?
+        };
+
+        let total = Instant::now();
+        let workspace = match crate::workspace::Workspace::create("transcribe") {
+            Ok(w) => w,
+            Err(e) => {
+                (self.emit)("process", &e, None);
+                return Err(format!("error: {}", e));
+            }
+        };
+
+        // Si el backend no lee el formato directamente (m4a, opus, etc.) convertimos a WAV temporal.
+        // Si hay preprocesamiento activo, siempre pasamos por prepare_wav.
+        let ext = std::path::Path::new(&self.file_path)
+            .extension().and_then(|e| e.to_str()).unwrap_or("").to_lowercase();
+        log::debug!("file ext={}", ext);
+        let mut offset_ms = 0;
+        let audio_path = if backend.supports_format(&ext) && !self.preprocess.any() {
+            log::debug!("native format, passing directly");
+            self.file_path.clone()
+        } else {
+            log::info!("converting via prepare_wav (preprocess={:?})", self.preprocess);
+            (self.emit)("process", "convirtiendo audio", None);
+            match self.prepare_wav(&self.file_path, workspace.path()) {
+                Ok((p, trimmed_ms)) => {
+                    log::debug!("prepare_wav ok: {}", p.display());
+                    offset_ms = trimmed_ms;
+                    p.to_str().unwrap().to_string()
+                }
+                Err(e) => {
+                    log::warn!("prepare_wav failed: {}", e);
+                    (self.emit)("process", &format!("Error decodificando audio: {}", e), None);
+                    return Err(format!("error: {}", e));
+                }
+            }
+        };
+
+        log::info!("calling transcribe with audio_path={}", audio_path);
+        (self.emit)("process", "iniciando transcripción", None);
+        let request = TranscriptionRequest {
+            audio_path: &audio_path,
+            model_path: &model_path,
+            vad_model_path: vad_path.as_deref(),
+            work_dir: workspace.path(),
+            language: &self.settings.language,
+            initial_prompt: &self.settings.initial_prompt,
+            beam_size: self.settings.beam_size(),
+            threads: self.settings.threads(),
+            denoised: self.preprocess.denoise,
+        };
+        let transcribe_started = Instant::now();
+        let result = backend.transcribe(&request);
+        if result.is_ok() && self.backend != BackendKind::Remote {
+            // Sobre el WAV preparado la duración sale de la cabecera, sin decodificar
+            if let Ok(audio_secs) = audio_decoder::duration_secs(&audio_path) {
+                crate::recommender::record_transcription(
+                    &self.whisper_model,
+                    &model_path,
+                    audio_secs,
+                    transcribe_started.elapsed().as_secs_f64(),
+                );
+            }
+        }
+        let mut transcription = match result {
+            Ok(t) => t,
+            Err(e) => {
+                (self.emit)("process", &e, None);
+                return Err(e);
+            }
+        };
+        // El recorte de silencio desplaza el audio; devolvemos timestamps del archivo original
+        for segment in &mut transcription.segments {
+            segment.from_ms += offset_ms;
+            segment.to_ms += offset_ms;
+        }
+
+        if let Ok(json) = serde_json::to_string(&transcription.segments) {
+            log::debug!("structured output ({} segmentos): {}", transcription.segments.len(), json);
+            (self.emit)("transcript_structured", &json, None);
+        }
+
+        let elapsed = total.elapsed().as_secs();
+        (self.emit)("process", &format!("Proceso completado en {:?} segundos", elapsed), None);
+        Ok(transcription)
+    }
+
+    /// Modelo de whisper y, si está activado y disponible, el de VAD.
+    fn local_models(&self) -> Result<(std::path::PathBuf, Option<std::path::PathBuf>), String> {
         (self.emit)("process", &format!("validando disponibilidad del modelo {}", self.whisper_model), None);
-        if let Err(e) = self.ensure_model(&*self.emit, &self.whisper_model){
-            println!("Failed to ensure model: {}", e);
-            (self.emit)("process", "hubo un error descargando el modelo".into(), None);
+        let model_path = match self.ensure_model(&self.whisper_model) {
+            Ok(path) => path,
+            Err(e) => {
+                log::warn!("ensure_model failed: {}", e);
+                (self.emit)("process", &format!("hubo un error preparando el modelo: {}", e), None);
+                return Err(format!("failed to ensure model: {}", e));
+            }
+        };
+
+        let vad_path = match self.ensure_vad_model() {
+            _ if !self.settings.vad => None,
+            Ok(path) => Some(path),
+            Err(e) => {
+                log::warn!("VAD model not available, proceeding without VAD: {}", e);
+                (self.emit)("process", "VAD no disponible, continuando sin filtro de voz", None);
+                None
+            }
+        };
+        Ok((model_path, vad_path))
+    }
+
+    pub fn get_model_path(&self, name: &str) -> std::path::PathBuf {
+        crate::utils::models_base_dir().join(name)
+    }
+
+    pub fn streaming_download(
+        &self,
+        whisper_model: &str,
+        model_url: &str,
+        model_path: std::path::PathBuf
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        (self.emit)("process_download_assets", &format!("aprovisionando modelo de IA localmente {}", whisper_model), None);
+        crate::download_manager::download(&*self.emit, whisper_model, model_url, &model_path)?;
+        (self.emit)("process_download_assets", "Modelo descargado", Some(100));
+        Ok(())
+    }
+
+    pub fn ensure_model(&self, whisper_model: &str) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+        let spec = whisper_models::resolve(whisper_model);
+        let model_path = whisper_models::local_path(&spec);
+        log::info!("ensure_model spec={:?} path={}", spec, model_path.display());
+        match &spec {
+            whisper_models::WhisperModelSpec::Local(path) if !path.exists() => {
+                return Err(format!("No se encontró el modelo {}", path.display()).into());
+            }
+            whisper_models::WhisperModelSpec::HuggingFace { repo, file } if !model_path.exists() => {
+                whisper_models::check_download(repo, file)?;
+                if let Some(parent) = model_path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                self.streaming_download(file, &whisper_models::download_url(repo, file), model_path.clone())?;
+            }
+            _ => {}
+        }
+
+        if let Err(e) = whisper_models::read_header(&model_path) {
+            // Una descarga que no es un modelo (p. ej. una página de error) no debe quedarse en caché
+            if matches!(spec, whisper_models::WhisperModelSpec::HuggingFace { .. }) {
+                let _ = std::fs::remove_file(&model_path);
+            }
+            return Err(e.into());
+        }
+        Ok(model_path)
+    }
+
+    pub fn ensure_vad_model(&self) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+        let vad_path = self.get_model_path(VAD_MODEL_NAME);
+        if !vad_path.exists() {
+            // Sin red (o sin el archivo en el espejo) se sigue sin VAD en vez de pasar una ruta inexistente
+            self.streaming_download(VAD_MODEL_NAME, VAD_MODEL_URL, vad_path.clone())?;
+        }
+        Ok(vad_path)
+    }
+
+    pub fn ensure_default_models(&self) -> String {
+        log::info!("ensure_default_models() start — file={} model={}", self.file_path, self.whisper_model);
+        if let Err(e) = self.ensure_model(&self.whisper_model) {
+            log::warn!("ensure_model failed: {}", e);
+            (self.emit)("process", "hubo un error descargando el modelo", None);
             return format!("failed to ensure model: {}", e);
         }
 
-        let vad_path = match self.ensure_vad_model(&*self.emit) {
+        let _vad_path = match self.ensure_vad_model() {
             Ok(path) => Some(path),
             Err(e) => {
-                println!("VAD model not available, proceeding without VAD: {}", e);
+                log::warn!("VAD model not available, proceeding without VAD: {}", e);
                 (self.emit)("process", "VAD no disponible, continuando sin filtro de voz", None);
                 None
             }
         };
+        (self.emit)("process", "", None);
 
-        let total = Instant::now();
-        let audio = audio_decoder::decode(&self.file_path)
-            .unwrap_or_else(|e| {
-                eprintln!("Error decodificando {}: {}", self.file_path, e);
-                panic!("Error decoding audio: {}", e);
-            });
-    
-        println!("Sample rate: {}", audio.sample_rate);
-        println!("Samples: {}", audio.samples.len());
-    
-        // (self.emit)("process", &format!("sample rate {}", audio.sample_rate), None);
-        // (self.emit)("process", &format!("samples {}", audio.samples.len()), None);
-        (self.emit)("process", &format!("preparando audio  {} HZ y {} muestras", audio.sample_rate, audio.samples.len()), None);
-        let clean = self.clean_audio(audio.samples);
-        let resampled = self.resample(&clean, audio.sample_rate, 16000);
-    
-        (self.emit)("process", "iniciando transcripción".into(), None);
-        let text = self.transcribe(&resampled, vad_path.as_deref());
-    
-        let elapsed = total.elapsed().as_secs();
-        (self.emit)("process", &format!("Proceso completado en {:?} segundos", elapsed), None);
-        text
+        String::from("Success")
     }
-    
-    pub fn clean_audio(&self, samples: Vec<f32>) -> Vec<f32>{
-        let mut denoiser = DenoiseState::new();
-        let mut clean: Vec<f32> = Vec::new();
-        let chunks: Vec<_> = samples.chunks(480).collect();
-    
-        let pb = ProgressBar::new(chunks.len() as u64);
-        pb.set_style(ProgressStyle::default_bar()
-            .template("{spinner:.green} [{bar:40.cyan/blue}] {pos}/{len} ({eta})")
-            .unwrap()
-            .progress_chars("#>-"));
-    
-        for chunk in chunks {
-            let mut input = [0.0f32; 480];
-            let mut output = [0.0f32; 480];
-    
-            for (i, &sample) in chunk.iter().enumerate(){
-                input[i] = sample;
-            }
-            denoiser.process_frame(&mut output, &input);
-            clean.extend_from_slice(&output[..chunk.len()]);
-            pb.inc(1);
+
+    /// Decodifica a WAV mono en `dest_dir` aplicando las etapas de preprocesamiento activas.
+    /// Devuelve la ruta y los milisegundos recortados al inicio.
+    pub fn prepare_wav(&self, file_path: &str, dest_dir: &Path) -> Result<(std::path::PathBuf, u64), Box<dyn std::error::Error>> {
+        let audio = audio_decoder::decode(file_path)?;
+        let audio = if self.preprocess.any() {
+            (self.emit)("process", "preprocesando audio", None);
+            preprocess::apply(audio.samples, audio.sample_rate, &self.preprocess)?
+        } else {
+            preprocess::PreprocessedAudio { samples: audio.samples, sample_rate: audio.sample_rate, offset_ms: 0 }
+        };
+        let temp_path = dest_dir.join("input.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: audio.sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let mut writer = hound::WavWriter::create(&temp_path, spec)?;
+        for sample in &audio.samples {
+            writer.write_sample(*sample)?;
         }
-    
-        clean
+        writer.finalize()?;
+        Ok((temp_path, audio.offset_ms))
+    }
+}
+
+/// Respaldo local del backend remoto. Modelos y binario se preparan al usarlo, no antes:
+/// quien transcribe en remoto suele hacerlo justamente para no descargar un modelo grande.
+struct LocalFallback<'a> {
+    processor: &'a AudioProcessor,
+    local: std::cell::OnceCell<Box<dyn TranscriptionBackend>>,
+}
+
+impl TranscriptionBackend for LocalFallback<'_> {
+    fn supports_format(&self, ext: &str) -> bool {
+        ["wav", "mp3", "flac"].contains(&ext)
+    }
+
+    fn transcribe(&self, request: &TranscriptionRequest) -> Result<TranscriptionResult, String> {
+        let (model_path, vad_path) = self.processor.local_models()?;
+        let request = TranscriptionRequest {
+            model_path: &model_path,
+            vad_model_path: vad_path.as_deref(),
+            ..*request
+        };
+        let local = match self.local.get() {
+            Some(local) => local,
+            None => {
+                let local = self.processor.local_backend()?;
+                self.local.get_or_init(|| local)
+            }
+        };
+        local.transcribe(&request)
     }
-    
-    pub fn get_model_path(&self, whisper_model: &str) -> std::path::PathBuf {
-         std::env::current_exe()
-             .ok()
-             .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-             .unwrap_or_default()
-             .join(whisper_model)
-     }
-   
-     pub fn ensure_model(&self, emit: &dyn Fn(&str, &str,Option<u32>), whisper_model: &str) -> Result<(), Box<dyn std::error::Error>> {
-         let model_path = self.get_model_path(whisper_model);
-         if !model_path.exists() {
-             let model_url = format!(
-                 "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/{}",
-                 whisper_model
-             );
-             emit("process", &format!("aprovisionando modelo de IA localmente {}", whisper_model), None);
-             let mut response = ureq::get(&model_url).call()?.into_reader();
-             let mut file = File::create(&model_path)?;
-             std::io::copy(&mut response, &mut file)?;
-         }
-         Ok(())
-     }
-
-     pub fn ensure_vad_model(&self, emit: &dyn Fn(&str, &str, Option<u32>)) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
-         let vad_path = self.get_model_path(VAD_MODEL_NAME);
-         if !vad_path.exists() {
-             emit("process", "Descargando modelo VAD (solo una vez, ~885KB)", None);
-             let mut response = ureq::get(VAD_MODEL_URL).call()?.into_reader();
-             let mut file = File::create(&vad_path)?;
-             std::io::copy(&mut response, &mut file)?;
-         }
-         Ok(vad_path)
-     }
-
-     pub fn resample(&self, samples: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
-         let params = SincInterpolationParameters {
-             sinc_len: 256,
-             f_cutoff: 0.95,
-             interpolation: SincInterpolationType::Linear,
-             oversampling_factor: 256,
-             window: WindowFunction::BlackmanHarris2,
-         };
-     
-         let mut resampler = SincFixedIn::<f32>::new(
-             to_rate as f64 / from_rate as f64,
-             2.0,
-             params,
-             samples.len(),
-             1,
-         ).unwrap();
-     
-         let waves_in = vec![samples.to_vec()];
-         let mut waves_out = resampler.process(&waves_in, None).unwrap();
-     
-         waves_out.remove(0)
-     }
-     
-     pub fn transcribe(&self, samples: &[f32], vad_model_path: Option<&std::path::Path>) -> String {
-         let model_path = self.get_model_path(&self.whisper_model);
-         let mut ctx_params = WhisperContextParameters::default();
-         ctx_params.use_gpu(true);
-         let ctx = WhisperContext::new_with_params(
-             model_path.to_str().unwrap(),
-             ctx_params
-         ).expect("Failed to load the model");
-
-         let mut state = ctx.create_state().expect("Could not create state");
-
-         // Procesar en chunks de 30s independientes.
-         // Cada chunk tiene contexto limpio: un loop en el himno no contamina el debate siguiente.
-         const CHUNK_SAMPLES: usize = 30 * 16000;
-         let chunks: Vec<&[f32]> = samples.chunks(CHUNK_SAMPLES).collect();
-         let total_chunks = chunks.len();
-         let mut full_text = String::new();
-
-         for (idx, chunk) in chunks.iter().enumerate() {
-             // FullParams es consumido por state.full(), se reconstruye por chunk
-             let mut params = FullParams::new(SamplingStrategy::BeamSearch {
-                 beam_size: 5,
-                 patience: 0.0,
-             });
-
-             params.set_language(Some("es"));
-             params.set_print_special(false);
-             params.set_print_realtime(false);
-             params.set_print_progress(false);
-             params.set_initial_prompt(
-                 "Transcripción profesional de audio. Contenido formal, sin publicidad, \
-                  sin menciones a redes sociales ni suscripciones."
-             );
-             params.set_temperature(0.0);
-             params.set_no_context(true);
-             params.set_suppress_blank(true);
-             params.set_suppress_nst(true);
-             params.set_no_speech_thold(0.2);
-             params.set_entropy_thold(2.0);
-             params.set_logprob_thold(-0.5);
-             params.set_max_len(50);
-
-             if let Some(vad_path) = vad_model_path {
-                 params.set_vad_model_path(Some(vad_path.to_str().unwrap()));
-                 let mut vad_params = WhisperVadParams::new();
-                 vad_params.set_threshold(0.9);
-                 vad_params.set_min_speech_duration(300);
-                 vad_params.set_min_silence_duration(100);
-                 vad_params.set_speech_pad(30);
-                 params.set_vad_params(vad_params);
-                 params.enable_vad(true);
-             }
-
-             let emit_progress = (self.emit).clone();
-             params.set_progress_callback_safe(move |progress: i32| {
-                 let overall = ((idx as f32 + progress as f32 / 100.0) / total_chunks as f32 * 100.0) as u32;
-                 emit_progress("process", "transcribiendo", Some(overall));
-             });
-
-             let emit_segment = (self.emit).clone();
-             params.set_segment_callback_safe(move |data: whisper_rs::SegmentCallbackData| {
-                 emit_segment("transcript_segment", &data.text, Some(data.segment as u32));
-             });
-
-             (self.emit)("process", &format!("transcribiendo segmento {}/{}", idx + 1, total_chunks), None);
-             state.full(params, chunk).expect("Could not transcribe chunk");
-
-             let mut chunk_text = String::new();
-             for segment in state.as_iter() {
-                 chunk_text.push_str(&segment.to_string());
-                 chunk_text.push(' ');
-             }
-             let chunk_text = chunk_text.trim().to_string();
-
-             if has_transcription_loop(&chunk_text) {
-                 (self.emit)("process", &format!("segmento {}/{}: audio no transcribible omitido", idx + 1, total_chunks), None);
-                 // No añadimos el segmento musical al transcript
-             } else if !chunk_text.is_empty() {
-                 if !full_text.is_empty() {
-                     full_text.push(' ');
-                 }
-                 full_text.push_str(&chunk_text);
-             }
-         }
-
-         full_text.trim().to_string()
-     }
 }
 
-/// Detecta si el texto de un chunk es un loop de Whisper.
-/// Un loop se define como la misma ventana de 5 palabras repetida 5+ veces consecutivas.
+/// Detecta si el texto es un loop de Whisper.
+/// Un loop: misma ventana de 5 palabras repetida 5+ veces consecutivas.
 /// El habla humana natural no repite frases exactas más de 3-4 veces seguidas.
 fn has_transcription_loop(text: &str) -> bool {
     let words: Vec<&str> = text.split_whitespace().collect();
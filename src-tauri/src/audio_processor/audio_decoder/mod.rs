@@ -31,6 +31,38 @@ pub fn decode(path: &str) -> Result<AudioData, Box<dyn std::error::Error>> {
     }
 }
 
+/// Duración en segundos. Lee sólo la cabecera cuando el contenedor declara el número de
+/// frames; si no (Opus, algunos streams), decodifica el archivo completo.
+pub fn duration_secs(path: &str) -> Result<f64, Box<dyn std::error::Error>> {
+    if let Some(secs) = probe_duration(path) {
+        return Ok(secs);
+    }
+    let data = decode(path)?;
+    Ok(data.samples.len() as f64 / data.sample_rate.max(1) as f64)
+}
+
+fn probe_duration(path: &str) -> Option<f64> {
+    let file = File::open(path).ok()?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(ext) = Path::new(path).extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(&hint, mss, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+    let params = &probed.format.default_track()?.codec_params;
+    let n_frames = params.n_frames?;
+    match (params.time_base, params.sample_rate) {
+        (Some(time_base), _) => {
+            let time = time_base.calc_time(n_frames);
+            Some(time.seconds as f64 + time.frac)
+        }
+        (None, Some(rate)) if rate > 0 => Some(n_frames as f64 / rate as f64),
+        _ => None,
+    }
+}
+
 fn decode_symphonia(path: &str) -> Result<AudioData, Box<dyn std::error::Error>> {
     let file = File::open(path)?;
     let mss = MediaSourceStream::new(Box::new(file), Default::default());
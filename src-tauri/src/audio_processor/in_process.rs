@@ -0,0 +1,132 @@
+use whisper_rs::{WhisperContext, WhisperContextParameters, FullParams, SamplingStrategy, WhisperVadParams};
+
+use super::{
+    audio_decoder, has_transcription_loop, preprocess, EmitType, TranscriptSegment, TranscriptionBackend,
+    TranscriptionRequest, TranscriptionResult,
+};
+use super::preprocess::WHISPER_SAMPLE_RATE;
+
+/// Backend que enlaza whisper.cpp vía whisper-rs. No depende de binarios externos.
+pub struct InProcessBackend {
+    emit: EmitType,
+}
+
+impl InProcessBackend {
+    pub fn new(emit: EmitType) -> Self {
+        whisper_rs::install_logging_hooks();
+        InProcessBackend { emit }
+    }
+}
+
+impl TranscriptionBackend for InProcessBackend {
+    /// Decodificamos con audio_decoder, así que cualquier formato soportado por él vale.
+    fn supports_format(&self, _ext: &str) -> bool {
+        true
+    }
+
+    fn transcribe(&self, request: &TranscriptionRequest) -> Result<TranscriptionResult, String> {
+        let audio = audio_decoder::decode(request.audio_path)
+            .map_err(|e| format!("Error decodificando {}: {}", request.audio_path, e))?;
+        (self.emit)("process", &format!("preparando audio  {} HZ y {} muestras", audio.sample_rate, audio.samples.len()), None);
+        let samples = if request.denoised {
+            preprocess::resample(&audio.samples, audio.sample_rate, WHISPER_SAMPLE_RATE)?
+        } else {
+            (self.emit)("process", "reduciendo ruido", None);
+            preprocess::clean_audio(&audio.samples, audio.sample_rate)?
+        };
+
+        let mut ctx_params = WhisperContextParameters::default();
+        ctx_params.use_gpu(true);
+        let ctx = WhisperContext::new_with_params(request.model_path, ctx_params)
+            .map_err(|e| format!("Error cargando el modelo {}: {}", request.model_path.display(), e))?;
+
+        let mut state = ctx.create_state()
+            .map_err(|e| format!("Error creando estado de whisper: {}", e))?;
+
+        // Procesar en chunks de 30s independientes.
+        // Cada chunk tiene contexto limpio: un loop en el himno no contamina el debate siguiente.
+        const CHUNK_SAMPLES: usize = 30 * WHISPER_SAMPLE_RATE as usize;
+        let chunks: Vec<&[f32]> = samples.chunks(CHUNK_SAMPLES).collect();
+        let total_chunks = chunks.len();
+        let mut full_text = String::new();
+        let mut segments: Vec<TranscriptSegment> = Vec::new();
+        let mut segment_idx: u32 = 0;
+
+        for (idx, chunk) in chunks.iter().enumerate() {
+            // FullParams es consumido por state.full(), se reconstruye por chunk
+            let mut params = FullParams::new(SamplingStrategy::BeamSearch {
+                beam_size: request.beam_size as i32,
+                patience: 0.0,
+            });
+
+            params.set_language(Some(request.language));
+            params.set_n_threads(request.threads as i32);
+            params.set_print_special(false);
+            params.set_print_realtime(false);
+            params.set_print_progress(false);
+            params.set_initial_prompt(request.initial_prompt);
+            params.set_temperature(0.0);
+            params.set_no_context(true);
+            params.set_suppress_blank(true);
+            params.set_suppress_nst(true);
+            params.set_no_speech_thold(0.2);
+            params.set_entropy_thold(2.0);
+            params.set_logprob_thold(-0.5);
+            params.set_max_len(50);
+
+            if let Some(vad_path) = request.vad_model_path {
+                params.set_vad_model_path(vad_path.to_str());
+                let mut vad_params = WhisperVadParams::new();
+                vad_params.set_threshold(0.9);
+                vad_params.set_min_speech_duration(300);
+                vad_params.set_min_silence_duration(100);
+                vad_params.set_speech_pad(30);
+                params.set_vad_params(vad_params);
+                params.enable_vad(true);
+            }
+
+            let emit_progress = (self.emit).clone();
+            params.set_progress_callback_safe(move |progress: i32| {
+                let overall = ((idx as f32 + progress as f32 / 100.0) / total_chunks as f32 * 100.0) as u32;
+                emit_progress("process", "transcribiendo", Some(overall));
+            });
+
+            (self.emit)("process", &format!("transcribiendo segmento {}/{}", idx + 1, total_chunks), None);
+            state.full(params, chunk)
+                .map_err(|e| format!("Error transcribiendo segmento {}/{}: {}", idx + 1, total_chunks, e))?;
+
+            // Timestamps de whisper-rs en centésimas de segundo, relativos al chunk
+            let chunk_offset_ms = (idx * 30_000) as u64;
+            let mut chunk_segments: Vec<TranscriptSegment> = Vec::new();
+            for segment in state.as_iter() {
+                let text = segment.to_string().trim().to_string();
+                if text.is_empty() {
+                    continue;
+                }
+                chunk_segments.push(TranscriptSegment {
+                    from_ms: chunk_offset_ms + segment.start_timestamp().max(0) as u64 * 10,
+                    to_ms: chunk_offset_ms + segment.end_timestamp().max(0) as u64 * 10,
+                    text,
+                });
+            }
+            let chunk_text = chunk_segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" ");
+
+            if has_transcription_loop(&chunk_text) {
+                (self.emit)("process", &format!("segmento {}/{}: audio no transcribible omitido", idx + 1, total_chunks), None);
+                // No añadimos el segmento musical al transcript
+                continue;
+            }
+            for segment in chunk_segments {
+                (self.emit)("transcript_segment", &segment.text, Some(segment_idx));
+                segment_idx += 1;
+                if !full_text.is_empty() {
+                    full_text.push(' ');
+                }
+                full_text.push_str(&segment.text);
+                segments.push(segment);
+            }
+        }
+
+        Ok(TranscriptionResult { text: full_text.trim().to_string(), segments })
+    }
+}
@@ -0,0 +1,403 @@
+use nnnoiseless::DenoiseState;
+use rubato::{Resampler, SincFixedIn, SincInterpolationType, SincInterpolationParameters, WindowFunction};
+use serde::{Deserialize, Serialize};
+
+/// RNNoise sólo funciona a 48 kHz con frames de 480 muestras.
+pub const DENOISE_SAMPLE_RATE: u32 = 48000;
+/// Frecuencia de muestreo con la que trabaja whisper.
+pub const WHISPER_SAMPLE_RATE: u32 = 16000;
+/// Objetivo de sonoridad integrada según EBU R128.
+const TARGET_LUFS: f64 = -23.0;
+/// Techo de pico tras normalizar, para no saturar al subir la ganancia.
+const PEAK_CEILING: f32 = 0.98;
+const HIGH_PASS_CUTOFF_HZ: f64 = 80.0;
+const SILENCE_THRESHOLD_DBFS: f32 = -45.0;
+const SILENCE_WINDOW_MS: u32 = 20;
+const SILENCE_PAD_MS: u32 = 200;
+
+/// Etapas opcionales de preprocesamiento. Todas desactivadas por defecto.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PreprocessOptions {
+    pub denoise: bool,
+    pub normalize: bool,
+    pub high_pass: bool,
+    pub trim_silence: bool,
+}
+
+impl PreprocessOptions {
+    pub fn any(&self) -> bool {
+        self.denoise || self.normalize || self.high_pass || self.trim_silence
+    }
+}
+
+pub struct PreprocessedAudio {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    /// Milisegundos recortados al inicio; hay que sumarlos a los timestamps de whisper.
+    pub offset_ms: u64,
+}
+
+/// Aplica las etapas activas en orden: high-pass → denoise → recorte de silencio → normalización.
+/// La normalización va al final para medir la sonoridad sólo sobre el audio que llega a whisper.
+pub fn apply(samples: Vec<f32>, sample_rate: u32, options: &PreprocessOptions) -> Result<PreprocessedAudio, String> {
+    let mut samples = samples;
+    let mut sample_rate = sample_rate;
+    let mut offset_ms = 0;
+
+    if options.high_pass {
+        high_pass(&mut samples, sample_rate, HIGH_PASS_CUTOFF_HZ);
+    }
+    if options.denoise {
+        samples = denoise(&samples, sample_rate)?;
+        sample_rate = DENOISE_SAMPLE_RATE;
+    }
+    if options.trim_silence {
+        let (start, end) = speech_bounds(&samples, sample_rate);
+        offset_ms = start as u64 * 1000 / sample_rate as u64;
+        samples = samples[start..end].to_vec();
+    }
+    if options.normalize {
+        normalize_loudness(&mut samples, sample_rate, TARGET_LUFS);
+    }
+
+    Ok(PreprocessedAudio { samples, sample_rate, offset_ms })
+}
+
+pub fn resample(samples: &[f32], from_rate: u32, to_rate: u32) -> Result<Vec<f32>, String> {
+    if from_rate == to_rate || samples.is_empty() {
+        return Ok(samples.to_vec());
+    }
+    if from_rate == 0 || to_rate == 0 {
+        return Err(format!("sample rate inválido: {} Hz → {} Hz", from_rate, to_rate));
+    }
+    let params = SincInterpolationParameters {
+        sinc_len: 256,
+        f_cutoff: 0.95,
+        interpolation: SincInterpolationType::Linear,
+        oversampling_factor: 256,
+        window: WindowFunction::BlackmanHarris2,
+    };
+
+    let mut resampler = SincFixedIn::<f32>::new(
+        to_rate as f64 / from_rate as f64,
+        2.0,
+        params,
+        samples.len(),
+        1,
+    ).map_err(|e| format!("No se pudo remuestrear de {} Hz a {} Hz: {}", from_rate, to_rate, e))?;
+
+    let waves_in = vec![samples.to_vec()];
+    let mut waves_out = resampler
+        .process(&waves_in, None)
+        .map_err(|e| format!("Error remuestreando de {} Hz a {} Hz: {}", from_rate, to_rate, e))?;
+
+    Ok(waves_out.remove(0))
+}
+
+// ─── Denoise ─────────────────────────────────────────────────────────────────
+
+/// Reduce ruido con RNNoise. Devuelve el audio a 48 kHz.
+/// RNNoise espera muestras en rango i16, así que escalamos desde [-1, 1] y volvemos.
+pub fn denoise(samples: &[f32], sample_rate: u32) -> Result<Vec<f32>, String> {
+    let input = resample(samples, sample_rate, DENOISE_SAMPLE_RATE)?;
+    let mut denoiser = DenoiseState::new();
+    let mut clean: Vec<f32> = Vec::with_capacity(input.len());
+    let mut frame_in = [0.0f32; DenoiseState::FRAME_SIZE];
+    let mut frame_out = [0.0f32; DenoiseState::FRAME_SIZE];
+
+    for chunk in input.chunks(DenoiseState::FRAME_SIZE) {
+        frame_in.fill(0.0);
+        for (dst, &sample) in frame_in.iter_mut().zip(chunk) {
+            *dst = sample * i16::MAX as f32;
+        }
+        denoiser.process_frame(&mut frame_out, &frame_in);
+        clean.extend(frame_out[..chunk.len()].iter().map(|s| (s / i16::MAX as f32).clamp(-1.0, 1.0)));
+    }
+
+    Ok(clean)
+}
+
+/// Reduce ruido y deja el audio listo para whisper (16 kHz). Lo usa el motor en proceso.
+#[cfg_attr(not(feature = "in-process"), allow(dead_code))]
+pub fn clean_audio(samples: &[f32], sample_rate: u32) -> Result<Vec<f32>, String> {
+    let clean = denoise(samples, sample_rate)?;
+    resample(&clean, DENOISE_SAMPLE_RATE, WHISPER_SAMPLE_RATE)
+}
+
+// ─── Filters ─────────────────────────────────────────────────────────────────
+
+/// Biquad en forma directa I.
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl Biquad {
+    fn new(b: [f64; 3], a: [f64; 3]) -> Self {
+        Biquad {
+            b0: b[0] / a[0],
+            b1: b[1] / a[0],
+            b2: b[2] / a[0],
+            a1: a[1] / a[0],
+            a2: a[2] / a[0],
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    /// High-pass Butterworth de segundo orden (RBJ cookbook).
+    fn high_pass(sample_rate: u32, cutoff: f64) -> Self {
+        let w0 = 2.0 * std::f64::consts::PI * cutoff / sample_rate as f64;
+        let alpha = w0.sin() / (2.0 * std::f64::consts::FRAC_1_SQRT_2);
+        let cos = w0.cos();
+        Biquad::new(
+            [(1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0],
+            [1.0 + alpha, -2.0 * cos, 1.0 - alpha],
+        )
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+pub fn high_pass(samples: &mut [f32], sample_rate: u32, cutoff: f64) {
+    let mut filter = Biquad::high_pass(sample_rate, cutoff);
+    for sample in samples.iter_mut() {
+        *sample = filter.process(*sample as f64) as f32;
+    }
+}
+
+// ─── Loudness (EBU R128 / ITU-R BS.1770) ─────────────────────────────────────
+
+/// Filtro K-weighting de BS.1770, coeficientes recalculados para cualquier sample rate
+/// (misma derivación que libebur128).
+fn k_weighting(sample_rate: u32) -> (Biquad, Biquad) {
+    let fs = sample_rate as f64;
+
+    let f0 = 1681.974450955533;
+    let gain_db = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let shelf = Biquad::new(
+        [vh + vb * k / q + k * k, 2.0 * (k * k - vh), vh - vb * k / q + k * k],
+        [1.0 + k / q + k * k, 2.0 * (k * k - 1.0), 1.0 - k / q + k * k],
+    );
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (std::f64::consts::PI * f0 / fs).tan();
+    let rlb = Biquad::new(
+        [1.0, -2.0, 1.0],
+        [1.0 + k / q + k * k, 2.0 * (k * k - 1.0), 1.0 - k / q + k * k],
+    );
+
+    (shelf, rlb)
+}
+
+fn power_to_lufs(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+/// Sonoridad integrada en LUFS con bloques de 400 ms (solape 75%),
+/// gate absoluto a -70 LUFS y gate relativo a -10 LU.
+pub fn integrated_loudness(samples: &[f32], sample_rate: u32) -> Option<f64> {
+    let (mut shelf, mut rlb) = k_weighting(sample_rate);
+    let weighted: Vec<f64> = samples
+        .iter()
+        .map(|&s| rlb.process(shelf.process(s as f64)))
+        .collect();
+
+    let block = (sample_rate as usize * 400) / 1000;
+    let step = block / 4;
+    if block == 0 || weighted.len() < block {
+        return None;
+    }
+
+    let mut powers = Vec::new();
+    let mut start = 0;
+    while start + block <= weighted.len() {
+        let sum: f64 = weighted[start..start + block].iter().map(|x| x * x).sum();
+        powers.push(sum / block as f64);
+        start += step;
+    }
+
+    let above_absolute: Vec<f64> = powers
+        .into_iter()
+        .filter(|&p| p > 0.0 && power_to_lufs(p) > -70.0)
+        .collect();
+    if above_absolute.is_empty() {
+        return None;
+    }
+
+    let mean = above_absolute.iter().sum::<f64>() / above_absolute.len() as f64;
+    let relative_gate = power_to_lufs(mean) - 10.0;
+    let gated: Vec<f64> = above_absolute
+        .into_iter()
+        .filter(|&p| power_to_lufs(p) > relative_gate)
+        .collect();
+    if gated.is_empty() {
+        return None;
+    }
+
+    Some(power_to_lufs(gated.iter().sum::<f64>() / gated.len() as f64))
+}
+
+/// Ajusta la ganancia para llevar el audio a `target_lufs`, limitada por el pico.
+pub fn normalize_loudness(samples: &mut [f32], sample_rate: u32, target_lufs: f64) {
+    let Some(loudness) = integrated_loudness(samples, sample_rate) else {
+        return;
+    };
+    let mut gain = 10f64.powf((target_lufs - loudness) / 20.0) as f32;
+
+    let peak = samples.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+    if peak > 0.0 && peak * gain > PEAK_CEILING {
+        gain = PEAK_CEILING / peak;
+    }
+    log::info!("loudness {:.1} LUFS, gain {:.2}", loudness, gain);
+
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+}
+
+// ─── Silence trimming ────────────────────────────────────────────────────────
+
+/// Devuelve el rango [inicio, fin) que contiene voz, con un margen a cada lado.
+/// Si todo es silencio devuelve el audio completo para no entregar un WAV vacío.
+pub fn speech_bounds(samples: &[f32], sample_rate: u32) -> (usize, usize) {
+    let window = (sample_rate * SILENCE_WINDOW_MS / 1000).max(1) as usize;
+    let pad = (sample_rate * SILENCE_PAD_MS / 1000) as usize;
+    let threshold = 10f32.powf(SILENCE_THRESHOLD_DBFS / 20.0);
+
+    let is_voiced = |chunk: &[f32]| {
+        let rms = (chunk.iter().map(|s| s * s).sum::<f32>() / chunk.len() as f32).sqrt();
+        rms > threshold
+    };
+
+    let windows: Vec<&[f32]> = samples.chunks(window).collect();
+    let Some(first) = windows.iter().position(|&w| is_voiced(w)) else {
+        return (0, samples.len());
+    };
+    let last = windows.iter().rposition(|&w| is_voiced(w)).unwrap_or(first);
+
+    let start = (first * window).saturating_sub(pad);
+    let end = ((last + 1) * window + pad).min(samples.len());
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT_RATE: u32 = 44100;
+    const SECONDS: usize = 4;
+    /// Ráfagas de tono de 500 ms separadas por 500 ms de sólo ruido.
+    const BURST_MS: usize = 500;
+    /// Margen a cada lado de una ráfaga al medir, para no depender del retardo del filtro.
+    const MARGIN_MS: usize = 100;
+
+    /// Seno de 220 Hz en ráfagas más ruido blanco determinista.
+    fn noisy_sine(sample_rate: u32) -> Vec<f32> {
+        let mut seed: u32 = 0x1234_5678;
+        let mut noise = move || {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0
+        };
+        let burst = sample_rate as usize * BURST_MS / 1000;
+        (0..sample_rate as usize * SECONDS)
+            .map(|i| {
+                let tone = if (i / burst).is_multiple_of(2) {
+                    0.3 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / sample_rate as f32).sin()
+                } else {
+                    0.0
+                };
+                tone + 0.05 * noise()
+            })
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|s| s * s).sum::<f32>() / samples.len().max(1) as f32).sqrt()
+    }
+
+    /// Relación en dB entre el nivel dentro de las ráfagas y el de los tramos de sólo ruido.
+    fn snr_db(samples: &[f32], sample_rate: u32) -> f32 {
+        let burst = sample_rate as usize * BURST_MS / 1000;
+        let margin = sample_rate as usize * MARGIN_MS / 1000;
+        let (mut signal, mut noise) = (Vec::new(), Vec::new());
+        for (i, window) in samples.chunks(burst).enumerate() {
+            if window.len() < burst {
+                break;
+            }
+            let inner = &window[margin..burst - margin];
+            if i % 2 == 0 {
+                signal.extend_from_slice(inner);
+            } else {
+                noise.extend_from_slice(inner);
+            }
+        }
+        20.0 * (rms(&signal) / rms(&noise).max(f32::EPSILON)).log10()
+    }
+
+    fn assert_len_close(actual: usize, expected: usize) {
+        let tolerance = expected / 100;
+        assert!(
+            actual.abs_diff(expected) <= tolerance,
+            "longitud {} lejos de la esperada {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn denoise_improves_snr() {
+        let input = noisy_sine(INPUT_RATE);
+        let before = snr_db(&input, INPUT_RATE);
+        let output = denoise(&input, INPUT_RATE).unwrap();
+        let after = snr_db(&output, DENOISE_SAMPLE_RATE);
+        assert!(after > before + 6.0, "SNR {:.1} dB → {:.1} dB", before, after);
+    }
+
+    #[test]
+    fn denoise_preserves_length() {
+        let input = noisy_sine(INPUT_RATE);
+        let output = denoise(&input, INPUT_RATE).unwrap();
+        assert_len_close(output.len(), SECONDS * DENOISE_SAMPLE_RATE as usize);
+    }
+
+    #[test]
+    fn clean_audio_outputs_16khz_with_better_snr() {
+        let input = noisy_sine(INPUT_RATE);
+        let before = snr_db(&input, INPUT_RATE);
+        let output = clean_audio(&input, INPUT_RATE).unwrap();
+        assert_len_close(output.len(), SECONDS * WHISPER_SAMPLE_RATE as usize);
+        let after = snr_db(&output, WHISPER_SAMPLE_RATE);
+        assert!(after > before + 6.0, "SNR {:.1} dB → {:.1} dB", before, after);
+    }
+
+    #[test]
+    fn resample_rejects_zero_rate() {
+        assert!(resample(&[0.0; 480], 0, WHISPER_SAMPLE_RATE).is_err());
+        assert!(resample(&[], 0, WHISPER_SAMPLE_RATE).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,471 @@
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    audio_decoder, has_transcription_loop, preprocess, EmitType, TranscriptSegment, TranscriptionBackend,
+    TranscriptionRequest, TranscriptionResult,
+};
+
+const UPLOAD_SAMPLE_RATE: u32 = 16000;
+const CONNECT_TIMEOUT_SECS: u64 = 5;
+
+/// Dialecto HTTP del servidor remoto.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RemoteApi {
+    /// `whisper.cpp/examples/server`: POST {url}/inference
+    #[default]
+    WhisperCpp,
+    /// API compatible con OpenAI (faster-whisper-server, speaches, etc.): POST {url}/v1/audio/transcriptions
+    OpenAi,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RemoteConfig {
+    pub url: String,
+    pub api: RemoteApi,
+    /// Nombre del modelo a pedir al servidor (sólo OpenAI-compatible).
+    pub model: Option<String>,
+    pub api_key: Option<String>,
+    pub timeout_secs: u64,
+    /// Si el servidor falla, transcribir localmente con whisper-cli.
+    pub fallback_to_local: bool,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> Self {
+        RemoteConfig {
+            url: String::new(),
+            api: RemoteApi::default(),
+            model: None,
+            api_key: None,
+            timeout_secs: 900,
+            fallback_to_local: true,
+        }
+    }
+}
+
+impl RemoteConfig {
+    fn endpoint(&self) -> String {
+        let base = self.url.trim_end_matches('/');
+        match self.api {
+            RemoteApi::WhisperCpp => format!("{}/inference", base),
+            RemoteApi::OpenAi => format!("{}/v1/audio/transcriptions", base),
+        }
+    }
+}
+
+/// Respuesta `verbose_json`: mismo formato en whisper.cpp server y en la API de OpenAI.
+#[derive(Deserialize)]
+struct VerboseJson {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    segments: Vec<VerboseJsonSegment>,
+}
+#[derive(Deserialize)]
+struct VerboseJsonSegment {
+    start: f64,
+    end: f64,
+    text: String,
+}
+
+/// Backend que envía el audio a un servidor de transcripción en la red local.
+pub struct RemoteBackend<'a> {
+    emit: EmitType,
+    config: RemoteConfig,
+    fallback: Option<Box<dyn TranscriptionBackend + 'a>>,
+}
+
+impl<'a> RemoteBackend<'a> {
+    pub fn new(emit: EmitType, config: RemoteConfig, fallback: Option<Box<dyn TranscriptionBackend + 'a>>) -> Self {
+        RemoteBackend { emit, config, fallback }
+    }
+
+    fn transcribe_remote(&self, request: &TranscriptionRequest) -> Result<TranscriptionResult, String> {
+        if self.config.url.trim().is_empty() {
+            return Err("no hay URL configurada para el servidor remoto".into());
+        }
+
+        let wav = encode_upload_wav(request.audio_path)?;
+        let endpoint = self.config.endpoint();
+        log::info!("remote endpoint={} upload_bytes={}", endpoint, wav.len());
+        (self.emit)("process", &format!("enviando audio a {}", self.config.url), None);
+
+        let mut form = MultipartForm::new();
+        form.file("file", "audio.wav", "audio/wav", &wav);
+        form.text("response_format", "verbose_json");
+        // Sin idioma el servidor lo detecta
+        if request.language != "auto" {
+            form.text("language", request.language);
+        }
+        form.text("prompt", request.initial_prompt);
+        form.text("temperature", "0.0");
+        if self.config.api == RemoteApi::OpenAi {
+            form.text("model", self.config.model.as_deref().unwrap_or("whisper-1"));
+        }
+        let (content_type, body) = form.finish();
+
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+            .timeout(Duration::from_secs(self.config.timeout_secs))
+            .build();
+        let mut http = agent.post(&endpoint).set("Content-Type", &content_type);
+        if let Some(key) = self.config.api_key.as_deref().filter(|k| !k.is_empty()) {
+            http = http.set("Authorization", &format!("Bearer {}", key));
+        }
+
+        (self.emit)("process", "transcribiendo en servidor remoto", None);
+        let response = http.send_bytes(&body).map_err(|e| match e {
+            ureq::Error::Status(code, r) => {
+                let detail = r.into_string().unwrap_or_default();
+                format!("el servidor respondió {}: {}", code, detail.trim())
+            }
+            e => format!("no se pudo contactar al servidor: {}", e),
+        })?;
+        let body = response
+            .into_string()
+            .map_err(|e| format!("error leyendo respuesta del servidor: {}", e))?;
+        let parsed: VerboseJson = serde_json::from_str(&body)
+            .map_err(|e| format!("respuesta inválida del servidor: {}", e))?;
+
+        Ok(self.build_result(parsed))
+    }
+
+    fn build_result(&self, parsed: VerboseJson) -> TranscriptionResult {
+        let mut segments: Vec<TranscriptSegment> = parsed
+            .segments
+            .into_iter()
+            .filter_map(|s| {
+                let text = s.text.trim().to_string();
+                if text.is_empty() || has_transcription_loop(&text) {
+                    return None;
+                }
+                Some(TranscriptSegment {
+                    from_ms: (s.start.max(0.0) * 1000.0).round() as u64,
+                    to_ms: (s.end.max(0.0) * 1000.0).round() as u64,
+                    text,
+                })
+            })
+            .collect();
+
+        // Algunos servidores ignoran verbose_json y devuelven sólo el texto
+        if segments.is_empty() && !parsed.text.trim().is_empty() {
+            segments.push(TranscriptSegment { from_ms: 0, to_ms: 0, text: parsed.text.trim().to_string() });
+        }
+
+        for (idx, segment) in segments.iter().enumerate() {
+            (self.emit)("transcript_segment", &segment.text, Some(idx as u32));
+        }
+        let text = segments.iter().map(|s| s.text.as_str()).collect::<Vec<_>>().join(" ");
+        TranscriptionResult { text, segments }
+    }
+}
+
+impl TranscriptionBackend for RemoteBackend<'_> {
+    /// Re-codificamos siempre a WAV 16 kHz antes de subir, pero aceptamos sólo lo que
+    /// el backend local de respaldo también puede leer.
+    fn supports_format(&self, ext: &str) -> bool {
+        ["wav", "mp3", "flac"].contains(&ext)
+    }
+
+    fn transcribe(&self, request: &TranscriptionRequest) -> Result<TranscriptionResult, String> {
+        match self.transcribe_remote(request) {
+            Ok(result) => Ok(result),
+            Err(e) => {
+                log::warn!("remote transcription failed: {}", e);
+                let Some(fallback) = self.fallback.as_ref() else {
+                    return Err(format!("Error en transcripción remota: {}", e));
+                };
+                (self.emit)("process", &format!("servidor remoto no disponible ({}), transcribiendo localmente", e), None);
+                fallback.transcribe(request)
+            }
+        }
+    }
+}
+
+/// WAV mono PCM 16-bit a 16 kHz: lo que whisper usa internamente y ~4x menos que f32 a 32 kHz+.
+fn encode_upload_wav(audio_path: &str) -> Result<Vec<u8>, String> {
+    let audio = audio_decoder::decode(audio_path)
+        .map_err(|e| format!("Error decodificando {}: {}", audio_path, e))?;
+    let samples = preprocess::resample(&audio.samples, audio.sample_rate, UPLOAD_SAMPLE_RATE)?;
+
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: UPLOAD_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut cursor = std::io::Cursor::new(Vec::new());
+    let write = (|| -> Result<(), hound::Error> {
+        let mut writer = hound::WavWriter::new(&mut cursor, spec)?;
+        for sample in &samples {
+            writer.write_sample((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)?;
+        }
+        writer.finalize()
+    })();
+    write.map_err(|e| format!("Error codificando WAV: {}", e))?;
+    Ok(cursor.into_inner())
+}
+
+/// multipart/form-data mínimo; ureq 2 no lo trae incluido.
+struct MultipartForm {
+    boundary: String,
+    body: Vec<u8>,
+}
+
+impl MultipartForm {
+    fn new() -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        MultipartForm { boundary: format!("----beautifulstt{:x}", nanos), body: Vec::new() }
+    }
+
+    fn text(&mut self, name: &str, value: &str) {
+        self.body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"\r\n\r\n{}\r\n",
+                self.boundary, name, value
+            )
+            .as_bytes(),
+        );
+    }
+
+    fn file(&mut self, name: &str, filename: &str, content_type: &str, data: &[u8]) {
+        self.body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Disposition: form-data; name=\"{}\"; filename=\"{}\"\r\nContent-Type: {}\r\n\r\n",
+                self.boundary, name, filename, content_type
+            )
+            .as_bytes(),
+        );
+        self.body.extend_from_slice(data);
+        self.body.extend_from_slice(b"\r\n");
+    }
+
+    fn finish(mut self) -> (String, Vec<u8>) {
+        self.body.extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        (format!("multipart/form-data; boundary={}", self.boundary), self.body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::path::{Path, PathBuf};
+    use std::sync::mpsc;
+    use std::sync::Arc;
+
+    const VERBOSE_JSON: &str = r#"{
+        "text": " Hola mundo. Segunda frase.",
+        "segments": [
+            {"id": 0, "start": 0.0, "end": 1.5, "text": " Hola mundo."},
+            {"id": 1, "start": 1.5, "end": 3.25, "text": " Segunda frase."}
+        ]
+    }"#;
+
+    /// Petición recibida por el servidor falso.
+    struct Received {
+        request_line: String,
+        headers: Vec<String>,
+        body: String,
+    }
+
+    /// Servidor HTTP de una sola petición que responde `status` con `body`.
+    fn mock_server(status: u16, body: &'static str) -> (String, mpsc::Receiver<Received>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::channel();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut headers = Vec::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let line = line.trim_end().to_string();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                headers.push(line);
+            }
+            let mut body_bytes = vec![0; content_length];
+            reader.read_exact(&mut body_bytes).unwrap();
+
+            let mut stream = stream;
+            write!(
+                stream,
+                "HTTP/1.1 {} Mock\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            )
+            .unwrap();
+            let _ = tx.send(Received {
+                request_line: request_line.trim_end().to_string(),
+                headers,
+                body: String::from_utf8_lossy(&body_bytes).into_owned(),
+            });
+        });
+        (url, rx)
+    }
+
+    /// Respaldo local falso: devuelve siempre el mismo texto.
+    struct FakeLocal;
+
+    impl TranscriptionBackend for FakeLocal {
+        fn supports_format(&self, _ext: &str) -> bool {
+            true
+        }
+
+        fn transcribe(&self, _request: &TranscriptionRequest) -> Result<TranscriptionResult, String> {
+            Ok(TranscriptionResult {
+                text: "transcripción local".into(),
+                segments: vec![TranscriptSegment { from_ms: 0, to_ms: 1000, text: "transcripción local".into() }],
+            })
+        }
+    }
+
+    /// Un segundo de tono a 44.1 kHz en una carpeta propia del test.
+    fn test_wav(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("beautiful-stt-remote-test-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("input.wav");
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..44100 {
+            let sample = (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44100.0).sin() * 0.3;
+            writer.write_sample((sample * i16::MAX as f32) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+
+    fn transcribe(
+        config: RemoteConfig,
+        fallback: Option<Box<dyn TranscriptionBackend>>,
+        name: &str,
+    ) -> Result<TranscriptionResult, String> {
+        let audio = test_wav(name);
+        let emit: EmitType = Arc::new(|_: &str, _: &str, _: Option<u32>| {});
+        let backend = RemoteBackend::new(emit, config, fallback);
+        let request = TranscriptionRequest {
+            audio_path: audio.to_str().unwrap(),
+            model_path: Path::new("modelo-inexistente.bin"),
+            vad_model_path: None,
+            work_dir: audio.parent().unwrap(),
+            language: "es",
+            initial_prompt: "",
+            beam_size: 1,
+            threads: 1,
+            denoised: false,
+        };
+        let result = backend.transcribe(&request);
+        let _ = std::fs::remove_dir_all(audio.parent().unwrap());
+        result
+    }
+
+    fn config(url: String, api: RemoteApi, fallback_to_local: bool) -> RemoteConfig {
+        RemoteConfig { url, api, timeout_secs: 10, fallback_to_local, ..RemoteConfig::default() }
+    }
+
+    #[test]
+    fn whisper_cpp_verbose_json() {
+        let (url, received) = mock_server(200, VERBOSE_JSON);
+        let result = transcribe(config(url, RemoteApi::WhisperCpp, false), None, "whisper-cpp").unwrap();
+
+        assert_eq!(result.text, "Hola mundo. Segunda frase.");
+        assert_eq!(result.segments.len(), 2);
+        assert_eq!((result.segments[1].from_ms, result.segments[1].to_ms), (1500, 3250));
+
+        let request = received.recv().unwrap();
+        assert!(request.request_line.starts_with("POST /inference "), "{}", request.request_line);
+        assert!(request.body.contains("name=\"response_format\"\r\n\r\nverbose_json"));
+        assert!(request.body.contains("name=\"language\"\r\n\r\nes"));
+        assert!(!request.body.contains("name=\"model\""));
+    }
+
+    #[test]
+    fn openai_verbose_json() {
+        let (url, received) = mock_server(200, VERBOSE_JSON);
+        let mut config = config(url, RemoteApi::OpenAi, false);
+        config.model = Some("large-v3".into());
+        config.api_key = Some("secreto".into());
+        let result = transcribe(config, None, "openai").unwrap();
+
+        assert_eq!(result.segments.len(), 2);
+        assert_eq!(result.segments[0].text, "Hola mundo.");
+
+        let request = received.recv().unwrap();
+        assert!(request.request_line.starts_with("POST /v1/audio/transcriptions "), "{}", request.request_line);
+        assert!(request.headers.iter().any(|h| h == "Authorization: Bearer secreto"));
+        assert!(request.body.contains("name=\"model\"\r\n\r\nlarge-v3"));
+    }
+
+    #[test]
+    fn plain_text_response_becomes_one_segment() {
+        let (url, _received) = mock_server(200, r#"{"text": " Sólo texto. "}"#);
+        let result = transcribe(config(url, RemoteApi::OpenAi, false), None, "plain-text").unwrap();
+        assert_eq!(result.text, "Sólo texto.");
+        assert_eq!(result.segments.len(), 1);
+    }
+
+    #[test]
+    fn server_error_without_fallback() {
+        let (url, _received) = mock_server(500, r#"{"error": "model not loaded"}"#);
+        let error = transcribe(config(url, RemoteApi::WhisperCpp, false), None, "error").unwrap_err();
+        assert!(error.contains("500"), "{}", error);
+        assert!(error.contains("model not loaded"), "{}", error);
+    }
+
+    #[test]
+    fn invalid_json_without_fallback() {
+        let (url, _received) = mock_server(200, "<html>proxy</html>");
+        let error = transcribe(config(url, RemoteApi::WhisperCpp, false), None, "invalid").unwrap_err();
+        assert!(error.contains("respuesta inválida"), "{}", error);
+    }
+
+    #[test]
+    fn server_error_falls_back_to_local() {
+        let (url, _received) = mock_server(503, "busy");
+        let result = transcribe(
+            config(url, RemoteApi::WhisperCpp, true),
+            Some(Box::new(FakeLocal)),
+            "fallback-error",
+        )
+        .unwrap();
+        assert_eq!(result.text, "transcripción local");
+    }
+
+    #[test]
+    fn unreachable_server_falls_back_to_local() {
+        // Puerto recién liberado: la conexión se rechaza
+        let url = {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            format!("http://{}", listener.local_addr().unwrap())
+        };
+        let result = transcribe(
+            config(url, RemoteApi::OpenAi, true),
+            Some(Box::new(FakeLocal)),
+            "fallback-unreachable",
+        )
+        .unwrap();
+        assert_eq!(result.text, "transcripción local");
+    }
+}
@@ -0,0 +1,224 @@
+use std::io::{BufRead, BufReader};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use serde::Deserialize;
+
+use super::{
+    has_transcription_loop, EmitType, TranscriptSegment, TranscriptionBackend, TranscriptionRequest,
+    TranscriptionResult,
+};
+
+#[derive(Deserialize)]
+struct WhisperJson {
+    transcription: Vec<WhisperJsonSegment>,
+}
+#[derive(Deserialize)]
+struct WhisperJsonSegment {
+    offsets: WhisperOffsets,
+    text: String,
+}
+#[derive(Deserialize)]
+struct WhisperOffsets {
+    from: u64,
+    to: u64,
+}
+
+/// Backend que ejecuta el binario whisper-cli y lee su salida JSON.
+pub struct WhisperCliBackend {
+    emit: EmitType,
+    whisper_bin: PathBuf,
+}
+
+impl WhisperCliBackend {
+    pub fn new(emit: EmitType, whisper_bin: PathBuf) -> Self {
+        WhisperCliBackend { emit, whisper_bin }
+    }
+}
+
+impl TranscriptionBackend for WhisperCliBackend {
+    /// Whisper-cli soporta nativamente: wav, mp3, flac
+    fn supports_format(&self, ext: &str) -> bool {
+        ["wav", "mp3", "flac"].contains(&ext)
+    }
+
+    fn transcribe(&self, request: &TranscriptionRequest) -> Result<TranscriptionResult, String> {
+        let whisper_bin = &self.whisper_bin;
+        let model_path = request.model_path;
+        let file_path = request.audio_path;
+
+        let json_base = request.work_dir.join("whisper_out");
+        let json_path = json_base.with_extension("json");
+        log::debug!("whisper_bin={} exists={}", whisper_bin.display(), whisper_bin.exists());
+        log::debug!("model_path={} exists={}", model_path.display(), model_path.exists());
+
+        let mut cmd = Command::new(whisper_bin);
+
+        // En producción macOS, los dylibs están en ../Frameworks/ relativo al exe.
+        // Aunque el rpath está patched, forzamos DYLD_LIBRARY_PATH por robustez.
+        #[cfg(all(target_os = "macos", not(debug_assertions)))]
+        {
+            let frameworks_dir = std::env::current_exe()
+                .ok()
+                .and_then(|p| p.parent().and_then(|p| p.parent()).map(|p| p.join("Frameworks")))
+                .unwrap_or_default();
+            if frameworks_dir.exists() {
+                log::debug!("setting DYLD_LIBRARY_PATH={}", frameworks_dir.display());
+                cmd.env("DYLD_LIBRARY_PATH", &frameworks_dir);
+            }
+        }
+        log::debug!("threads={} beam_size={} language={}", request.threads, request.beam_size, request.language);
+        cmd.arg("-m").arg(model_path.to_str().unwrap())
+           .arg("-f").arg(file_path)
+           .arg("-l").arg(request.language)
+           .arg("-bs").arg(request.beam_size.to_string())
+           .arg("-t").arg(request.threads.to_string())
+           .arg("--prompt")
+           .arg(request.initial_prompt)
+           .arg("-et").arg("2.4")          // entropy threshold
+           .arg("-sns")                    // suppress non-speech tokens
+           .arg("-pp")                     // print-progress: emite % al stderr
+           .arg("-oj")                     // output JSON estructurado (segmentos + offsets ms)
+           .arg("-of").arg(&json_base)     // ruta base del/los archivo(s) de salida
+           .stdout(Stdio::piped())
+           .stderr(Stdio::piped());
+
+        if let Some(vad_path) = request.vad_model_path {
+            cmd.arg("--vad")
+               .arg("-vm").arg(vad_path.to_str().unwrap())
+               .arg("-vt").arg("0.7")      // vad threshold (default)
+               .arg("-vspd").arg("300")    // min speech duration ms
+               .arg("-vsd").arg("500")     // min silence duration ms
+               .arg("-vp").arg("30");      // speech pad ms
+        }
+
+        // Debug: mostrar comando exacto
+        let cmd_str = format!("{} -m {} -f {}", whisper_bin.display(), model_path.display(), file_path);
+        (self.emit)("process", &format!("cmd: {}", cmd_str), None);
+
+        let mut child = match cmd.spawn() {
+            Ok(c) => c,
+            Err(e) => {
+                return Err(format!("Error al ejecutar whisper-cli ({}): {}", whisper_bin.display(), e));
+            }
+        };
+
+        // Thread separado para leer progreso de stderr sin bloquear stdout
+        let stderr = child.stderr.take().expect("Failed to capture stderr");
+        let emit_progress = self.emit.clone();
+        let stderr_thread = thread::spawn(move || {
+            let reader = BufReader::new(stderr);
+            let mut lines_collected: Vec<String> = Vec::new();
+            for line in reader.lines() {
+                let Ok(line) = line else { continue };
+                if let Some(pct) = parse_progress_line(&line) {
+                    emit_progress("process", "transcribiendo", Some(pct));
+                } else if !line.trim().is_empty() {
+                    log::debug!(target: crate::logging::WHISPER_CLI, "{}", line);
+                    lines_collected.push(line);
+                }
+            }
+            lines_collected
+        });
+
+        let stdout = child.stdout.take().expect("Failed to capture stdout");
+        let reader = BufReader::new(stdout);
+        let mut full_text = String::new();
+        let mut segment_idx: u32 = 0;
+
+        for line in reader.lines() {
+            let Ok(line) = line else { continue };
+            if let Some(text) = parse_whisper_segment(&line) {
+                if has_transcription_loop(text) {
+                    (self.emit)("process", &format!("segmento {} omitido (loop)", segment_idx + 1), None);
+                    segment_idx += 1;
+                    continue;
+                }
+                (self.emit)("transcript_segment", text, Some(segment_idx));
+                segment_idx += 1;
+                if !full_text.is_empty() {
+                    full_text.push(' ');
+                }
+                full_text.push_str(text);
+            }
+        }
+
+        let stderr_lines = stderr_thread.join().unwrap_or_default();
+        let status = child.wait();
+        log::info!("whisper exit status: {:?}", status);
+        // Las últimas líneas de stderr explican casi siempre por qué falló o salió vacío
+        let error_hint = stderr_lines[stderr_lines.len().saturating_sub(3)..].join(" | ");
+        match status {
+            Ok(status) if status.success() => {}
+            Ok(status) => return Err(format!("whisper-cli terminó con error ({}): {}", status, error_hint)),
+            Err(e) => return Err(format!("Error esperando a whisper-cli: {}", e)),
+        }
+        let text = full_text.trim().to_string();
+
+        let segments = parse_whisper_json(&json_path);
+        log::info!("structured segments parsed: {}", segments.len());
+
+        if text.is_empty() && !error_hint.is_empty() {
+            (self.emit)("process", &format!("whisper stderr: {}", error_hint), None);
+        }
+
+        Ok(TranscriptionResult { text, segments })
+    }
+}
+
+fn parse_whisper_json(json_path: &std::path::Path) -> Vec<TranscriptSegment> {
+    let content = match std::fs::read_to_string(json_path) {
+        Ok(c) => c,
+        Err(e) => {
+            log::warn!("whisper json no encontrado en {}: {}", json_path.display(), e);
+            return Vec::new();
+        }
+    };
+    match serde_json::from_str::<WhisperJson>(&content) {
+        Ok(parsed) => parsed
+            .transcription
+            .into_iter()
+            .filter_map(|s| {
+                let text = s.text.trim().to_string();
+                if text.is_empty() || has_transcription_loop(&text) {
+                    return None;
+                }
+                Some(TranscriptSegment {
+                    from_ms: s.offsets.from,
+                    to_ms: s.offsets.to,
+                    text,
+                })
+            })
+            .collect(),
+        Err(e) => {
+            log::warn!("error parseando whisper json: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// Parsea el progreso de stderr con -pp.
+/// Formato: "whisper_print_progress_callback: progress =  10%"
+fn parse_progress_line(line: &str) -> Option<u32> {
+    if line.contains("progress =") {
+        let after_eq = line.split("progress =").nth(1)?;
+        let num_str = after_eq.trim().trim_end_matches('%').trim();
+        num_str.parse().ok()
+    } else {
+        None
+    }
+}
+
+/// Parsea una línea de salida de whisper-cli con -np.
+/// Formato: [HH:MM:SS.mmm --> HH:MM:SS.mmm]  texto
+fn parse_whisper_segment(line: &str) -> Option<&str> {
+    if line.starts_with('[') {
+        if let Some(bracket_end) = line.find(']') {
+            let text = line[bracket_end + 1..].trim();
+            if !text.is_empty() {
+                return Some(text);
+            }
+        }
+    }
+    None
+}
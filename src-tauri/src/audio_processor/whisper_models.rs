@@ -0,0 +1,276 @@
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+/// Repo de los modelos oficiales; los nombres sueltos (`ggml-base.bin`) se buscan aquí.
+const DEFAULT_REPO: &str = "ggerganov/whisper.cpp";
+const HF_BASE_URL: &str = "https://huggingface.co";
+/// "ggml" leído como u32 little-endian.
+const GGML_MAGIC: u32 = 0x67676d6c;
+/// whisper.cpp guarda `ftype + 1000 * versión de cuantización`.
+const GGML_QNT_VERSION_FACTOR: i32 = 1000;
+
+/// Dónde vive un modelo de whisper.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WhisperModelSpec {
+    /// Archivo en disco (ruta directa o modelo registrado); nunca se copia ni se borra.
+    Local(PathBuf),
+    HuggingFace { repo: String, file: String },
+}
+
+/// Hiperparámetros leídos de la cabecera ggml.
+#[derive(Debug, Clone, Serialize)]
+pub struct WhisperModelHeader {
+    pub n_vocab: i32,
+    pub n_audio_layer: i32,
+    pub n_text_layer: i32,
+    pub n_mels: i32,
+    pub quantization: String,
+    /// Vocabulario multilingüe (51865+) frente a los modelos `.en`.
+    pub multilingual: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct RegisteredModel {
+    name: String,
+    path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WhisperModelInfo {
+    /// Valor a pasar como `whisper_model`.
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    /// true si se registró desde otra carpeta en vez de descargarse.
+    pub registered: bool,
+    pub header: Option<WhisperModelHeader>,
+}
+
+/// Acepta, en este orden: ruta a un archivo, nombre de un modelo registrado,
+/// `owner/repo/archivo.bin` (o `owner/repo:archivo.bin`) y nombres del repo oficial.
+/// Sólo modelos ggml: whisper.cpp no carga whisper en GGUF y `read_header` los rechaza.
+pub fn resolve(whisper_model: &str) -> WhisperModelSpec {
+    let name = whisper_model.trim();
+    let path = Path::new(name);
+    if path.is_absolute() || path.exists() {
+        return WhisperModelSpec::Local(path.to_path_buf());
+    }
+    if let Some(registered) = load_registry().into_iter().find(|m| m.name == name) {
+        return WhisperModelSpec::Local(PathBuf::from(registered.path));
+    }
+    if let Some((repo, file)) = name.split_once(':') {
+        return WhisperModelSpec::HuggingFace { repo: repo.to_string(), file: file.to_string() };
+    }
+    let parts: Vec<&str> = name.splitn(3, '/').collect();
+    if parts.len() == 3 {
+        return WhisperModelSpec::HuggingFace {
+            repo: format!("{}/{}", parts[0], parts[1]),
+            file: parts[2].to_string(),
+        };
+    }
+    WhisperModelSpec::HuggingFace { repo: DEFAULT_REPO.to_string(), file: name.to_string() }
+}
+
+/// Los del repo oficial siguen en la raíz del directorio de datos (como siempre);
+/// los de otros repos van a `whisper_models/<owner>--<repo>/`.
+pub fn local_path(spec: &WhisperModelSpec) -> PathBuf {
+    match spec {
+        WhisperModelSpec::Local(path) => path.clone(),
+        WhisperModelSpec::HuggingFace { repo, file } if repo == DEFAULT_REPO => {
+            crate::utils::models_base_dir().join(file)
+        }
+        WhisperModelSpec::HuggingFace { repo, file } => crate::utils::models_base_dir()
+            .join("whisper_models")
+            .join(repo.replace('/', "--"))
+            .join(file),
+    }
+}
+
+/// `repo` debe ser `owner/repo` y `file` una ruta relativa sin `..`: la descarga se guarda
+/// con esas piezas bajo el directorio de datos y no puede terminar fuera.
+pub fn check_download(repo: &str, file: &str) -> Result<(), String> {
+    let base = crate::utils::models_base_dir();
+    let valid = repo.split('/').count() == 2
+        && crate::model_pack::safe_join(&base, Path::new(repo)).is_some()
+        && crate::model_pack::safe_join(&base, Path::new(file)).is_some();
+    if !valid {
+        return Err(format!("Modelo inválido: '{}:{}' (usa owner/repo/archivo.bin)", repo, file));
+    }
+    Ok(())
+}
+
+pub fn download_url(repo: &str, file: &str) -> String {
+    format!("{}/{}/resolve/main/{}", HF_BASE_URL, repo, file)
+}
+
+/// Comprueba que el archivo es un modelo ggml de whisper antes de pasarlo a whisper.cpp,
+/// que con un archivo inválido aborta sin un error útil.
+pub fn read_header(path: &Path) -> Result<WhisperModelHeader, String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("No se pudo abrir {}: {}", path.display(), e))?;
+    let mut buf = [0u8; 48];
+    file.read_exact(&mut buf)
+        .map_err(|_| format!("{} es demasiado pequeño para ser un modelo", path.display()))?;
+
+    let magic = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    if &buf[..4] == b"GGUF" {
+        return Err(format!(
+            "{} es GGUF y los modelos de whisper en GGUF no están soportados: whisper.cpp sólo carga \
+             el formato ggml (.bin). Usa la versión ggml del modelo o conviértelo con \
+             models/convert-h5-to-ggml.py de whisper.cpp",
+            path.display()
+        ));
+    }
+    if magic != GGML_MAGIC {
+        return Err(format!("{} no es un modelo ggml de whisper", path.display()));
+    }
+
+    // n_vocab, n_audio_ctx, n_audio_state, n_audio_head, n_audio_layer,
+    // n_text_ctx, n_text_state, n_text_head, n_text_layer, n_mels, ftype
+    let field = |idx: usize| {
+        let at = 4 + idx * 4;
+        i32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+    };
+    let header = WhisperModelHeader {
+        n_vocab: field(0),
+        n_audio_layer: field(4),
+        n_text_layer: field(8),
+        n_mels: field(9),
+        quantization: ftype_name(field(10) % GGML_QNT_VERSION_FACTOR).to_string(),
+        multilingual: field(0) >= 51865,
+    };
+    let plausible = header.n_vocab > 0
+        && (1..=64).contains(&header.n_audio_layer)
+        && (1..=64).contains(&header.n_text_layer)
+        && matches!(header.n_mels, 80 | 128);
+    if !plausible {
+        return Err(format!("Cabecera de modelo whisper inválida en {}", path.display()));
+    }
+    Ok(header)
+}
+
+fn ftype_name(ftype: i32) -> &'static str {
+    match ftype {
+        0 => "f32",
+        1 => "f16",
+        2 => "q4_0",
+        3 => "q4_1",
+        7 => "q8_0",
+        8 => "q5_0",
+        9 => "q5_1",
+        10 => "q2_k",
+        11 => "q3_k",
+        12 => "q4_k",
+        13 => "q5_k",
+        14 => "q6_k",
+        _ => "desconocida",
+    }
+}
+
+// ─── Registry ────────────────────────────────────────────────────────────────
+
+fn registry_path() -> PathBuf {
+    crate::utils::models_base_dir().join("whisper_models.json")
+}
+
+fn load_registry() -> Vec<RegisteredModel> {
+    std::fs::read_to_string(registry_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_registry(models: &[RegisteredModel]) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(models).map_err(|e| e.to_string())?;
+    std::fs::write(registry_path(), json).map_err(|e| format!("No se pudo guardar el registro de modelos: {}", e))
+}
+
+/// Registra un modelo que ya está en disco (p. ej. un fine-tune en una unidad compartida).
+/// Tiene que ser ggml; los GGUF se rechazan con una explicación.
+pub fn import_model(path: &str, name: &str) -> Result<WhisperModelInfo, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("El modelo necesita un nombre".into());
+    }
+    let path = Path::new(path.trim());
+    let header = read_header(path)?;
+    log::info!("import whisper model name={} path={} header={:?}", name, path.display(), header);
+
+    let mut registry = load_registry();
+    registry.retain(|m| m.name != name);
+    registry.push(RegisteredModel { name: name.to_string(), path: path.to_string_lossy().into_owned() });
+    save_registry(&registry)?;
+
+    Ok(WhisperModelInfo {
+        name: name.to_string(),
+        path: path.to_string_lossy().into_owned(),
+        size: std::fs::metadata(path).map(|m| m.len()).unwrap_or_default(),
+        registered: true,
+        header: Some(header),
+    })
+}
+
+/// Quita un modelo del registro; el archivo original no se toca.
+pub fn forget_model(name: &str) -> Result<(), String> {
+    let mut registry = load_registry();
+    let before = registry.len();
+    registry.retain(|m| m.name != name);
+    if registry.len() == before {
+        return Err(format!("No hay ningún modelo registrado llamado '{}'", name));
+    }
+    save_registry(&registry)
+}
+
+/// Modelos descargados (repo oficial y otros repos) más los registrados.
+pub fn list_models() -> Vec<WhisperModelInfo> {
+    let base = crate::utils::models_base_dir();
+    let mut models: Vec<WhisperModelInfo> = Vec::new();
+
+    let mut downloaded: Vec<(String, PathBuf)> = read_model_files(&base)
+        .into_iter()
+        .map(|p| (p.file_name().unwrap_or_default().to_string_lossy().into_owned(), p))
+        .collect();
+    if let Ok(repos) = std::fs::read_dir(base.join("whisper_models")) {
+        for repo_dir in repos.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.is_dir()) {
+            let repo = repo_dir.file_name().unwrap_or_default().to_string_lossy().replacen("--", "/", 1);
+            for path in read_model_files(&repo_dir) {
+                let file = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+                downloaded.push((format!("{}/{}", repo, file), path));
+            }
+        }
+    }
+    downloaded.sort();
+    for (name, path) in downloaded {
+        models.push(model_info(name, &path, false));
+    }
+    for registered in load_registry() {
+        models.push(model_info(registered.name, Path::new(&registered.path), true));
+    }
+    models
+}
+
+/// Archivos `ggml-*.bin` de una carpeta, excluyendo el modelo de VAD.
+fn read_model_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            let name = p.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            p.is_file() && name.ends_with(".bin") && name != super::VAD_MODEL_NAME
+        })
+        .collect()
+}
+
+fn model_info(name: String, path: &Path, registered: bool) -> WhisperModelInfo {
+    WhisperModelInfo {
+        name,
+        path: path.to_string_lossy().into_owned(),
+        size: std::fs::metadata(path).map(|m| m.len()).unwrap_or_default(),
+        registered,
+        header: read_header(path).ok(),
+    }
+}
@@ -0,0 +1,319 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+use serde::Serialize;
+
+use crate::audio_processor::whisper_models::{self, WhisperModelInfo};
+use crate::audio_processor::{AudioProcessor, BackendKind, EmitType};
+use crate::hardware::HardwareInfo;
+use crate::summarizer_cli::models::LocalModel;
+use crate::tools::{self, Tool};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolReport {
+    pub name: String,
+    pub path: Option<String>,
+    pub version: Option<String>,
+    pub ok: bool,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmokeStatus {
+    Passed,
+    Failed,
+    Skipped,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SmokeTest {
+    pub status: SmokeStatus,
+    pub model: Option<String>,
+    pub elapsed_ms: u64,
+    pub detail: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    pub app_version: String,
+    pub os: String,
+    pub arch: String,
+    pub hardware: HardwareInfo,
+    pub data_dir: String,
+    pub free_disk_bytes: Option<u64>,
+    pub offline: bool,
+    pub mirror_url: Option<String>,
+    pub proxy_configured: bool,
+    pub tools: Vec<ToolReport>,
+    pub whisper_models: Vec<WhisperModelInfo>,
+    pub llm_models: Vec<LocalModel>,
+    pub smoke_test: SmokeTest,
+    /// El mismo informe en texto plano, listo para pegar en un issue o un chat.
+    pub report: String,
+}
+
+/// Recoge el estado del entorno y prueba la transcripción local con un clip de voz corto.
+pub fn run(emit: EmitType) -> DiagnosticsReport {
+    emit("process", "Diagnóstico: revisando herramientas y modelos", None);
+    let data_dir = crate::utils::models_base_dir();
+    let network = crate::network::load_config();
+    let whisper_models = whisper_models::list_models();
+
+    let mut report = DiagnosticsReport {
+        app_version: env!("CARGO_PKG_VERSION").to_string(),
+        os: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        hardware: crate::hardware::detect().clone(),
+        data_dir: data_dir.to_string_lossy().into_owned(),
+        free_disk_bytes: free_disk_bytes(&data_dir),
+        offline: network.offline,
+        mirror_url: network.mirror_url.clone(),
+        proxy_configured: network.proxy.is_some(),
+        tools: tool_reports(),
+        smoke_test: SmokeTest { status: SmokeStatus::Skipped, model: None, elapsed_ms: 0, detail: String::new() },
+        whisper_models,
+        llm_models: crate::summarizer_cli::models::list_models(),
+        report: String::new(),
+    };
+
+    emit("process", "Diagnóstico: transcripción de prueba", None);
+    report.smoke_test = smoke_transcription(emit.clone(), &report.whisper_models);
+    report.report = render(&report);
+    log::info!("diagnostics:\n{}", report.report);
+    emit("process", "Diagnóstico completado", None);
+    report
+}
+
+fn tool_reports() -> Vec<ToolReport> {
+    let mut reports: Vec<ToolReport> = tools::status_all()
+        .into_iter()
+        .map(|status| {
+            let name = match status.tool {
+                Tool::WhisperCli => "whisper-cli",
+                Tool::YtDlp => "yt-dlp",
+            };
+            let detail = match (&status.path, status.meets_minimum, status.stale) {
+                (None, _, _) => "no encontrado".to_string(),
+                (Some(_), false, _) => format!("anterior a la mínima {}", status.minimum_version),
+                (Some(_), true, true) => "desactualizado, conviene actualizar".to_string(),
+                (Some(_), true, false) => format!("{:?}", status.source.unwrap_or(tools::ToolSource::System)).to_lowercase(),
+            };
+            ToolReport {
+                name: name.to_string(),
+                ok: status.path.is_some() && status.meets_minimum,
+                path: status.path,
+                version: status.version,
+                detail,
+            }
+        })
+        .collect();
+
+    let llama = crate::summarizer_cli::installed_llama_bin();
+    reports.push(ToolReport {
+        name: "llama-completion".to_string(),
+        ok: llama.is_some(),
+        path: llama.as_ref().map(|(p, _)| p.to_string_lossy().into_owned()),
+        version: llama.as_ref().and_then(|(_, v)| v.clone()),
+        detail: if llama.is_some() { "instalado" } else { "se descarga al primer resumen" }.to_string(),
+    });
+    reports
+}
+
+/// `df` en macOS/Linux, `Get-PSDrive` en Windows; `None` si no se puede averiguar.
+fn free_disk_bytes(dir: &Path) -> Option<u64> {
+    if cfg!(target_os = "windows") {
+        let drive = dir.to_string_lossy().chars().next().filter(|c| c.is_ascii_alphabetic())?;
+        let output = Command::new("powershell")
+            .args(["-NoProfile", "-Command", &format!("(Get-PSDrive -Name {}).Free", drive)])
+            .stdin(Stdio::null())
+            .output()
+            .ok()?;
+        String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+    } else {
+        let output = Command::new("df").arg("-Pk").arg(dir).stdin(Stdio::null()).output().ok()?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let available_kb: u64 = stdout.lines().nth(1)?.split_whitespace().nth(3)?.parse().ok()?;
+        Some(available_kb * 1024)
+    }
+}
+
+// ─── Smoke test ──────────────────────────────────────────────────────────────
+
+const SMOKE_SAMPLE_RATE: u32 = 16_000;
+const SMOKE_CLIP_SECS: u32 = 2;
+
+/// Transcribe un clip generado al vuelo con el modelo válido más pequeño, por el mismo
+/// camino que un archivo del usuario (`AudioProcessor` con whisper-cli). El clip es un tono:
+/// no se espera texto, sino que el binario cargue el modelo, procese el audio y termine bien.
+fn smoke_transcription(emit: EmitType, models: &[WhisperModelInfo]) -> SmokeTest {
+    let skipped = |detail: &str| SmokeTest {
+        status: SmokeStatus::Skipped,
+        model: None,
+        elapsed_ms: 0,
+        detail: detail.to_string(),
+    };
+    let Some(model) = models.iter().filter(|m| m.header.is_some()).min_by_key(|m| m.size) else {
+        return skipped("no hay modelos de whisper válidos descargados");
+    };
+    if tools::status(Tool::WhisperCli).path.is_none() {
+        return skipped("whisper-cli no encontrado");
+    }
+
+    let workspace = match crate::workspace::Workspace::create("smoke") {
+        Ok(w) => w,
+        Err(e) => return skipped(&e),
+    };
+    let clip = workspace.file("smoke.wav");
+    if let Err(e) = write_smoke_clip(&clip) {
+        return skipped(&format!("no se pudo generar el clip de prueba: {}", e));
+    }
+
+    let started = Instant::now();
+    // El backend remoto se prueba aparte; aquí interesa la instalación local
+    let result = AudioProcessor::new(emit, clip.to_string_lossy().into_owned(), model.name.clone())
+        .with_backend(BackendKind::WhisperCli)
+        .run();
+    let elapsed_ms = started.elapsed().as_millis() as u64;
+
+    let (status, detail) = match result {
+        Ok(transcription) => match spoken_text(&transcription.text) {
+            Some(text) => (SmokeStatus::Passed, format!("transcribió: \"{}\"", text)),
+            None => (SmokeStatus::Passed, "whisper-cli procesó el clip de prueba".to_string()),
+        },
+        Err(e) => (SmokeStatus::Failed, e),
+    };
+    SmokeTest { status, model: Some(model.name.clone()), elapsed_ms, detail }
+}
+
+fn write_smoke_clip(path: &Path) -> Result<(), hound::Error> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: SMOKE_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    let total = SMOKE_SAMPLE_RATE * SMOKE_CLIP_SECS;
+    for n in 0..total {
+        let t = n as f32 / SMOKE_SAMPLE_RATE as f32;
+        // Ráfagas de 440 Hz de 250 ms separadas por silencio
+        let gate = if (t * 2.0).fract() < 0.5 { 1.0 } else { 0.0 };
+        let sample = (t * 440.0 * std::f32::consts::TAU).sin() * 0.3 * gate;
+        writer.write_sample((sample * i16::MAX as f32) as i16)?;
+    }
+    writer.finalize()
+}
+
+/// El texto sin las marcas que whisper emite cuando no reconoce voz (`[BLANK_AUDIO]`, `(música)`).
+fn spoken_text(text: &str) -> Option<String> {
+    let mut spoken = String::new();
+    let mut depth = 0usize;
+    for c in text.chars() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            c if depth == 0 => spoken.push(c),
+            _ => {}
+        }
+    }
+    let spoken = spoken.split_whitespace().collect::<Vec<_>>().join(" ");
+    spoken.chars().any(char::is_alphanumeric).then_some(spoken)
+}
+
+// ─── Report ──────────────────────────────────────────────────────────────────
+
+fn render(report: &DiagnosticsReport) -> String {
+    let gb = |bytes: u64| format!("{:.2} GB", bytes as f64 / 1_073_741_824.0);
+    let mut out = vec![
+        format!("beautiful-stt {} ({} {})", report.app_version, report.os, report.arch),
+        format!(
+            "GPU: {}{}  ·  whisper-cli: {}  ·  hilos: {}  ·  memoria: {}",
+            report.hardware.backend.as_str(),
+            report.hardware.devices.iter().map(|d| format!(", {}", d.name)).collect::<String>(),
+            report.hardware.whisper_backend.as_str(),
+            report.hardware.logical_cores,
+            report.hardware.total_memory_bytes.map(gb).unwrap_or_else(|| "desconocida".into())
+        ),
+        format!(
+            "CPU: {}  ·  {:?}",
+            report.hardware.cpu_name.as_deref().unwrap_or("?"),
+            report.hardware.cpu_features
+        ),
+        format!(
+            "Recomendado: {}, beam {}, {} hilos",
+            report.hardware.recommended.whisper_model,
+            report.hardware.recommended.beam_size,
+            report.hardware.recommended.threads
+        ),
+        format!(
+            "Datos: {} (libre: {})",
+            report.data_dir,
+            report.free_disk_bytes.map(gb).unwrap_or_else(|| "desconocido".into())
+        ),
+        format!(
+            "Red: {}{}{}",
+            if report.offline { "sin conexión" } else { "en línea" },
+            report.mirror_url.as_deref().map(|m| format!(", espejo {}", m)).unwrap_or_default(),
+            if report.proxy_configured { ", con proxy" } else { "" }
+        ),
+        String::new(),
+        "Herramientas:".to_string(),
+    ];
+    for tool in &report.tools {
+        out.push(format!(
+            "  [{}] {} {} — {} ({})",
+            if tool.ok { "ok" } else { "!!" },
+            tool.name,
+            tool.version.as_deref().unwrap_or("?"),
+            tool.path.as_deref().unwrap_or("-"),
+            tool.detail
+        ));
+    }
+    out.push(String::new());
+    out.push("Modelos de whisper:".to_string());
+    for model in &report.whisper_models {
+        let header = match &model.header {
+            Some(h) => format!("{}, {} mels, {} capas de texto", h.quantization, h.n_mels, h.n_text_layer),
+            None => "cabecera inválida".to_string(),
+        };
+        out.push(format!("  {} — {} ({})", model.name, gb(model.size), header));
+    }
+    out.push("Modelos LLM:".to_string());
+    for model in &report.llm_models {
+        out.push(format!("  {} — {}", model.name, gb(model.size)));
+    }
+    out.push(String::new());
+    out.push(format!(
+        "Prueba de transcripción: {:?} {} en {} ms — {}",
+        report.smoke_test.status,
+        report.smoke_test.model.as_deref().unwrap_or(""),
+        report.smoke_test.elapsed_ms,
+        report.smoke_test.detail
+    ));
+    out.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spoken_text_ignores_whisper_markers() {
+        assert_eq!(spoken_text(" [BLANK_AUDIO] "), None);
+        assert_eq!(spoken_text("(música) ... "), None);
+        assert_eq!(spoken_text(" [risas] Hola, esto es una prueba."), Some("Hola, esto es una prueba.".to_string()));
+    }
+
+    #[test]
+    fn smoke_clip_is_16khz_mono() {
+        let workspace = crate::workspace::Workspace::create("smoke-test").unwrap();
+        let clip = workspace.file("smoke.wav");
+        write_smoke_clip(&clip).unwrap();
+        let reader = hound::WavReader::open(&clip).unwrap();
+        let spec = reader.spec();
+        assert_eq!(spec.channels, 1);
+        assert_eq!(spec.sample_rate, 16_000);
+        assert_eq!(reader.duration(), SMOKE_SAMPLE_RATE * SMOKE_CLIP_SECS);
+    }
+}
@@ -0,0 +1,323 @@
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Condvar, Mutex};
+use std::time::{Duration, Instant};
+use serde::Serialize;
+
+use crate::network::{self, NetworkConfig};
+
+const CHUNK_SIZE: usize = 512 * 1024;
+const MAX_BACKOFF_SECS: u64 = 30;
+/// Sin tamaño conocido no hay porcentaje; se informa cada cierto tiempo.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Evento `download_progress`: el mismo para modelos de whisper, VAD, LLMs y binarios.
+/// El texto legible se emite además como `process_download_assets`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DownloadProgress {
+    /// Nombre del archivo (o del modelo) que se está descargando.
+    pub id: String,
+    pub state: DownloadState,
+    pub downloaded_bytes: u64,
+    pub total_bytes: Option<u64>,
+    pub percent: Option<u32>,
+    pub bytes_per_sec: u64,
+    pub attempt: u32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadState {
+    Queued,
+    Downloading,
+    Retrying,
+    Completed,
+    Failed,
+}
+
+enum TransferError {
+    /// Error de red o corte: vale la pena reintentar.
+    Retry(String),
+    /// 404, acceso denegado, archivo ausente en el espejo: reintentar no sirve.
+    Fatal(String),
+}
+
+/// Descarga `url` a `dest` con la configuración de red (espejo, proxy, token, límites).
+/// Escribe primero en `<dest>.part` y reanuda desde ahí en los reintentos; `dest` sólo
+/// aparece cuando el archivo está completo.
+pub fn download(
+    emit: &dyn Fn(&str, &str, Option<u32>),
+    id: &str,
+    url: &str,
+    dest: &Path,
+) -> Result<(), String> {
+    let config = network::load_config();
+    let resolved = network::resolve_url(&config, url)?;
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("No se pudo crear {}: {}", parent.display(), e))?;
+    }
+
+    let mut progress = Progress::new(emit, id);
+    let _slot = Slot::acquire(dest, config.max_concurrent_downloads.max(1), &mut progress);
+    // Otra tarea pudo haberlo descargado mientras esperábamos turno
+    if dest.exists() {
+        return Ok(());
+    }
+
+    let part = part_path(dest);
+    let mut last_error = String::new();
+    for attempt in 0..=config.max_retries {
+        progress.attempt = attempt + 1;
+        if attempt > 0 {
+            let wait = 2u64.saturating_pow(attempt).min(MAX_BACKOFF_SECS);
+            progress.report(
+                DownloadState::Retrying,
+                &format!("Reintentando en {} s ({}/{}): {}", wait, attempt, config.max_retries, last_error),
+            );
+            std::thread::sleep(Duration::from_secs(wait));
+        }
+        log::info!("download id={} url={} attempt={}", id, resolved, attempt + 1);
+        match transfer(&config, &resolved, &part, &mut progress) {
+            Ok(()) => {
+                std::fs::rename(&part, dest).map_err(|e| format!("No se pudo guardar {}: {}", dest.display(), e))?;
+                progress.report(DownloadState::Completed, "Descarga completada");
+                return Ok(());
+            }
+            Err(TransferError::Fatal(e)) => {
+                last_error = e;
+                break;
+            }
+            Err(TransferError::Retry(e)) => {
+                log::warn!("download id={} failed: {}", id, e);
+                last_error = e;
+            }
+        }
+    }
+
+    progress.report(DownloadState::Failed, &last_error);
+    Err(format!("Descarga interrumpida: {}", last_error))
+}
+
+fn part_path(dest: &Path) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(".part");
+    dest.with_file_name(name)
+}
+
+fn transfer(config: &NetworkConfig, url: &str, part: &Path, progress: &mut Progress) -> Result<(), TransferError> {
+    let offset = std::fs::metadata(part).map(|m| m.len()).unwrap_or(0);
+
+    let (mut reader, resumed, total_bytes): (Box<dyn Read + Send>, bool, Option<u64>) =
+        match network::file_url_path(url) {
+            Some(path) => {
+                let mut file = std::fs::File::open(&path)
+                    .map_err(|e| TransferError::Fatal(format!("No está en el espejo: {} ({})", path.display(), e)))?;
+                let len = file.metadata().map(|m| m.len()).ok();
+                let resumed = offset > 0 && len.is_some_and(|len| offset <= len);
+                if resumed {
+                    file.seek(SeekFrom::Start(offset)).map_err(|e| TransferError::Retry(e.to_string()))?;
+                }
+                (Box::new(file), resumed, len)
+            }
+            None => {
+                let agent = config.agent().map_err(TransferError::Fatal)?;
+                let mut request = config.get(&agent, url);
+                if offset > 0 {
+                    request = request.set("Range", &format!("bytes={}-", offset));
+                }
+                let response = match request.call() {
+                    Ok(response) => response,
+                    // El .part ya no corresponde al archivo remoto; se empieza de cero
+                    Err(ureq::Error::Status(416, _)) => {
+                        let _ = std::fs::remove_file(part);
+                        return Err(TransferError::Retry("rango inválido, reiniciando".into()));
+                    }
+                    Err(ureq::Error::Status(code, r)) if (400..500).contains(&code) && code != 408 && code != 429 => {
+                        return Err(TransferError::Fatal(network::http_error(url, ureq::Error::Status(code, r))));
+                    }
+                    Err(e) => return Err(TransferError::Retry(network::http_error(url, e))),
+                };
+                let resumed = response.status() == 206;
+                let length = response.header("Content-Length").and_then(|v| v.parse::<u64>().ok());
+                let total = if resumed { length.map(|l| l + offset) } else { length };
+                (Box::new(response.into_reader()), resumed, total)
+            }
+        };
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(resumed)
+        .write(true)
+        .truncate(!resumed)
+        .open(part)
+        .map_err(|e| TransferError::Fatal(format!("No se pudo crear {}: {}", part.display(), e)))?;
+
+    progress.start(if resumed { offset } else { 0 }, total_bytes);
+    let limit = config.bandwidth_limit_kbps.filter(|k| *k > 0).map(|k| k * 1024);
+    let mut buf = vec![0u8; limit.map(|l| (l as usize).clamp(16 * 1024, CHUNK_SIZE)).unwrap_or(CHUNK_SIZE)];
+    let started = Instant::now();
+    let mut transferred: u64 = 0;
+    loop {
+        let n = reader.read(&mut buf).map_err(|e| TransferError::Retry(e.to_string()))?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buf[..n]).map_err(|e| TransferError::Fatal(format!("Error escribiendo {}: {}", part.display(), e)))?;
+        transferred += n as u64;
+        progress.advance(n as u64);
+
+        if let Some(bytes_per_sec) = limit {
+            let expected = Duration::from_secs_f64(transferred as f64 / bytes_per_sec as f64);
+            if let Some(wait) = expected.checked_sub(started.elapsed()) {
+                std::thread::sleep(wait);
+            }
+        }
+    }
+
+    match total_bytes {
+        Some(total) if progress.downloaded != total => Err(TransferError::Retry(format!(
+            "se recibieron {} de {} bytes",
+            progress.downloaded, total
+        ))),
+        _ => Ok(()),
+    }
+}
+
+// ─── Progress ────────────────────────────────────────────────────────────────
+
+struct Progress<'a> {
+    emit: &'a dyn Fn(&str, &str, Option<u32>),
+    id: String,
+    attempt: u32,
+    downloaded: u64,
+    total: Option<u64>,
+    started_bytes: u64,
+    started: Instant,
+    last_emit: Instant,
+    last_percent: Option<u32>,
+}
+
+impl<'a> Progress<'a> {
+    fn new(emit: &'a dyn Fn(&str, &str, Option<u32>), id: &str) -> Self {
+        Progress {
+            emit,
+            id: id.to_string(),
+            attempt: 0,
+            downloaded: 0,
+            total: None,
+            started_bytes: 0,
+            started: Instant::now(),
+            last_emit: Instant::now(),
+            last_percent: None,
+        }
+    }
+
+    fn start(&mut self, downloaded: u64, total: Option<u64>) {
+        self.downloaded = downloaded;
+        self.started_bytes = downloaded;
+        self.total = total;
+        self.started = Instant::now();
+        let size = total
+            .map(|b| format!(" ({:.1} GB)", b as f64 / 1_073_741_824.0))
+            .unwrap_or_default();
+        self.report(DownloadState::Downloading, &format!("Descargando {}{}...", self.id, size));
+    }
+
+    fn advance(&mut self, bytes: u64) {
+        self.downloaded += bytes;
+        let percent = self.percent();
+        let due = match percent {
+            Some(pct) => Some(pct) != self.last_percent,
+            None => self.last_emit.elapsed() >= PROGRESS_INTERVAL,
+        };
+        if due {
+            let message = match self.total {
+                Some(total) => format!(
+                    "Descargando {}... {:.0}/{:.0} MB",
+                    self.id,
+                    self.downloaded as f64 / 1_048_576.0,
+                    total as f64 / 1_048_576.0
+                ),
+                None => format!("Descargando {}... {:.0} MB", self.id, self.downloaded as f64 / 1_048_576.0),
+            };
+            self.report(DownloadState::Downloading, &message);
+        }
+    }
+
+    fn percent(&self) -> Option<u32> {
+        self.total
+            .filter(|t| *t > 0)
+            .map(|t| ((self.downloaded as f64 / t as f64) * 100.0).min(100.0) as u32)
+    }
+
+    fn report(&mut self, state: DownloadState, message: &str) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let percent = match state {
+            DownloadState::Completed => Some(100),
+            _ => self.percent(),
+        };
+        let event = DownloadProgress {
+            id: self.id.clone(),
+            state,
+            downloaded_bytes: self.downloaded,
+            total_bytes: self.total,
+            percent,
+            bytes_per_sec: if elapsed > 0.0 {
+                ((self.downloaded - self.started_bytes) as f64 / elapsed) as u64
+            } else {
+                0
+            },
+            attempt: self.attempt,
+            message: message.to_string(),
+        };
+        if let Ok(json) = serde_json::to_string(&event) {
+            (self.emit)("download_progress", &json, percent);
+        }
+        (self.emit)("process_download_assets", message, percent);
+        self.last_percent = percent;
+        self.last_emit = Instant::now();
+    }
+}
+
+// ─── Concurrency ─────────────────────────────────────────────────────────────
+
+struct Slots {
+    active: usize,
+    destinations: Vec<PathBuf>,
+}
+
+static SLOTS: Mutex<Slots> = Mutex::new(Slots { active: 0, destinations: Vec::new() });
+static SLOT_FREED: Condvar = Condvar::new();
+
+/// Turno de descarga: limita las descargas simultáneas y evita que dos tareas
+/// escriban el mismo archivo a la vez. Se libera al salir de `download`.
+struct Slot {
+    dest: PathBuf,
+}
+
+impl Slot {
+    fn acquire(dest: &Path, max_concurrent: usize, progress: &mut Progress) -> Slot {
+        let mut slots = SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+        let mut notified = false;
+        while slots.active >= max_concurrent || slots.destinations.iter().any(|d| d == dest) {
+            if !notified {
+                progress.report(DownloadState::Queued, &format!("En cola: {}", progress.id));
+                notified = true;
+            }
+            slots = SLOT_FREED.wait(slots).unwrap_or_else(|e| e.into_inner());
+        }
+        slots.active += 1;
+        slots.destinations.push(dest.to_path_buf());
+        Slot { dest: dest.to_path_buf() }
+    }
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        let mut slots = SLOTS.lock().unwrap_or_else(|e| e.into_inner());
+        slots.active -= 1;
+        slots.destinations.retain(|d| d != &self.dest);
+        SLOT_FREED.notify_all();
+    }
+}
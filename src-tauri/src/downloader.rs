@@ -23,26 +23,58 @@ impl DownloaderProcessor {
 
     pub fn download(&self) -> DownloadResult {
         let audio_url = self.audio_url.clone();
+        if crate::network::load_config().offline {
+            (self.emit)("process", "Modo sin conexión: no se pueden descargar audios de internet", None);
+            return DownloadResult {
+                title: String::new(),
+                path: String::new(),
+            };
+        }
         (self.emit)(
             "process",
             &format!("Descargando audio de {}", audio_url),
             None,
         );
 
-        let yt_dlp_bin = self.get_ytdlp_bin_path();
-        let tmp_dir = std::env::temp_dir();
-        let file_path = tmp_dir.join("beautiful-stt-download.%(ext)s");
+        let yt_dlp_bin = match crate::tools::ensure(&*self.emit, crate::tools::Tool::YtDlp) {
+            Ok(path) => path,
+            Err(e) => {
+                (self.emit)("process", &e, None);
+                return DownloadResult {
+                    title: String::new(),
+                    path: String::new(),
+                };
+            }
+        };
+        // Fragmentos y .part de yt-dlp van a la carpeta del trabajo; el audio final, con
+        // nombre único, a la carpeta de descargas porque se usa después de este trabajo. Si es la
+        // temporal del sistema, `workspace::sweep_orphans` lo borra en el próximo arranque.
+        let workspace = match crate::workspace::Workspace::create("download") {
+            Ok(w) => w,
+            Err(e) => {
+                (self.emit)("process", &e, None);
+                return DownloadResult {
+                    title: String::new(),
+                    path: String::new(),
+                };
+            }
+        };
+        let settings = crate::settings::load().download;
+        let output_dir = settings.output_dir.map(std::path::PathBuf::from).unwrap_or_else(std::env::temp_dir);
+        let file_path = output_dir.join(format!("{}{}.%(ext)s", crate::workspace::DOWNLOAD_PREFIX, workspace.id()));
         let file_path_str = file_path.to_string_lossy();
+        let temp_paths = format!("temp:{}", workspace.path().display());
 
         let mut counter = 1;
-        let max_retries = 3;
+        let max_retries = u64::from(settings.max_retries);
         loop {
             let output = match Command::new(&yt_dlp_bin)
                 .arg("-f")
-                .arg("bestaudio[ext=m4a]/bestaudio[ext=mp3]/bestaudio")
+                .arg(&settings.format)
                 .arg("--output")
                 .arg(file_path_str.as_ref())
-                .arg("--force-overwrites")
+                .arg("--paths")
+                .arg(&temp_paths)
                 .arg("--socket-timeout")
                 .arg("30")
                 .arg("--print")
@@ -63,6 +95,7 @@ impl DownloaderProcessor {
                 }
             };
 
+            crate::logging::log_output(crate::logging::YT_DLP, &output.stderr);
             if !output.status.success() {
                 let stderr = String::from_utf8_lossy(&output.stderr);
                 let last_line = stderr.lines().last().unwrap_or("error desconocido");
@@ -100,24 +133,4 @@ impl DownloaderProcessor {
             };
         }
     }
-
-    pub fn get_ytdlp_bin_path(&self) -> std::path::PathBuf {
-        let bin_name = if cfg!(target_os = "windows") {
-            "yt-dlp.exe"
-        } else {
-            "yt-dlp"
-        };
-
-        let exe_dir = std::env::current_exe()
-            .ok()
-            .and_then(|p| p.parent().map(|p| p.to_path_buf()))
-            .unwrap_or_default();
-
-        let yt_dlp_bin = exe_dir.join(bin_name);
-        if yt_dlp_bin.exists() {
-            return yt_dlp_bin;
-        }
-
-        std::path::PathBuf::from(bin_name)
-    }
 }
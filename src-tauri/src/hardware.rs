@@ -0,0 +1,456 @@
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+use serde::Serialize;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+/// Margen de VRAM para el contexto (KV cache) y los buffers de cómputo.
+const VRAM_OVERHEAD_BYTES: u64 = 1536 * 1024 * 1024;
+/// Capas de un modelo de 7-8B; sólo se usa para repartir un modelo que no cabe entero.
+const TYPICAL_LLM_LAYERS: u64 = 32;
+/// whisper.cpp deja de escalar pasados unos pocos hilos por núcleo físico.
+const MAX_CPU_THREADS: usize = 8;
+const MAX_GPU_THREADS: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum GpuBackend {
+    Metal,
+    #[serde(rename = "CUDA")]
+    Cuda,
+    #[serde(rename = "ROCm")]
+    Rocm,
+    Vulkan,
+    #[serde(rename = "OpenCL")]
+    OpenCl,
+    #[serde(rename = "CPU")]
+    Cpu,
+}
+
+impl GpuBackend {
+    /// El mismo texto que devuelve el comando `detect_gpu`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            GpuBackend::Metal => "Metal",
+            GpuBackend::Cuda => "CUDA",
+            GpuBackend::Rocm => "ROCm",
+            GpuBackend::Vulkan => "Vulkan",
+            GpuBackend::OpenCl => "OpenCL",
+            GpuBackend::Cpu => "CPU",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GpuDevice {
+    pub name: String,
+    pub backend: GpuBackend,
+    /// En Apple Silicon es la memoria unificada.
+    pub vram_bytes: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CpuFeatures {
+    pub avx: bool,
+    pub avx2: bool,
+    pub avx512: bool,
+    pub fma: bool,
+    pub f16c: bool,
+    pub neon: bool,
+}
+
+/// Parámetros derivados del hardware para whisper-cli y llama.cpp.
+#[derive(Debug, Clone, Serialize)]
+pub struct Recommendations {
+    pub beam_size: u32,
+    pub threads: usize,
+    /// Modelo de whisper sugerido según la memoria disponible.
+    pub whisper_model: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HardwareInfo {
+    /// Backend preferido: el primero que aparece entre Metal, CUDA, ROCm, Vulkan y OpenCL.
+    pub backend: GpuBackend,
+    /// El que usa de verdad whisper-cli: `Cpu` si el binario no trae soporte para `backend`.
+    pub whisper_backend: GpuBackend,
+    pub devices: Vec<GpuDevice>,
+    pub cpu_name: Option<String>,
+    pub cpu_features: CpuFeatures,
+    pub logical_cores: usize,
+    pub total_memory_bytes: Option<u64>,
+    pub recommended: Recommendations,
+}
+
+impl HardwareInfo {
+    /// VRAM del dispositivo principal del backend elegido.
+    pub fn vram_bytes(&self) -> Option<u64> {
+        self.devices
+            .iter()
+            .filter(|d| d.backend == self.backend)
+            .filter_map(|d| d.vram_bytes)
+            .max()
+    }
+
+    /// Valor de `-ngl` para un modelo GGUF de `model_size` bytes: todo en la GPU si cabe,
+    /// una parte proporcional si no, y 0 sin GPU.
+    pub fn gpu_layers(&self, model_size: u64) -> u32 {
+        if self.backend == GpuBackend::Cpu {
+            return 0;
+        }
+        let Some(vram) = self.vram_bytes() else {
+            return 99;
+        };
+        let usable = vram.saturating_sub(VRAM_OVERHEAD_BYTES);
+        if model_size == 0 || model_size <= usable {
+            return 99;
+        }
+        (usable * TYPICAL_LLM_LAYERS / model_size) as u32
+    }
+}
+
+/// `-ngl` para el modelo GGUF en `model_path`.
+pub fn gpu_layers_for(model_path: &std::path::Path) -> u32 {
+    let size = std::fs::metadata(model_path).map(|m| m.len()).unwrap_or(0);
+    detect().gpu_layers(size)
+}
+
+static HARDWARE: OnceLock<HardwareInfo> = OnceLock::new();
+
+/// Detecta el hardware una vez por ejecución; las herramientas externas tardan en responder.
+pub fn detect() -> &'static HardwareInfo {
+    HARDWARE.get_or_init(|| {
+        let devices = detect_devices();
+        let backend = [GpuBackend::Metal, GpuBackend::Cuda, GpuBackend::Rocm, GpuBackend::Vulkan, GpuBackend::OpenCl]
+            .into_iter()
+            .find(|b| devices.iter().any(|d| d.backend == *b))
+            .unwrap_or(GpuBackend::Cpu);
+        let logical_cores = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+        let total_memory_bytes = total_memory_bytes();
+        let cpu_features = cpu_features();
+
+        let whisper_backend = match crate::tools::path(crate::tools::Tool::WhisperCli) {
+            Some(bin) if backend != GpuBackend::Cpu && !binary_supports(&bin, backend) => {
+                log::info!("whisper-cli {} has no {} support, using the CPU profile", bin.display(), backend.as_str());
+                GpuBackend::Cpu
+            }
+            Some(_) => backend,
+            None => GpuBackend::Cpu,
+        };
+
+        let mut info = HardwareInfo {
+            backend,
+            whisper_backend,
+            devices,
+            cpu_name: cpu_name(),
+            cpu_features,
+            logical_cores,
+            total_memory_bytes,
+            recommended: Recommendations { beam_size: 5, threads: logical_cores, whisper_model: String::new() },
+        };
+        info.recommended = recommend(&info);
+        log::info!(
+            "hardware backend={} whisper={} devices={:?} features={:?} cores={} memory={:?} recommended={:?}",
+            info.backend.as_str(),
+            info.whisper_backend.as_str(),
+            info.devices,
+            info.cpu_features,
+            info.logical_cores,
+            info.total_memory_bytes,
+            info.recommended
+        );
+        info
+    })
+}
+
+/// Perfil de whisper: depende del backend con el que corre whisper-cli, no sólo del hardware.
+fn recommend(info: &HardwareInfo) -> Recommendations {
+    let gpu = info.whisper_backend != GpuBackend::Cpu;
+    let threads = info.logical_cores.clamp(1, if gpu { MAX_GPU_THREADS } else { MAX_CPU_THREADS });
+    let fast_cpu = info.cpu_features.avx512 || (info.cpu_features.neon && info.logical_cores >= 8);
+    let beam_size = match (gpu, fast_cpu) {
+        (true, _) => 5,
+        (false, true) => 3,
+        (false, false) => 2,
+    };
+
+    let vram = info.vram_bytes().unwrap_or(0);
+    let memory = info.total_memory_bytes.unwrap_or(0);
+    let whisper_model = match info.whisper_backend {
+        GpuBackend::Metal if memory >= 16 * GIB => "ggml-large-v3-turbo.bin",
+        GpuBackend::Metal => "ggml-small.bin",
+        GpuBackend::Cpu if info.cpu_features.avx2 || info.cpu_features.neon => "ggml-small-q8_0.bin",
+        GpuBackend::Cpu => "ggml-tiny.bin",
+        _ if vram >= 6 * GIB => "ggml-large-v3-turbo.bin",
+        _ if vram >= 2 * GIB => "ggml-small.bin",
+        _ => "ggml-small-q8_0.bin",
+    };
+
+    Recommendations { beam_size, threads, whisper_model: whisper_model.to_string() }
+}
+
+// ─── Dispositivos ────────────────────────────────────────────────────────────
+
+fn detect_devices() -> Vec<GpuDevice> {
+    let mut devices = Vec::new();
+    if cfg!(target_os = "macos") {
+        devices.push(GpuDevice {
+            name: cpu_name().unwrap_or_else(|| "Apple GPU".to_string()),
+            backend: GpuBackend::Metal,
+            vram_bytes: total_memory_bytes(),
+        });
+        return devices;
+    }
+    devices.extend(nvidia_devices());
+    if cfg!(target_os = "linux") {
+        devices.extend(rocm_devices());
+    }
+    devices.extend(vulkan_devices());
+    // OpenCL sólo aporta algo si no hay otra GPU utilizable
+    if devices.is_empty() {
+        devices.extend(opencl_devices());
+    }
+    devices
+}
+
+fn command_stdout(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// `nvidia-smi --query-gpu=name,memory.total`: "NVIDIA GeForce RTX 3060, 12288" (MiB).
+fn nvidia_devices() -> Vec<GpuDevice> {
+    let Some(stdout) = command_stdout("nvidia-smi", &["--query-gpu=name,memory.total", "--format=csv,noheader,nounits"]) else {
+        return Vec::new();
+    };
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let (name, mib) = line.rsplit_once(',')?;
+            Some(GpuDevice {
+                name: name.trim().to_string(),
+                backend: GpuBackend::Cuda,
+                vram_bytes: mib.trim().parse::<u64>().ok().map(|m| m * 1024 * 1024),
+            })
+        })
+        .collect()
+}
+
+/// GPUs AMD con el driver amdgpu; cuentan como ROCm sólo si el runtime está instalado.
+fn rocm_devices() -> Vec<GpuDevice> {
+    if !std::path::Path::new("/opt/rocm").exists() && command_stdout("rocm-smi", &[]).is_none() {
+        return Vec::new();
+    }
+    let Ok(cards) = std::fs::read_dir("/sys/class/drm") else {
+        return Vec::new();
+    };
+    cards
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path().join("device"))
+        .filter(|device| {
+            std::fs::read_to_string(device.join("vendor")).is_ok_and(|v| v.trim() == "0x1002")
+        })
+        .filter_map(|device| {
+            let vram = std::fs::read_to_string(device.join("mem_info_vram_total")).ok()?;
+            let name = std::fs::read_to_string(device.join("product_name"))
+                .map(|n| n.trim().to_string())
+                .unwrap_or_else(|_| "AMD GPU".to_string());
+            Some(GpuDevice { name, backend: GpuBackend::Rocm, vram_bytes: vram.trim().parse().ok() })
+        })
+        .collect()
+}
+
+/// `vulkaninfo --summary`; se descartan los renderizadores por software (llvmpipe).
+fn vulkan_devices() -> Vec<GpuDevice> {
+    let Some(stdout) = command_stdout("vulkaninfo", &["--summary"]) else {
+        return Vec::new();
+    };
+    let mut devices = Vec::new();
+    let mut name: Option<String> = None;
+    for line in stdout.lines().map(str::trim) {
+        if let Some(value) = line.strip_prefix("deviceName") {
+            name = Some(value.trim_start_matches([' ', '=']).trim().to_string());
+        } else if let Some(value) = line.strip_prefix("deviceType") {
+            let device_type = value.trim_start_matches([' ', '=']).trim();
+            if let Some(name) = name.take() {
+                if device_type.contains("CPU") || name.to_lowercase().contains("llvmpipe") {
+                    continue;
+                }
+                devices.push(GpuDevice { name, backend: GpuBackend::Vulkan, vram_bytes: None });
+            }
+        }
+    }
+    if devices.is_empty() {
+        // Versiones antiguas de vulkaninfo listan deviceName sin deviceType
+        devices.extend(name.map(|name| GpuDevice { name, backend: GpuBackend::Vulkan, vram_bytes: None }));
+    }
+    devices
+}
+
+/// `clinfo`: "Device Name   Intel(R) UHD Graphics" seguido de "Device Type   GPU". Se descartan
+/// los dispositivos CPU (pocl, runtime de Intel para CPU), que no aceleran nada.
+fn opencl_devices() -> Vec<GpuDevice> {
+    let Some(stdout) = command_stdout("clinfo", &[]) else {
+        return Vec::new();
+    };
+    parse_clinfo(&stdout)
+}
+
+fn parse_clinfo(stdout: &str) -> Vec<GpuDevice> {
+    let mut devices = Vec::new();
+    let mut name: Option<String> = None;
+    for line in stdout.lines().map(str::trim) {
+        if let Some(value) = line.strip_prefix("Device Name") {
+            name = Some(value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("Device Type") {
+            if let Some(name) = name.take() {
+                if value.contains("GPU") {
+                    devices.push(GpuDevice { name, backend: GpuBackend::OpenCl, vram_bytes: None });
+                }
+            }
+        }
+    }
+    devices
+}
+
+/// Busca el backend de ggml en el binario y en las bibliotecas que lo acompañan: por el nombre
+/// (`ggml-cuda.dll`, `libggml-vulkan.so`) o, si está enlazado estáticamente, por sus símbolos.
+fn binary_supports(bin: &std::path::Path, backend: GpuBackend) -> bool {
+    let markers: &[&str] = match backend {
+        GpuBackend::Metal => &["ggml-metal", "ggml_backend_metal_reg", "MTLCreateSystemDefaultDevice"],
+        GpuBackend::Cuda => &["ggml-cuda", "ggml_backend_cuda_reg", "cublasCreate"],
+        GpuBackend::Rocm => &["ggml-hip", "hipblasCreate"],
+        GpuBackend::Vulkan => &["ggml-vulkan", "ggml_backend_vk_reg", "vkCreateInstance"],
+        GpuBackend::OpenCl => &["ggml-opencl", "ggml_backend_opencl_reg", "clCreateContext"],
+        GpuBackend::Cpu => return true,
+    };
+    let mut files = vec![bin.to_path_buf()];
+    if let Some(dir) = bin.parent() {
+        // Windows junto al ejecutable, Linux también en ../lib y el bundle de macOS en ../Frameworks
+        for dir in [dir.to_path_buf(), dir.join("../lib"), dir.join("../Frameworks")] {
+            let Ok(entries) = std::fs::read_dir(dir) else { continue };
+            files.extend(
+                entries
+                    .filter_map(|e| e.ok())
+                    .map(|e| e.path())
+                    .filter(|p| p.file_name().is_some_and(|n| n.to_string_lossy().contains("ggml"))),
+            );
+        }
+    }
+    files.iter().any(|file| {
+        let name = file.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        markers.iter().any(|m| name.contains(m))
+            || std::fs::read(file).is_ok_and(|bytes| {
+                markers.iter().any(|m| bytes.windows(m.len()).any(|w| w == m.as_bytes()))
+            })
+    })
+}
+
+// ─── CPU y memoria ───────────────────────────────────────────────────────────
+
+fn cpu_features() -> CpuFeatures {
+    #[allow(unused_mut)]
+    let mut features = CpuFeatures::default();
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        features.avx = std::arch::is_x86_feature_detected!("avx");
+        features.avx2 = std::arch::is_x86_feature_detected!("avx2");
+        features.avx512 = std::arch::is_x86_feature_detected!("avx512f");
+        features.fma = std::arch::is_x86_feature_detected!("fma");
+        features.f16c = std::arch::is_x86_feature_detected!("f16c");
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        features.neon = std::arch::is_aarch64_feature_detected!("neon");
+    }
+    features
+}
+
+fn cpu_name() -> Option<String> {
+    let name = if cfg!(target_os = "macos") {
+        command_stdout("sysctl", &["-n", "machdep.cpu.brand_string"])?
+    } else if cfg!(target_os = "windows") {
+        command_stdout("powershell", &["-NoProfile", "-Command", "(Get-CimInstance Win32_Processor).Name"])?
+    } else {
+        let cpuinfo = std::fs::read_to_string("/proc/cpuinfo").ok()?;
+        cpuinfo
+            .lines()
+            .find(|l| l.starts_with("model name"))?
+            .split_once(':')?
+            .1
+            .to_string()
+    };
+    Some(name.trim().to_string()).filter(|n| !n.is_empty())
+}
+
+fn total_memory_bytes() -> Option<u64> {
+    if cfg!(target_os = "macos") {
+        command_stdout("sysctl", &["-n", "hw.memsize"])?.trim().parse().ok()
+    } else if cfg!(target_os = "windows") {
+        command_stdout(
+            "powershell",
+            &["-NoProfile", "-Command", "(Get-CimInstance Win32_ComputerSystem).TotalPhysicalMemory"],
+        )?
+        .trim()
+        .parse()
+        .ok()
+    } else {
+        // "MemTotal:       16314508 kB"
+        let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+        let kb: u64 = meminfo
+            .lines()
+            .find(|l| l.starts_with("MemTotal:"))?
+            .split_whitespace()
+            .nth(1)?
+            .parse()
+            .ok()?;
+        Some(kb * 1024)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clinfo_skips_cpu_devices() {
+        let stdout = "\
+Number of platforms                               2
+  Platform Name                                   Portable Computing Language
+  Device Name                                     pthread-Intel(R) Core(TM) i7-8550U CPU @ 1.80GHz
+  Device Type                                     CPU
+  Platform Name                                   Intel(R) OpenCL HD Graphics
+  Device Name                                     Intel(R) UHD Graphics 620
+  Device Type                                     GPU
+NULL platform behavior
+  clCreateContextFromType(NULL, CL_DEVICE_TYPE_DEFAULT)  Success (1)
+    Device Name                                   pthread-Intel(R) Core(TM) i7-8550U CPU @ 1.80GHz
+";
+        let devices = parse_clinfo(stdout);
+        assert_eq!(devices.len(), 1);
+        assert_eq!(devices[0].name, "Intel(R) UHD Graphics 620");
+    }
+
+    #[test]
+    fn gpu_profile_needs_whisper_support() {
+        let mut info = HardwareInfo {
+            backend: GpuBackend::Vulkan,
+            whisper_backend: GpuBackend::Cpu,
+            devices: vec![GpuDevice { name: "Radeon".into(), backend: GpuBackend::Vulkan, vram_bytes: None }],
+            cpu_name: None,
+            cpu_features: CpuFeatures { avx2: true, ..CpuFeatures::default() },
+            logical_cores: 16,
+            total_memory_bytes: None,
+            recommended: Recommendations { beam_size: 0, threads: 0, whisper_model: String::new() },
+        };
+        let cpu = recommend(&info);
+        assert_eq!((cpu.threads, cpu.beam_size), (MAX_CPU_THREADS, 2));
+
+        info.whisper_backend = GpuBackend::Vulkan;
+        let gpu = recommend(&info);
+        assert_eq!((gpu.threads, gpu.beam_size), (MAX_GPU_THREADS, 5));
+    }
+}
@@ -3,10 +3,21 @@ use std::sync::Arc;
 use serde::Serialize;
 use tauri::{AppHandle, Emitter};
 mod utils;
-#[path = "audio_processor_cli.rs"]
+mod archive;
+mod diagnostics;
 mod audio_processor;
 mod downloader;
+mod download_manager;
+mod hardware;
+mod logging;
+mod model_pack;
+mod network;
+mod recommender;
+mod settings;
 mod summarizer_cli;
+mod tools;
+mod watch_folder;
+mod workspace;
 
 #[derive(Clone, Serialize)]
 struct ProcessEvent {
@@ -17,33 +28,52 @@ struct ProcessEvent {
 
 #[tauri::command]
 async fn detect_gpu() -> Result<String, String> {
-    let backend = utils::detect_gpu();
+    let backend = tauri::async_runtime::spawn_blocking(|| hardware::detect().backend.as_str())
+        .await
+        .map_err(|e| e.to_string())?;
     Ok(backend.to_string())
 }
 
 #[tauri::command]
-async fn process_audio_file(app: AppHandle, file_path: String, whisper_model: &str) -> Result<String, String> {
+async fn hardware_info() -> Result<hardware::HardwareInfo, String> {
+    tauri::async_runtime::spawn_blocking(|| hardware::detect().clone())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn process_audio_file(
+    app: AppHandle,
+    file_path: String,
+    whisper_model: Option<String>,
+    preprocess: Option<audio_processor::PreprocessOptions>,
+    backend: Option<audio_processor::BackendKind>,
+    remote: Option<audio_processor::RemoteConfig>,
+) -> Result<String, String> {
     let emit: Arc<dyn Fn(&str, &str,  Option<u32>) + Send + Sync> = Arc::new(move |event: &str, step: &str, count: Option<u32>| {
         app.emit("process", ProcessEvent { event: event.into(), step: step.into(), count }).unwrap();
     });
-    let processor = audio_processor::AudioProcessor::new(
-        emit,
-        file_path,
-        whisper_model.to_string()
-    );
+    let whisper_model = whisper_model.unwrap_or_else(|| settings::load().transcription.whisper_model);
+    let mut processor = audio_processor::AudioProcessor::new(emit, file_path, whisper_model);
+    if let Some(preprocess) = preprocess {
+        processor = processor.with_preprocess(preprocess);
+    }
+    if let Some(backend) = backend {
+        processor = processor.with_backend(backend);
+    }
+    if remote.is_some() {
+        processor = processor.with_remote(remote);
+    }
     Ok(processor.process())
 }
 
 #[tauri::command]
-async fn ensure_default_models(app: AppHandle, file_path: String, whisper_model: &str) -> Result<String, String> {
+async fn ensure_default_models(app: AppHandle, file_path: String, whisper_model: Option<String>) -> Result<String, String> {
     let emit: Arc<dyn Fn(&str, &str,  Option<u32>) + Send + Sync> = Arc::new(move |event: &str, step: &str, count: Option<u32>| {
         app.emit("process", ProcessEvent { event: event.into(), step: step.into(), count }).unwrap();
     });
-    let processor = audio_processor::AudioProcessor::new(
-        emit,
-        file_path,
-        whisper_model.to_string()
-    );
+    let whisper_model = whisper_model.unwrap_or_else(|| settings::load().transcription.whisper_model);
+    let processor = audio_processor::AudioProcessor::new(emit, file_path, whisper_model);
     Ok(processor.ensure_default_models())
 }
 
@@ -67,24 +97,329 @@ async fn summarize_transcript(
     transcript: String,
     llm_model: Option<String>,
     output_mode: Option<String>,
-) -> Result<String, String> {
+    template: Option<String>,
+    llm_backend: Option<summarizer_cli::LlmBackendConfig>,
+    language: Option<String>,
+) -> Result<summarizer_cli::SummaryResult, String> {
+    let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
+        Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+            app.emit( "process", ProcessEvent { event: event.into(), step: step.into(), count, }, ).unwrap();
+        });
+
+    summarizer_cli::summarize_transcript(
+        emit,
+        &transcript,
+        llm_model.as_deref(),
+        output_mode.as_deref(),
+        template.as_deref(),
+        llm_backend.as_ref(),
+        language.as_deref(),
+    )
+}
+
+#[tauri::command]
+async fn list_summary_templates() -> Result<Vec<summarizer_cli::templates::SummaryTemplate>, String> {
+    Ok(summarizer_cli::templates::list())
+}
+
+#[tauri::command]
+async fn save_summary_template(
+    template: summarizer_cli::templates::SummaryTemplate,
+) -> Result<summarizer_cli::templates::SummaryTemplate, String> {
+    summarizer_cli::templates::save(&template)
+}
+
+#[tauri::command]
+async fn delete_summary_template(id: String) -> Result<(), String> {
+    summarizer_cli::templates::delete(&id)
+}
+
+#[tauri::command]
+async fn import_summary_template(path: String) -> Result<summarizer_cli::templates::SummaryTemplate, String> {
+    summarizer_cli::templates::import(&path)
+}
+
+#[tauri::command]
+async fn chat_with_transcript(
+    app: AppHandle,
+    transcript_id: String,
+    segments: Vec<audio_processor::TranscriptSegment>,
+    question: String,
+    llm_model: Option<String>,
+    llm_backend: Option<summarizer_cli::LlmBackendConfig>,
+) -> Result<summarizer_cli::chat::ChatTurn, String> {
+    let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
+        Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+            app.emit( "process", ProcessEvent { event: event.into(), step: step.into(), count, }, ).unwrap();
+        });
+
+    summarizer_cli::chat::ask(
+        emit,
+        &transcript_id,
+        &segments,
+        &question,
+        llm_model.as_deref(),
+        llm_backend.as_ref(),
+    )
+}
+
+#[tauri::command]
+async fn get_chat_history(transcript_id: String) -> Result<Vec<summarizer_cli::chat::ChatTurn>, String> {
+    Ok(summarizer_cli::chat::history(&transcript_id))
+}
+
+#[tauri::command]
+async fn clear_chat_history(transcript_id: String) -> Result<(), String> {
+    summarizer_cli::chat::clear_history(&transcript_id)
+}
+
+#[tauri::command]
+async fn index_transcript(
+    app: AppHandle,
+    source: String,
+    segments: Vec<audio_processor::TranscriptSegment>,
+    embedding_model: Option<String>,
+) -> Result<summarizer_cli::search::IndexStats, String> {
+    let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
+        Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+            app.emit( "process", ProcessEvent { event: event.into(), step: step.into(), count, }, ).unwrap();
+        });
+
+    summarizer_cli::search::index_transcript(emit, &source, &segments, embedding_model.as_deref())
+}
+
+#[tauri::command]
+async fn semantic_search(
+    app: AppHandle,
+    query: String,
+    limit: Option<usize>,
+    embedding_model: Option<String>,
+) -> Result<Vec<summarizer_cli::search::SearchHit>, String> {
+    let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
+        Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+            app.emit( "process", ProcessEvent { event: event.into(), step: step.into(), count, }, ).unwrap();
+        });
+
+    summarizer_cli::search::semantic_search(emit, &query, limit, embedding_model.as_deref())
+}
+
+#[tauri::command]
+async fn remove_from_search_index(source: String) -> Result<(), String> {
+    summarizer_cli::search::remove_from_index(&source)
+}
+
+#[tauri::command]
+async fn detect_chapters(
+    app: AppHandle,
+    segments: Vec<audio_processor::TranscriptSegment>,
+    llm_model: Option<String>,
+    llm_backend: Option<summarizer_cli::LlmBackendConfig>,
+) -> Result<summarizer_cli::chapters::ChapterList, String> {
     let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
         Arc::new(move |event: &str, step: &str, count: Option<u32>| {
             app.emit( "process", ProcessEvent { event: event.into(), step: step.into(), count, }, ).unwrap();
         });
 
-    summarizer_cli::summarize_transcript(emit, &transcript, llm_model.as_deref(), output_mode.as_deref())
+    summarizer_cli::chapters::detect_chapters(emit, &segments, llm_model.as_deref(), llm_backend.as_ref())
+}
+
+#[tauri::command]
+async fn export_chapters(
+    chapters: Vec<summarizer_cli::chapters::Chapter>,
+    duration_ms: u64,
+    format: summarizer_cli::chapters::ChapterFormat,
+) -> Result<String, String> {
+    Ok(summarizer_cli::chapters::export_chapters(&chapters, duration_ms, format))
+}
+
+#[tauri::command]
+async fn list_llm_models() -> Result<Vec<summarizer_cli::models::LocalModel>, String> {
+    Ok(summarizer_cli::models::list_models())
+}
+
+#[tauri::command]
+async fn list_gguf_quantizations(repo: String) -> Result<Vec<summarizer_cli::models::GgufFile>, String> {
+    summarizer_cli::models::list_quantizations(&repo)
+}
+
+#[tauri::command]
+async fn import_llm_model(path: String, name: Option<String>) -> Result<summarizer_cli::models::LocalModel, String> {
+    summarizer_cli::models::import_model(&path, name.as_deref())
+}
+
+#[tauri::command]
+async fn forget_llm_model(name: String) -> Result<(), String> {
+    summarizer_cli::models::forget_model(&name)
+}
+
+#[tauri::command]
+async fn list_whisper_models() -> Result<Vec<audio_processor::whisper_models::WhisperModelInfo>, String> {
+    Ok(audio_processor::whisper_models::list_models())
+}
+
+/// Sólo modelos ggml (.bin); whisper.cpp no carga modelos de whisper en GGUF.
+#[tauri::command]
+async fn import_whisper_model(path: String, name: String) -> Result<audio_processor::whisper_models::WhisperModelInfo, String> {
+    audio_processor::whisper_models::import_model(&path, &name)
+}
+
+#[tauri::command]
+async fn forget_whisper_model(name: String) -> Result<(), String> {
+    audio_processor::whisper_models::forget_model(&name)
+}
+
+#[tauri::command]
+async fn get_network_config() -> Result<network::NetworkConfig, String> {
+    Ok(network::load_config())
+}
+
+#[tauri::command]
+async fn set_network_config(config: network::NetworkConfig) -> Result<network::NetworkConfig, String> {
+    network::save_config(&config)
+}
+
+#[tauri::command]
+async fn export_model_pack(app: AppHandle, path: String) -> Result<model_pack::ModelPackManifest, String> {
+    let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
+        Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+            app.emit("process", ProcessEvent { event: event.into(), step: step.into(), count }).unwrap();
+        });
+    tauri::async_runtime::spawn_blocking(move || model_pack::export_pack(emit, &path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn import_model_pack(app: AppHandle, path: String) -> Result<model_pack::ModelPackManifest, String> {
+    let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
+        Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+            app.emit("process", ProcessEvent { event: event.into(), step: step.into(), count }).unwrap();
+        });
+    tauri::async_runtime::spawn_blocking(move || model_pack::import_pack(emit, &path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn list_tools() -> Result<Vec<tools::ToolStatus>, String> {
+    tauri::async_runtime::spawn_blocking(tools::status_all)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn ensure_tool(app: AppHandle, tool: tools::Tool) -> Result<tools::ToolStatus, String> {
+    let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
+        Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+            app.emit("process", ProcessEvent { event: event.into(), step: step.into(), count }).unwrap();
+        });
+    tauri::async_runtime::spawn_blocking(move || tools::ensure(&*emit, tool).map(|_| tools::status(tool)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn update_ytdlp(app: AppHandle) -> Result<tools::ToolStatus, String> {
+    let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
+        Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+            app.emit("process", ProcessEvent { event: event.into(), step: step.into(), count }).unwrap();
+        });
+    tauri::async_runtime::spawn_blocking(move || tools::update_ytdlp(&*emit))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn diagnostics(app: AppHandle) -> Result<diagnostics::DiagnosticsReport, String> {
+    let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
+        Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+            app.emit("process", ProcessEvent { event: event.into(), step: step.into(), count }).unwrap();
+        });
+    tauri::async_runtime::spawn_blocking(move || diagnostics::run(emit))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn export_logs(path: String) -> Result<String, String> {
+    tauri::async_runtime::spawn_blocking(move || logging::export(&path))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn get_settings() -> Result<settings::Settings, String> {
+    Ok(settings::load())
+}
+
+#[tauri::command]
+async fn update_settings(patch: serde_json::Value) -> Result<settings::Settings, String> {
+    tauri::async_runtime::spawn_blocking(move || settings::update(patch))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn recommend_models(
+    file_path: Option<String>,
+    audio_secs: Option<f64>,
+    budget_secs: Option<f64>,
+) -> Result<recommender::Recommendation, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let audio_secs = match (file_path, audio_secs) {
+            (Some(path), _) => audio_processor::audio_decoder::duration_secs(&path).map_err(|e| e.to_string())?,
+            (None, Some(secs)) => secs,
+            (None, None) => return Err("Indica un archivo o la duración del audio".to_string()),
+        };
+        let budget_secs = budget_secs.or(settings::load().transcription.time_budget_secs);
+        Ok(recommender::recommend(audio_secs, budget_secs))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+async fn watch_folder_status() -> Result<watch_folder::WatchStatus, String> {
+    tauri::async_runtime::spawn_blocking(watch_folder::status)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+async fn reprocess_watched_file(path: String) -> Result<(), String> {
+    watch_folder::forget(&path)
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    logging::init();
+    std::thread::spawn(workspace::sweep_orphans);
     tauri::Builder::default()
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_updater::Builder::new().build())
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
-        .invoke_handler(tauri::generate_handler![detect_gpu, process_audio_file, ensure_default_models, summarize_transcript, download_audio])
+        .setup(|app| {
+            let handle = app.handle().clone();
+            let emit: Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync> =
+                Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+                    let _ = handle.emit("process", ProcessEvent { event: event.into(), step: step.into(), count });
+                });
+            watch_folder::start(emit);
+            Ok(())
+        })
+        .invoke_handler(tauri::generate_handler![
+            detect_gpu, hardware_info, process_audio_file, ensure_default_models, summarize_transcript, download_audio,
+            list_summary_templates, save_summary_template, delete_summary_template, import_summary_template,
+            chat_with_transcript, get_chat_history, clear_chat_history,
+            index_transcript, semantic_search, remove_from_search_index,
+            detect_chapters, export_chapters,
+            list_llm_models, list_gguf_quantizations, import_llm_model, forget_llm_model,
+            list_whisper_models, import_whisper_model, forget_whisper_model,
+            get_network_config, set_network_config, export_model_pack, import_model_pack,
+            list_tools, ensure_tool, update_ytdlp, diagnostics, export_logs,
+            recommend_models, get_settings, update_settings, watch_folder_status, reprocess_watched_file])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
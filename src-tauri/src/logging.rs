@@ -0,0 +1,212 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+const LOG_FILE: &str = "beautiful-stt.log";
+const MAX_FILE_BYTES: u64 = 5 * 1024 * 1024;
+/// Archivos rotados que se conservan además del actual (`beautiful-stt.1.log`, ...).
+const KEEP_ROTATED: usize = 4;
+/// `BEAUTIFUL_STT_LOG=info,beautiful_stt_lib::summarizer_cli=debug,llama-completion=warn`
+const FILTER_ENV: &str = "BEAUTIFUL_STT_LOG";
+/// Por defecto el archivo guarda todo (incluida la salida de whisper-cli y llama.cpp)
+/// y la consola sólo `info` en adelante, como los `println!` de antes.
+const DEFAULT_FILTER: &str = "debug";
+const CONSOLE_LEVEL: Level = Level::Info;
+
+/// Targets de la salida de error de los procesos hijos.
+pub const WHISPER_CLI: &str = "whisper-cli";
+pub const LLAMA_CPP: &str = "llama-completion";
+pub const YT_DLP: &str = "yt-dlp";
+
+struct Filter {
+    default: LevelFilter,
+    /// Prefijos de target con su nivel; gana el más largo que coincida.
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl Filter {
+    fn parse(spec: &str) -> Filter {
+        let mut filter = Filter { default: LevelFilter::Info, targets: Vec::new() };
+        for part in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((target, level)) => {
+                    if let Ok(level) = level.trim().parse() {
+                        filter.targets.push((target.trim().to_string(), level));
+                    }
+                }
+                None => {
+                    if let Ok(level) = part.parse() {
+                        filter.default = level;
+                    }
+                }
+            }
+        }
+        filter.targets.sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        filter
+    }
+
+    fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(prefix, _)| target.starts_with(prefix.as_str()))
+            .map(|(_, level)| *level)
+            .unwrap_or(self.default)
+    }
+
+    fn max_level(&self) -> LevelFilter {
+        self.targets.iter().map(|(_, level)| *level).fold(self.default, Ord::max)
+    }
+}
+
+struct LogFile {
+    file: Option<File>,
+    size: u64,
+}
+
+struct Logger {
+    filter: Filter,
+    dir: PathBuf,
+    output: Mutex<LogFile>,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!("{} {:<5} {}: {}\n", timestamp(), record.level(), record.target(), record.args());
+        if record.level() <= CONSOLE_LEVEL {
+            print!("{}", line);
+        }
+
+        let mut output = self.output.lock().unwrap_or_else(|e| e.into_inner());
+        if output.size + line.len() as u64 > MAX_FILE_BYTES {
+            output.file = None;
+            rotate(&self.dir);
+            output.size = 0;
+        }
+        if output.file.is_none() {
+            output.file = OpenOptions::new().create(true).append(true).open(self.dir.join(LOG_FILE)).ok();
+        }
+        if let Some(file) = output.file.as_mut() {
+            if file.write_all(line.as_bytes()).is_ok() {
+                output.size += line.len() as u64;
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Some(file) = self.output.lock().unwrap_or_else(|e| e.into_inner()).file.as_mut() {
+            let _ = file.flush();
+        }
+    }
+}
+
+pub fn logs_dir() -> PathBuf {
+    crate::utils::models_base_dir().join("logs")
+}
+
+/// Instala el logger global. Llamar una sola vez al arrancar.
+pub fn init() {
+    let dir = logs_dir();
+    let _ = std::fs::create_dir_all(&dir);
+    let spec = std::env::var(FILTER_ENV).unwrap_or_else(|_| DEFAULT_FILTER.to_string());
+    let filter = Filter::parse(&spec);
+    let max_level = filter.max_level();
+    let size = std::fs::metadata(dir.join(LOG_FILE)).map(|m| m.len()).unwrap_or(0);
+    let logger = Logger { filter, dir, output: Mutex::new(LogFile { file: None, size }) };
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(max_level);
+    }
+    log::info!("beautiful-stt {} — logs en {}", env!("CARGO_PKG_VERSION"), logs_dir().display());
+}
+
+fn rotated_path(dir: &std::path::Path, n: usize) -> PathBuf {
+    dir.join(format!("beautiful-stt.{}.log", n))
+}
+
+fn rotate(dir: &std::path::Path) {
+    let _ = std::fs::remove_file(rotated_path(dir, KEEP_ROTATED));
+    for n in (1..KEEP_ROTATED).rev() {
+        let _ = std::fs::rename(rotated_path(dir, n), rotated_path(dir, n + 1));
+    }
+    let _ = std::fs::rename(dir.join(LOG_FILE), rotated_path(dir, 1));
+}
+
+/// Lee la salida de error de un proceso hijo línea por línea y la registra con `target`.
+/// Devuelve las líneas para quien necesite mostrarlas (p. ej. el final ante un error).
+pub fn capture_stderr(stderr: impl Read + Send + 'static, target: &'static str) -> std::thread::JoinHandle<Vec<String>> {
+    std::thread::spawn(move || {
+        let mut lines = Vec::new();
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if !line.trim().is_empty() {
+                log::debug!(target: target, "{}", line);
+                lines.push(line);
+            }
+        }
+        lines
+    })
+}
+
+/// Igual que `capture_stderr` para procesos ejecutados con `.output()`.
+pub fn log_output(target: &'static str, bytes: &[u8]) {
+    for line in String::from_utf8_lossy(bytes).lines().filter(|l| !l.trim().is_empty()) {
+        log::debug!(target: target, "{}", line);
+    }
+}
+
+/// Junta los logs (del más antiguo al actual) en un solo archivo para adjuntar a un reporte.
+pub fn export(dest: &str) -> Result<String, String> {
+    log::logger().flush();
+    let dir = logs_dir();
+    let mut sources: Vec<PathBuf> = (1..=KEEP_ROTATED).rev().map(|n| rotated_path(&dir, n)).collect();
+    sources.push(dir.join(LOG_FILE));
+
+    let mut out = File::create(dest).map_err(|e| format!("No se pudo crear {}: {}", dest, e))?;
+    let mut written = false;
+    for source in sources.iter().filter(|p| p.exists()) {
+        let mut file = File::open(source).map_err(|e| e.to_string())?;
+        std::io::copy(&mut file, &mut out).map_err(|e| format!("Error exportando logs: {}", e))?;
+        written = true;
+    }
+    if !written {
+        return Err("Todavía no hay logs".into());
+    }
+    log::info!("logs exportados a {}", dest);
+    Ok(dest.to_string())
+}
+
+/// UTC en formato RFC 3339 con milisegundos, sin depender de una crate de fechas.
+fn timestamp() -> String {
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    // civil_from_days (Howard Hinnant)
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        (rem / 60) % 60,
+        rem % 60,
+        now.subsec_millis()
+    )
+}
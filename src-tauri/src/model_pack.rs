@@ -0,0 +1,207 @@
+use std::path::{Component, Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::audio_processor::EmitType;
+
+const MANIFEST_NAME: &str = "model_pack.json";
+const PACK_FORMAT: u32 = 1;
+
+/// Contenido del directorio de datos que se puede llevar a otra máquina: modelos de
+/// whisper y VAD (`*.bin` en la raíz), modelos de otros repos, LLMs, binarios de
+/// llama.cpp y plantillas. Los registros de modelos importados apuntan a rutas locales
+/// y no se incluyen.
+const PACK_DIRS: &[&str] = &["whisper_models", "llm_models", "bin", "templates"];
+const PACK_EXCLUDED: &[&str] = &["llm_models/imported.json"];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PackFile {
+    /// Ruta relativa al directorio de datos, con `/` como separador.
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ModelPackManifest {
+    pub format: u32,
+    pub created_at: u64,
+    pub files: Vec<PackFile>,
+}
+
+/// Empaqueta los modelos descargados en un `.tar` para aprovisionar equipos sin red.
+/// Sin comprimir: los modelos ya están cuantizados y gzip sólo añadiría tiempo.
+pub fn export_pack(emit: EmitType, dest: &str) -> Result<ModelPackManifest, String> {
+    let base = crate::utils::models_base_dir();
+    let files = collect_files(&base);
+    if files.is_empty() {
+        return Err("No hay modelos descargados para exportar".into());
+    }
+    let manifest = ModelPackManifest {
+        format: PACK_FORMAT,
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        files,
+    };
+    let total_bytes: u64 = manifest.files.iter().map(|f| f.size).sum::<u64>().max(1);
+    log::info!("export model pack dest={} files={} bytes={}", dest, manifest.files.len(), total_bytes);
+
+    let file = std::fs::File::create(dest).map_err(|e| format!("No se pudo crear {}: {}", dest, e))?;
+    let mut builder = tar::Builder::new(file);
+    let manifest_json = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest_json.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    let written = builder
+        .append_data(&mut header, MANIFEST_NAME, manifest_json.as_slice())
+        .and_then(|_| {
+            let mut done: u64 = 0;
+            for entry in &manifest.files {
+                emit(
+                    "process_download_assets",
+                    &format!("Exportando {}", entry.path),
+                    Some((done as f64 / total_bytes as f64 * 100.0) as u32),
+                );
+                builder.append_path_with_name(base.join(&entry.path), &entry.path)?;
+                done += entry.size;
+            }
+            builder.finish()
+        });
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(dest);
+        return Err(format!("Error exportando el paquete de modelos: {}", e));
+    }
+
+    emit("process_download_assets", "Paquete de modelos exportado", Some(100));
+    Ok(manifest)
+}
+
+/// Extrae un paquete en el directorio de datos. Los archivos que ya existen con el mismo
+/// tamaño se omiten, así que reimportar un paquete grande es rápido.
+pub fn import_pack(emit: EmitType, src: &str) -> Result<ModelPackManifest, String> {
+    let base = crate::utils::models_base_dir();
+    let file = std::fs::File::open(src).map_err(|e| format!("No se pudo abrir {}: {}", src, e))?;
+    let mut archive = tar::Archive::new(file);
+    let mut entries = archive.entries().map_err(|e| format!("Paquete inválido: {}", e))?;
+
+    let manifest: ModelPackManifest = {
+        let mut first = entries
+            .next()
+            .ok_or("El paquete está vacío")?
+            .map_err(|e| format!("Paquete inválido: {}", e))?;
+        let is_manifest = first.path().map(|p| p == Path::new(MANIFEST_NAME)).unwrap_or(false);
+        if !is_manifest {
+            return Err(format!("{} no es un paquete de modelos (falta {})", src, MANIFEST_NAME));
+        }
+        serde_json::from_reader(&mut first).map_err(|e| format!("Manifiesto inválido: {}", e))?
+    };
+    if manifest.format > PACK_FORMAT {
+        return Err(format!("Formato de paquete {} no soportado; actualiza la aplicación", manifest.format));
+    }
+    let total_bytes: u64 = manifest.files.iter().map(|f| f.size).sum::<u64>().max(1);
+
+    let mut done: u64 = 0;
+    for entry in entries {
+        let mut entry = entry.map_err(|e| format!("Paquete dañado: {}", e))?;
+        // Ni enlaces ni dispositivos: un symlink podría redirigir escrituras posteriores
+        if entry.header().entry_type() != tar::EntryType::Regular {
+            continue;
+        }
+        let rel = entry.path().map_err(|e| format!("Paquete dañado: {}", e))?.into_owned();
+        let dest = safe_join(&base, &rel).ok_or_else(|| format!("Ruta no permitida en el paquete: {}", rel.display()))?;
+        let size = entry.size();
+        // Sólo lo declarado en el manifiesto y dentro de lo que exporta `collect_files`: un
+        // paquete no puede pisar configuración (settings.json, network.json...) ni colar archivos
+        let listed = manifest.files.iter().any(|f| Path::new(&f.path) == rel && f.size == size);
+        if !listed || !is_packable(&rel) {
+            log::warn!("import model pack: se ignora {} (fuera del manifiesto o no permitido)", rel.display());
+            continue;
+        }
+        emit(
+            "process_download_assets",
+            &format!("Importando {}", rel.display()),
+            Some((done as f64 / total_bytes as f64 * 100.0) as u32),
+        );
+        done += size;
+
+        if std::fs::metadata(&dest).map(|m| m.len() == size).unwrap_or(false) {
+            log::debug!("import model pack skip (ya existe) {}", rel.display());
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        entry
+            .unpack(&dest)
+            .map_err(|e| format!("Error extrayendo {}: {}", rel.display(), e))?;
+    }
+
+    log::info!("import model pack src={} files={}", src, manifest.files.len());
+    emit("process_download_assets", "Paquete de modelos importado", Some(100));
+    Ok(manifest)
+}
+
+/// Mismo criterio que `collect_files`: `*.bin` en la raíz o algo dentro de `PACK_DIRS`.
+fn is_packable(rel: &Path) -> bool {
+    let parts: Vec<String> = rel.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+    let joined = parts.join("/");
+    if PACK_EXCLUDED.contains(&joined.as_str()) {
+        return false;
+    }
+    match parts.as_slice() {
+        [_] => rel.extension().and_then(|e| e.to_str()) == Some("bin"),
+        [dir, ..] => PACK_DIRS.contains(&dir.as_str()),
+        [] => false,
+    }
+}
+
+fn collect_files(base: &Path) -> Vec<PackFile> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(base)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.is_file() && p.extension().and_then(|e| e.to_str()) == Some("bin"))
+                .collect()
+        })
+        .unwrap_or_default();
+    for dir in PACK_DIRS {
+        walk(&base.join(dir), &mut paths);
+    }
+
+    let mut files: Vec<PackFile> = paths
+        .into_iter()
+        .filter_map(|path| {
+            let rel = path.strip_prefix(base).ok()?;
+            let rel = rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/");
+            if PACK_EXCLUDED.contains(&rel.as_str()) {
+                return None;
+            }
+            Some(PackFile { size: std::fs::metadata(&path).ok()?.len(), path: rel })
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+fn walk(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            walk(&path, out);
+        } else if path.is_file() {
+            out.push(path);
+        }
+    }
+}
+
+/// Sólo rutas relativas sin `..`: un paquete nunca escribe fuera del directorio de datos.
+pub(crate) fn safe_join(base: &Path, rel: &Path) -> Option<PathBuf> {
+    if rel.as_os_str().is_empty() || !rel.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    Some(base.join(rel))
+}
@@ -0,0 +1,358 @@
+use std::path::Path;
+use serde::{Deserialize, Serialize};
+
+use crate::hardware::{GpuBackend, HardwareInfo};
+
+const GIB: f64 = 1_073_741_824.0;
+const TIMINGS_FILE: &str = "job_timings.json";
+const MAX_TIMINGS: usize = 200;
+/// Trabajos recientes del mismo modelo que se usan para corregir la estimación.
+const CALIBRATION_SAMPLES: usize = 10;
+
+/// Una transcripción y un resumen pueden terminar a la vez; sin esto uno pisa el registro del otro.
+static TIMINGS_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+/// Modelos de whisper que ofrece la app: (archivo, bytes, factor de tiempo real en CPU, calidad).
+/// El factor es segundos de proceso por segundo de audio con AVX2 y 8 hilos.
+const WHISPER_CATALOG: &[(&str, u64, f64, f64)] = &[
+    ("ggml-tiny.bin", 77_700_000, 0.04, 1.0),
+    ("ggml-base.bin", 148_000_000, 0.08, 2.0),
+    ("ggml-small-q8_0.bin", 264_000_000, 0.18, 3.0),
+    ("ggml-small.bin", 488_000_000, 0.25, 4.0),
+    ("ggml-medium.bin", 1_530_000_000, 0.80, 5.0),
+    ("ggml-large-v3-turbo.bin", 1_620_000_000, 0.60, 6.0),
+    ("ggml-large-v3.bin", 3_100_000_000, 1.60, 7.0),
+];
+/// Factor de tiempo real por GB para modelos importados que no están en el catálogo.
+const WHISPER_RTF_PER_GB: f64 = 0.5;
+/// Búferes de cómputo de whisper.cpp además de los pesos.
+const WHISPER_OVERHEAD_BYTES: u64 = 300 * 1024 * 1024;
+
+/// LLMs que ofrece la app con su tamaño aproximado en bytes.
+const LLM_CATALOG: &[(&str, u64)] = &[
+    ("Llama-3.2-3B-Instruct-Q4_K_M.gguf", 2_020_000_000),
+    ("Qwen_Qwen3.5-4B-Q4_K_S.gguf", 2_850_000_000),
+    ("Qwen_Qwen3.5-4B-Q6_K.gguf", 3_810_000_000),
+    ("Qwen_Qwen3.5-4B-Q8_0.gguf", 4_620_000_000),
+];
+/// KV cache de un contexto de 8192 tokens más búferes.
+const LLM_OVERHEAD_BYTES: u64 = 1024 * 1024 * 1024;
+/// ~150 palabras por minuto de habla.
+const SPEECH_CHARS_PER_SEC: f64 = 15.0;
+const CHARS_PER_TOKEN: f64 = 3.5;
+/// Tokens de entrada por llamada antes de que el resumen pase a procesarse por secciones.
+const LLM_CHUNK_TOKENS: f64 = 7000.0;
+const LLM_TOKENS_PER_CHUNK_OUTPUT: f64 = 180.0;
+const LLM_FINAL_OUTPUT_TOKENS: f64 = 800.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobKind {
+    Transcription,
+    Summary,
+}
+
+impl JobKind {
+    /// La transcripción corre en whisper-cli y el resumen en llama.cpp: cada uno con la
+    /// aceleración con la que se compiló su binario.
+    fn backend(self, hardware: &HardwareInfo) -> GpuBackend {
+        match self {
+            JobKind::Transcription => hardware.whisper_backend,
+            JobKind::Summary => hardware.backend,
+        }
+    }
+}
+
+/// Un trabajo terminado: cuánto se estimó con la fórmula base y cuánto tardó de verdad.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+struct JobTiming {
+    kind: JobKind,
+    model: String,
+    backend: String,
+    estimated_secs: f64,
+    actual_secs: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelEstimate {
+    pub name: String,
+    pub installed: bool,
+    pub size_bytes: u64,
+    pub estimated_secs: f64,
+    pub memory_bytes: u64,
+    pub fits_memory: bool,
+    pub within_budget: bool,
+    /// true si la estimación se corrigió con tiempos de trabajos anteriores.
+    pub calibrated: bool,
+    #[serde(skip)]
+    quality: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Recommendation {
+    pub audio_secs: f64,
+    /// Presupuesto aplicado a la transcripción; sin presupuesto se usa la duración del audio.
+    pub budget_secs: f64,
+    pub backend: String,
+    pub whisper: Vec<ModelEstimate>,
+    pub llm: Vec<ModelEstimate>,
+    pub whisper_model: Option<String>,
+    /// El resumen usa lo que sobre del presupuesto después de la transcripción.
+    pub llm_model: Option<String>,
+}
+
+/// Estima tiempo y memoria de cada modelo para `audio_secs` de audio y elige el de mejor
+/// calidad que cabe en memoria y termina dentro de `budget_secs`.
+pub fn recommend(audio_secs: f64, budget_secs: Option<f64>) -> Recommendation {
+    let hardware = crate::hardware::detect();
+    let timings = load_timings();
+    let budget_secs = budget_secs.filter(|b| *b > 0.0).unwrap_or(audio_secs);
+
+    let whisper: Vec<ModelEstimate> = whisper_candidates()
+        .into_iter()
+        .map(|(name, installed, size, rtf, quality)| {
+            let baseline = whisper_secs(hardware, rtf, audio_secs);
+            let (estimated_secs, calibrated) = calibrate(&timings, JobKind::Transcription, &name, hardware, baseline);
+            let memory_bytes = size + WHISPER_OVERHEAD_BYTES;
+            ModelEstimate {
+                name,
+                installed,
+                size_bytes: size,
+                estimated_secs,
+                memory_bytes,
+                fits_memory: fits_memory(hardware, memory_bytes, hardware.whisper_backend),
+                within_budget: estimated_secs <= budget_secs,
+                calibrated,
+                quality,
+            }
+        })
+        .collect();
+    let whisper_model = pick(&whisper);
+
+    let transcription_secs = whisper_model
+        .as_ref()
+        .and_then(|name| whisper.iter().find(|m| &m.name == name))
+        .map(|m| m.estimated_secs)
+        .unwrap_or(0.0);
+    let llm_budget = budget_secs - transcription_secs;
+    let transcript_chars = (audio_secs * SPEECH_CHARS_PER_SEC) as usize;
+    let llm: Vec<ModelEstimate> = llm_candidates()
+        .into_iter()
+        .map(|(name, installed, size)| {
+            let baseline = llm_secs(hardware, size, transcript_chars);
+            let (estimated_secs, calibrated) = calibrate(&timings, JobKind::Summary, &name, hardware, baseline);
+            let memory_bytes = size + LLM_OVERHEAD_BYTES;
+            ModelEstimate {
+                name,
+                installed,
+                size_bytes: size,
+                estimated_secs,
+                memory_bytes,
+                // llama.cpp reparte capas entre GPU y CPU; basta con que quepa en RAM
+                fits_memory: fits_memory(hardware, memory_bytes, GpuBackend::Cpu),
+                within_budget: estimated_secs <= llm_budget,
+                calibrated,
+                quality: size as f64,
+            }
+        })
+        .collect();
+    let llm_model = pick(&llm);
+
+    Recommendation {
+        audio_secs,
+        budget_secs,
+        backend: hardware.backend.as_str().to_string(),
+        whisper,
+        llm,
+        whisper_model,
+        llm_model,
+    }
+}
+
+/// El de mejor calidad dentro del presupuesto; si ninguno llega, el más rápido que cabe.
+fn pick(estimates: &[ModelEstimate]) -> Option<String> {
+    let fitting = || estimates.iter().filter(|m| m.fits_memory);
+    fitting()
+        .filter(|m| m.within_budget)
+        .max_by(|a, b| a.quality.total_cmp(&b.quality).then(b.estimated_secs.total_cmp(&a.estimated_secs)))
+        .or_else(|| fitting().min_by(|a, b| a.estimated_secs.total_cmp(&b.estimated_secs)))
+        .map(|m| m.name.clone())
+}
+
+/// `gpu`: donde tiene que caber el modelo entero; con `Cpu` basta la RAM.
+fn fits_memory(hardware: &HardwareInfo, bytes: u64, gpu: GpuBackend) -> bool {
+    let limit = match hardware.vram_bytes() {
+        Some(vram) if !matches!(gpu, GpuBackend::Cpu | GpuBackend::Metal) => Some(vram),
+        // La memoria unificada o la RAM se comparten con el sistema
+        _ => hardware.total_memory_bytes.map(|m| m * 7 / 10),
+    };
+    limit.is_none_or(|limit| bytes <= limit)
+}
+
+// ─── Candidatos ──────────────────────────────────────────────────────────────
+
+/// Catálogo más los modelos instalados o importados: (nombre, instalado, bytes, rtf, calidad).
+fn whisper_candidates() -> Vec<(String, bool, u64, f64, f64)> {
+    let installed = crate::audio_processor::whisper_models::list_models();
+    let mut candidates: Vec<(String, bool, u64, f64, f64)> = WHISPER_CATALOG
+        .iter()
+        .map(|(name, size, rtf, quality)| {
+            let local = installed.iter().find(|m| m.name == *name && m.header.is_some());
+            (name.to_string(), local.is_some(), local.map(|m| m.size).unwrap_or(*size), *rtf, *quality)
+        })
+        .collect();
+    for model in installed.iter().filter(|m| !WHISPER_CATALOG.iter().any(|(name, ..)| *name == m.name)) {
+        let Some(header) = &model.header else { continue };
+        // Más capas del encoder, mejor transcripción: tiny 4 ... large 32
+        let quality = header.n_audio_layer as f64 / 32.0 * 7.0;
+        candidates.push((model.name.clone(), true, model.size, model.size as f64 / GIB * WHISPER_RTF_PER_GB, quality));
+    }
+    candidates
+}
+
+fn llm_candidates() -> Vec<(String, bool, u64)> {
+    let installed: Vec<_> = crate::summarizer_cli::models::list_models()
+        .into_iter()
+        // Los modelos de embeddings no sirven para resumir
+        .filter(|m| !m.name.to_lowercase().contains("bge"))
+        .collect();
+    let mut candidates: Vec<(String, bool, u64)> = LLM_CATALOG
+        .iter()
+        .map(|(name, size)| {
+            let local = installed.iter().find(|m| m.name == *name);
+            (name.to_string(), local.is_some(), local.map(|m| m.size).unwrap_or(*size))
+        })
+        .collect();
+    for model in installed.iter().filter(|m| !LLM_CATALOG.iter().any(|(name, _)| *name == m.name)) {
+        candidates.push((model.name.clone(), true, model.size));
+    }
+    candidates
+}
+
+// ─── Estimaciones ────────────────────────────────────────────────────────────
+
+fn gpu_speedup(backend: GpuBackend) -> f64 {
+    match backend {
+        GpuBackend::Cuda => 10.0,
+        GpuBackend::Rocm => 8.0,
+        GpuBackend::Metal => 6.0,
+        GpuBackend::Vulkan => 5.0,
+        GpuBackend::OpenCl => 2.0,
+        GpuBackend::Cpu => 1.0,
+    }
+}
+
+fn whisper_secs(hardware: &HardwareInfo, rtf: f64, audio_secs: f64) -> f64 {
+    let mut factor = rtf / gpu_speedup(hardware.whisper_backend);
+    if hardware.whisper_backend == GpuBackend::Cpu {
+        factor *= (8.0 / hardware.recommended.threads.max(1) as f64).clamp(0.5, 4.0);
+        if !hardware.cpu_features.avx2 && !hardware.cpu_features.neon {
+            factor *= 2.5;
+        }
+    }
+    audio_secs * factor
+}
+
+/// Ancho de banda de memoria efectivo en GB/s: la generación de tokens está limitada por él.
+fn memory_bandwidth(backend: GpuBackend) -> f64 {
+    match backend {
+        GpuBackend::Cuda => 300.0,
+        GpuBackend::Rocm => 250.0,
+        GpuBackend::Vulkan => 150.0,
+        GpuBackend::Metal => 100.0,
+        GpuBackend::OpenCl => 50.0,
+        GpuBackend::Cpu => 30.0,
+    }
+}
+
+fn llm_secs(hardware: &HardwareInfo, model_size: u64, transcript_chars: usize) -> f64 {
+    let size_gb = (model_size as f64 / GIB).max(0.1);
+    // Con descarga parcial a la GPU, la parte en CPU marca el ritmo
+    let offloaded = (hardware.gpu_layers(model_size).min(32) as f64 / 32.0).min(1.0);
+    let bandwidth = memory_bandwidth(GpuBackend::Cpu)
+        + (memory_bandwidth(hardware.backend) - memory_bandwidth(GpuBackend::Cpu)) * offloaded;
+    let generation_tps = bandwidth / size_gb;
+    let prompt_tps = generation_tps * if offloaded > 0.0 { 25.0 } else { 4.0 };
+
+    let input_tokens = transcript_chars as f64 / CHARS_PER_TOKEN;
+    let chunks = (input_tokens / LLM_CHUNK_TOKENS).ceil().max(1.0);
+    let output_tokens = if chunks > 1.0 { chunks * LLM_TOKENS_PER_CHUNK_OUTPUT } else { 0.0 } + LLM_FINAL_OUTPUT_TOKENS;
+    input_tokens / prompt_tps + output_tokens / generation_tps
+}
+
+/// Corrige la estimación base con la mediana de `real / estimado` de trabajos anteriores
+/// del mismo modelo; si no hay, con los del mismo tipo de trabajo en este hardware.
+fn calibrate(timings: &[JobTiming], kind: JobKind, model: &str, hardware: &HardwareInfo, baseline: f64) -> (f64, bool) {
+    let backend = kind.backend(hardware).as_str();
+    let same_kind = || timings.iter().rev().filter(|t| t.kind == kind && t.backend == backend && t.estimated_secs > 0.0);
+    let ratios = |timings: Vec<&JobTiming>| -> Vec<f64> {
+        timings.iter().take(CALIBRATION_SAMPLES).map(|t| t.actual_secs / t.estimated_secs).collect()
+    };
+    let mut ratios_found = ratios(same_kind().filter(|t| t.model == model).collect());
+    if ratios_found.is_empty() {
+        ratios_found = ratios(same_kind().collect());
+    }
+    if ratios_found.is_empty() {
+        return (baseline, false);
+    }
+    ratios_found.sort_by(f64::total_cmp);
+    (baseline * ratios_found[ratios_found.len() / 2], true)
+}
+
+// ─── Historial de tiempos ────────────────────────────────────────────────────
+
+fn timings_path() -> std::path::PathBuf {
+    crate::utils::models_base_dir().join(TIMINGS_FILE)
+}
+
+fn load_timings() -> Vec<JobTiming> {
+    std::fs::read_to_string(timings_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn record(kind: JobKind, model: &str, estimated_secs: f64, actual_secs: f64) {
+    if estimated_secs <= 0.0 || actual_secs <= 0.0 {
+        return;
+    }
+    let _guard = TIMINGS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut timings = load_timings();
+    timings.push(JobTiming {
+        kind,
+        model: model.to_string(),
+        backend: kind.backend(crate::hardware::detect()).as_str().to_string(),
+        estimated_secs,
+        actual_secs,
+    });
+    let excess = timings.len().saturating_sub(MAX_TIMINGS);
+    timings.drain(..excess);
+    let Ok(json) = serde_json::to_string_pretty(&timings) else {
+        return;
+    };
+    // Quien sólo lee para estimar nunca ve el archivo a medio escribir
+    let tmp = timings_path().with_extension("json.tmp");
+    if let Err(e) = std::fs::write(&tmp, json).and_then(|_| std::fs::rename(&tmp, timings_path())) {
+        log::warn!("no se pudo guardar {}: {}", TIMINGS_FILE, e);
+    }
+}
+
+/// Registra una transcripción local terminada para afinar las próximas estimaciones.
+pub fn record_transcription(model: &str, model_path: &Path, audio_secs: f64, actual_secs: f64) {
+    let hardware = crate::hardware::detect();
+    let rtf = WHISPER_CATALOG
+        .iter()
+        .find(|(name, ..)| *name == model)
+        .map(|(_, _, rtf, _)| *rtf)
+        .unwrap_or_else(|| {
+            let size = std::fs::metadata(model_path).map(|m| m.len()).unwrap_or(0);
+            size as f64 / GIB * WHISPER_RTF_PER_GB
+        });
+    record(JobKind::Transcription, model, whisper_secs(hardware, rtf, audio_secs), actual_secs);
+}
+
+/// Registra un resumen hecho con llama-completion.
+pub fn record_summary(model: &str, model_path: &Path, transcript_chars: usize, actual_secs: f64) {
+    let size = std::fs::metadata(model_path).map(|m| m.len()).unwrap_or(0);
+    record(JobKind::Summary, model, llm_secs(crate::hardware::detect(), size, transcript_chars), actual_secs);
+}
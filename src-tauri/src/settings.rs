@@ -0,0 +1,381 @@
+use std::path::PathBuf;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::audio_processor::{BackendKind, PreprocessOptions, RemoteConfig};
+use crate::summarizer_cli::LlmBackendConfig;
+
+const SETTINGS_FILE: &str = "settings.json";
+/// Subir al cambiar la forma del archivo y agregar el paso correspondiente a `MIGRATIONS`.
+pub const SETTINGS_VERSION: u32 = 1;
+const MAX_BEAM_SIZE: u32 = 16;
+
+/// Preferencias de la app. Se guarda en `settings.json`; los campos que faltan toman
+/// su valor por defecto, así un archivo viejo o editado a mano sigue cargando.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub version: u32,
+    pub transcription: TranscriptionSettings,
+    pub summary: SummarySettings,
+    pub download: DownloadSettings,
+    pub watch: WatchSettings,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TranscriptionSettings {
+    pub whisper_model: String,
+    /// Código ISO 639-1 (`es`, `en`...) o `auto` para que whisper lo detecte.
+    pub language: String,
+    pub initial_prompt: String,
+    /// `None` = según el hardware (ver `hardware::Recommendations`).
+    pub beam_size: Option<u32>,
+    pub threads: Option<usize>,
+    /// Filtro de voz (Silero) antes de transcribir.
+    pub vad: bool,
+    pub backend: BackendKind,
+    pub preprocess: PreprocessOptions,
+    pub remote: Option<RemoteConfig>,
+    /// Tiempo máximo deseado para el recomendador de modelos; `None` = la duración del audio.
+    pub time_budget_secs: Option<f64>,
+}
+
+impl Default for TranscriptionSettings {
+    fn default() -> Self {
+        TranscriptionSettings {
+            whisper_model: "ggml-small.bin".to_string(),
+            language: "es".to_string(),
+            initial_prompt: "Transcripción profesional de audio. Contenido formal, sin publicidad, sin menciones a redes sociales ni suscripciones.".to_string(),
+            beam_size: None,
+            threads: None,
+            vad: true,
+            backend: BackendKind::default(),
+            preprocess: PreprocessOptions::default(),
+            remote: None,
+            time_budget_secs: None,
+        }
+    }
+}
+
+impl TranscriptionSettings {
+    pub fn beam_size(&self) -> u32 {
+        self.beam_size.unwrap_or_else(|| crate::hardware::detect().recommended.beam_size)
+    }
+
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(|| crate::hardware::detect().recommended.threads)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SummarySettings {
+    pub llm_model: String,
+    /// Modo de salida (`summary`, `minutes`...); lo ignora una plantilla explícita.
+    pub output_mode: Option<String>,
+    pub template: Option<String>,
+    pub llm_backend: LlmBackendConfig,
+    /// Idioma del resumen (`{language}` en las plantillas); `None` = el de la transcripción.
+    pub language: Option<String>,
+}
+
+impl Default for SummarySettings {
+    fn default() -> Self {
+        SummarySettings {
+            llm_model: crate::summarizer_cli::DEFAULT_LLM_MODEL.to_string(),
+            output_mode: None,
+            template: None,
+            llm_backend: LlmBackendConfig::default(),
+            language: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DownloadSettings {
+    /// Carpeta donde yt-dlp deja los audios; `None` = carpeta temporal del sistema.
+    pub output_dir: Option<String>,
+    /// Selector de formato de yt-dlp.
+    pub format: String,
+    pub max_retries: u32,
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        DownloadSettings {
+            output_dir: None,
+            format: "bestaudio[ext=m4a]/bestaudio[ext=mp3]/bestaudio".to_string(),
+            max_retries: 3,
+        }
+    }
+}
+
+/// Carpetas vigiladas para transcribir automáticamente lo que llegue a ellas.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WatchSettings {
+    pub folders: Vec<WatchFolder>,
+    pub poll_interval_secs: u64,
+    /// Un archivo se procesa cuando su tamaño no cambia durante este tiempo.
+    pub stable_secs: u64,
+}
+
+impl Default for WatchSettings {
+    fn default() -> Self {
+        WatchSettings { folders: Vec::new(), poll_interval_secs: 10, stable_secs: 15 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WatchFolder {
+    pub path: String,
+    pub enabled: bool,
+    pub recursive: bool,
+    pub summarize: bool,
+    /// `None` = los resultados se escriben junto al archivo original.
+    pub output_dir: Option<String>,
+    /// Procesar también lo que ya estaba en la carpeta al agregarla.
+    pub process_existing: bool,
+}
+
+impl Default for WatchFolder {
+    fn default() -> Self {
+        WatchFolder {
+            path: String::new(),
+            enabled: true,
+            recursive: false,
+            summarize: false,
+            output_dir: None,
+            process_existing: false,
+        }
+    }
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            transcription: TranscriptionSettings::default(),
+            summary: SummarySettings::default(),
+            download: DownloadSettings::default(),
+            watch: WatchSettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Comprueba sólo lo que cambia respecto de `previous` y sin tocar el disco: un valor
+    /// guardado que dejó de ser válido (una carpeta vigilada en un disco desmontado) no
+    /// impide cambiar otra cosa.
+    pub fn validate(&self, previous: &Settings) -> Result<(), String> {
+        let (t, old) = (&self.transcription, &previous.transcription);
+        if t.whisper_model != old.whisper_model && t.whisper_model.trim().is_empty() {
+            return Err("Falta el modelo de whisper".into());
+        }
+        let language_ok = t.language == "auto"
+            || ((2..=3).contains(&t.language.len()) && t.language.chars().all(|c| c.is_ascii_lowercase()));
+        if t.language != old.language && !language_ok {
+            return Err(format!("Idioma inválido: '{}' (usa un código como es, en o auto)", t.language));
+        }
+        if let Some(beam_size) = t.beam_size.filter(|_| t.beam_size != old.beam_size) {
+            if !(1..=MAX_BEAM_SIZE).contains(&beam_size) {
+                return Err(format!("beam_size debe estar entre 1 y {}", MAX_BEAM_SIZE));
+            }
+        }
+        if t.threads != old.threads && t.threads == Some(0) {
+            return Err("threads debe ser mayor que 0".into());
+        }
+        let remote_url = |t: &TranscriptionSettings| t.remote.as_ref().map(|r| r.url.trim().to_string());
+        let remote_changed = t.backend != old.backend || remote_url(t) != remote_url(old);
+        if remote_changed && t.backend == BackendKind::Remote && remote_url(t).is_none_or(|url| url.is_empty()) {
+            return Err("El backend remoto necesita la URL del servidor".into());
+        }
+        if t.time_budget_secs != old.time_budget_secs && t.time_budget_secs.is_some_and(|b| b.is_nan() || b <= 0.0) {
+            return Err("El tiempo máximo debe ser mayor que 0".into());
+        }
+
+        let (s, old) = (&self.summary, &previous.summary);
+        if s.llm_model != old.llm_model && s.llm_model.trim().is_empty() {
+            return Err("Falta el modelo LLM".into());
+        }
+        if let Some(mode) = s.output_mode.as_ref().filter(|_| s.output_mode != old.output_mode) {
+            crate::summarizer_cli::OutputMode::parse(Some(mode.as_str()))?;
+        }
+
+        let (d, old) = (&self.download, &previous.download);
+        if d.format != old.format && d.format.trim().is_empty() {
+            return Err("Falta el formato de descarga".into());
+        }
+        if let Some(dir) = d.output_dir.as_ref().filter(|_| d.output_dir != old.output_dir) {
+            check_output_dir(dir).map_err(|e| format!("Carpeta de descargas inválida '{}': {}", dir, e))?;
+        }
+
+        let (w, old) = (&self.watch, &previous.watch);
+        if w.poll_interval_secs == 0 {
+            return Err("poll_interval_secs debe ser mayor que 0".into());
+        }
+        for folder in &w.folders {
+            let before = old.folders.iter().find(|f| f.path == folder.path);
+            if before.is_none() && !std::path::Path::new(&folder.path).is_dir() {
+                return Err(format!("La carpeta vigilada no existe: '{}'", folder.path));
+            }
+            let output_changed = before.is_none_or(|f| f.output_dir != folder.output_dir);
+            if let Some(dir) = folder.output_dir.as_ref().filter(|_| output_changed) {
+                check_output_dir(dir).map_err(|e| format!("Carpeta de resultados inválida '{}': {}", dir, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Una carpeta de salida puede no existir todavía (se crea al escribir), pero no puede ser un archivo.
+fn check_output_dir(dir: &str) -> Result<(), &'static str> {
+    let path = std::path::Path::new(dir);
+    if dir.trim().is_empty() {
+        Err("ruta vacía")
+    } else if path.exists() && !path.is_dir() {
+        Err("es un archivo")
+    } else {
+        Ok(())
+    }
+}
+
+// ─── Persistencia ────────────────────────────────────────────────────────────
+
+fn settings_path() -> PathBuf {
+    crate::utils::models_base_dir().join(SETTINGS_FILE)
+}
+
+/// Pasos de migración: `MIGRATIONS[n]` lleva un archivo de la versión `n` a la `n + 1`.
+const MIGRATIONS: &[fn(&mut Value)] = &[
+    // v0: archivos sin `version`; mismos campos, sólo se marca la versión
+    |_| {},
+];
+
+fn migrate(value: &mut Value) -> bool {
+    let from = value.get("version").and_then(Value::as_u64).unwrap_or(0) as usize;
+    if from >= MIGRATIONS.len() {
+        return false;
+    }
+    for (version, step) in MIGRATIONS.iter().enumerate().skip(from) {
+        log::info!("settings: migrando de v{} a v{}", version, version + 1);
+        step(value);
+    }
+    if let Some(object) = value.as_object_mut() {
+        object.insert("version".into(), Value::from(SETTINGS_VERSION));
+    }
+    true
+}
+
+/// Lee la configuración, migrándola si es de una versión anterior. Un archivo ilegible
+/// se aparta como `settings.json.bak` y se usan los valores por defecto.
+pub fn load() -> Settings {
+    let path = settings_path();
+    let Ok(content) = std::fs::read_to_string(&path) else {
+        return Settings::default();
+    };
+    let parsed = serde_json::from_str::<Value>(&content).and_then(|mut value| {
+        let migrated = migrate(&mut value);
+        serde_json::from_value::<Settings>(value).map(|settings| (settings, migrated))
+    });
+    match parsed {
+        Ok((settings, migrated)) => {
+            if settings.version > SETTINGS_VERSION {
+                log::warn!("settings v{} es más nueva que esta versión de la app", settings.version);
+            }
+            if migrated {
+                if let Err(e) = write(&settings) {
+                    log::warn!("no se pudo guardar la configuración migrada: {}", e);
+                }
+            }
+            settings
+        }
+        Err(e) => {
+            log::warn!("{} inválido, se usan los valores por defecto: {}", SETTINGS_FILE, e);
+            let _ = std::fs::rename(&path, path.with_extension("json.bak"));
+            Settings::default()
+        }
+    }
+}
+
+fn write(settings: &Settings) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    let tmp = settings_path().with_extension("json.tmp");
+    std::fs::write(&tmp, json).map_err(|e| format!("No se pudo guardar la configuración: {}", e))?;
+    std::fs::rename(&tmp, settings_path()).map_err(|e| format!("No se pudo guardar la configuración: {}", e))
+}
+
+/// Aplica un cambio parcial (`{"transcription": {"language": "en"}}`) sobre la
+/// configuración actual; `null` vuelve un campo a su valor por defecto.
+pub fn update(patch: Value) -> Result<Settings, String> {
+    let previous = load();
+    let mut value = serde_json::to_value(&previous).map_err(|e| e.to_string())?;
+    merge(&mut value, patch);
+    let mut settings: Settings = serde_json::from_value(value).map_err(|e| format!("Configuración inválida: {}", e))?;
+    settings.version = SETTINGS_VERSION;
+    settings.validate(&previous)?;
+    write(&settings)?;
+    log::info!("settings actualizados");
+    Ok(settings)
+}
+
+fn merge(target: &mut Value, patch: Value) {
+    match (target, patch) {
+        (Value::Object(target), Value::Object(patch)) => {
+            for (key, value) in patch {
+                if value.is_null() {
+                    target.remove(&key);
+                } else {
+                    merge(target.entry(key).or_insert(Value::Null), value);
+                }
+            }
+        }
+        (target, patch) => *target = patch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_unmounted_folder() -> Settings {
+        let mut settings = Settings::default();
+        settings.watch.folders.push(WatchFolder {
+            path: "/media/usb-que-no-esta/grabaciones".into(),
+            output_dir: Some("/media/usb-que-no-esta/textos".into()),
+            ..WatchFolder::default()
+        });
+        settings
+    }
+
+    #[test]
+    fn unmounted_watch_folder_does_not_block_other_changes() {
+        let previous = with_unmounted_folder();
+        let mut settings = previous.clone();
+        settings.transcription.language = "en".into();
+        assert!(settings.validate(&previous).is_ok());
+    }
+
+    #[test]
+    fn new_folder_and_changed_fields_are_checked() {
+        let previous = Settings::default();
+        assert!(with_unmounted_folder().validate(&previous).unwrap_err().contains("no existe"));
+
+        let mut settings = previous.clone();
+        settings.transcription.language = "español".into();
+        assert!(settings.validate(&previous).is_err());
+    }
+
+    #[test]
+    fn validate_does_not_create_output_dirs() {
+        let previous = Settings::default();
+        let dir = std::env::temp_dir().join(format!("beautiful-stt-settings-test-{}", std::process::id()));
+        let mut settings = previous.clone();
+        settings.download.output_dir = Some(dir.to_string_lossy().into_owned());
+        assert!(settings.validate(&previous).is_ok());
+        assert!(!dir.exists());
+    }
+}
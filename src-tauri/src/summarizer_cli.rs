@@ -1,265 +1,307 @@
 use std::io::Read;
 use std::process::Stdio;
 use std::sync::Arc;
+use serde::Serialize;
+mod chat_template;
+mod gguf;
+mod llm_backend;
+pub mod models;
+mod output_mode;
+pub mod chapters;
+pub mod chat;
+pub mod search;
+pub mod templates;
+
+pub use llm_backend::LlmBackendConfig;
+pub use output_mode::OutputMode;
+use llm_backend::{ChatPrompt, LlmBackend, StreamProgress, MAX_CONTEXT};
+use templates::{render_template, SummaryTemplate};
 
 pub type EmitType = Arc<dyn Fn(&str, &str, Option<u32>) + Send + Sync>;
 
 const LLAMA_VERSION: &str = "b9496";
-const DEFAULT_LLM_MODEL: &str = "Llama-3.2-3B-Instruct-Q4_K_M.gguf";
-const MAX_DIRECT_CHARS: usize = 6000;
-const CHUNK_SIZE: usize = 5000;
-
-const SYSTEM_PROMPT: &str = "Eres un experto en resumir transcripciones de audio en español. \
-    Captura el tema central, los puntos más importantes, y cualquier dato relevante \
-    como nombres propios, cifras, fechas o lugares. \
-    Escribe en español claro y natural, en prosa continua. \
-    Sin encabezados, sin viñetas, sin listas, sin markdown. Solo párrafos de texto. \
-    Corrige implícitamente errores fonéticos de Whisper usando el contexto del texto.";
+pub(crate) const DEFAULT_LLM_MODEL: &str = "Llama-3.2-3B-Instruct-Q4_K_M.gguf";
+/// Contexto cuando ni el backend ni el GGUF lo informan (el `-c` fijo de antes).
+const DEFAULT_CONTEXT: usize = 8192;
+/// Tokens reservados para el prompt de sistema, la plantilla y el error de la estimación.
+const PROMPT_OVERHEAD_TOKENS: usize = 512;
+/// Español con tokenizadores tipo Llama/Qwen; sólo se usa si no hay tokenizador disponible.
+const DEFAULT_CHARS_PER_TOKEN: f32 = 3.5;
+const CALIBRATION_SAMPLE_CHARS: usize = 20_000;
+/// Fracción de cada sección que se repite al inicio de la siguiente para no cortar ideas.
+const CHUNK_OVERLAP: f32 = 0.1;
+const EXTRACTION_MAX_TOKENS: u32 = 180;
+const CONDENSE_MAX_TOKENS: u32 = 400;
+
+const CONDENSE_PROMPT: &str = "Notas de varias secciones consecutivas de una transcripción:\n\n{notes}\n\n\
+    Combínalas en una sola lista de puntos clave en orden, sin repetir ideas y conservando \
+    nombres, cifras y fechas. Puntos clave:";
 
 // ─── Binary management ────────────────────────────────────────────────────────
 
-fn llama_cli_archive_url() -> &'static str {
-    if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
-        "https://github.com/ggml-org/llama.cpp/releases/download/b9496/llama-b9496-bin-macos-arm64.tar.gz"
-    } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
-        "https://github.com/ggml-org/llama.cpp/releases/download/b9496/llama-b9496-bin-macos-x64.tar.gz"
-    } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
-        "https://github.com/ggml-org/llama.cpp/releases/download/b9496/llama-b9496-bin-ubuntu-x64.tar.gz"
-    } else if cfg!(all(target_os = "linux", target_arch = "aarch64")) {
-        "https://github.com/ggml-org/llama.cpp/releases/download/b9496/llama-b9496-bin-ubuntu-arm64.tar.gz"
-    } else if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
-        if crate::utils::detect_gpu() == "CUDA" {
-            "https://github.com/ggml-org/llama.cpp/releases/download/b9496/llama-b9496-bin-win-cuda-12.4-x64.zip"
-        } else {
-            "https://github.com/ggml-org/llama.cpp/releases/download/b9496/llama-b9496-bin-win-cpu-x64.zip"
-        }
+/// Release asset for this platform. `sha256` pins the archive published for `LLAMA_VERSION`;
+/// bump both together. The app never installs a download without a pin: it would otherwise
+/// run whatever binary the download happened to return. Binaries already on disk (an earlier
+/// install, a model pack, a system llama.cpp) are the user's and are used as they are.
+struct LlamaRelease {
+    asset: &'static str,
+    sha256: Option<&'static str>,
+}
+
+/// `None` until the digest of that archive has been checked against the release; those
+/// platforms need a llama.cpp already on disk.
+const LLAMA_RELEASES: &[(&str, LlamaRelease)] = &[
+    ("macos-aarch64", LlamaRelease { asset: "bin-macos-arm64.tar.gz", sha256: None }),
+    ("macos-x86_64", LlamaRelease { asset: "bin-macos-x64.tar.gz", sha256: None }),
+    ("linux-x86_64", LlamaRelease { asset: "bin-ubuntu-x64.tar.gz", sha256: None }),
+    ("linux-aarch64", LlamaRelease { asset: "bin-ubuntu-arm64.tar.gz", sha256: None }),
+    ("windows-x86_64-cuda", LlamaRelease { asset: "bin-win-cuda-12.4-x64.zip", sha256: None }),
+    ("windows-x86_64", LlamaRelease { asset: "bin-win-cpu-x64.zip", sha256: None }),
+];
+
+/// Everything from one release lives here, so an upgrade replaces it as a whole.
+const LLAMA_INSTALL_DIR: &str = "llama.cpp";
+
+/// Serializes provisioning: two commands may ask for the binary at the same time.
+static LLAMA_INSTALL_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[derive(Debug, serde::Deserialize, Serialize)]
+struct LlamaInstall {
+    version: String,
+    asset: String,
+    sha256: String,
+}
+
+fn llama_release() -> Option<&'static LlamaRelease> {
+    let platform = format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH);
+    let platform = if platform == "windows-x86_64" && crate::hardware::detect().backend == crate::hardware::GpuBackend::Cuda {
+        "windows-x86_64-cuda".to_string()
     } else {
-        ""
-    }
+        platform
+    };
+    LLAMA_RELEASES.iter().find(|(p, _)| *p == platform).map(|(_, release)| release)
 }
 
-// macOS/Linux: streaming tar.gz extraction, strips one leading path component
-#[cfg(not(target_os = "windows"))]
-fn extract_tar_gz(
-    reader: impl Read,
-    dest_dir: &std::path::Path,
-) -> Result<(), Box<dyn std::error::Error>> {
-    use flate2::read::GzDecoder;
-    use tar::Archive;
-
-    let gz = GzDecoder::new(reader);
-    let mut archive = Archive::new(gz);
-
-    for entry in archive.entries()? {
-        let mut entry = entry?;
-        let path = entry.path()?.into_owned();
-        // Strip "llama-b9496/" prefix
-        let stripped: std::path::PathBuf = path.components().skip(1).collect();
-        if stripped.as_os_str().is_empty() {
-            continue;
-        }
-        let dest = dest_dir.join(&stripped);
-        if let Some(parent) = dest.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        entry.unpack(&dest)?;
-    }
-    Ok(())
+fn llama_release_url(release: &LlamaRelease) -> String {
+    format!(
+        "https://github.com/ggml-org/llama.cpp/releases/download/{v}/llama-{v}-{asset}",
+        v = LLAMA_VERSION,
+        asset = release.asset
+    )
 }
 
-// Windows: zip extraction (must buffer — ZipArchive requires Seek)
-#[cfg(target_os = "windows")]
-fn extract_zip(
-    reader: impl Read,
-    dest_dir: &std::path::Path,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let mut data = Vec::new();
-    let mut r = reader;
-    r.read_to_end(&mut data)?;
-    let cursor = std::io::Cursor::new(data);
-    let mut archive = zip::ZipArchive::new(cursor)?;
-    for i in 0..archive.len() {
-        let mut file = archive.by_index(i)?;
-        if file.is_dir() {
-            continue;
-        }
-        let name = file.enclosed_name().ok_or("Invalid zip entry")?.to_owned();
-        let dest = dest_dir.join(&name);
-        let mut outfile = std::fs::File::create(&dest)?;
-        std::io::copy(&mut file, &mut outfile)?;
-    }
-    Ok(())
+fn read_llama_install(install_dir: &std::path::Path) -> Option<LlamaInstall> {
+    let content = std::fs::read_to_string(install_dir.join("install.json")).ok()?;
+    serde_json::from_str(&content).ok()
 }
 
-fn ensure_llama_bin(
-    emit: &dyn Fn(&str, &str, Option<u32>),
-) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+fn llama_bin_name() -> &'static str {
     // llama-completion is available on all platforms in b9496+: non-interactive, stdout-clean.
-    let bin_name = if cfg!(target_os = "windows") {
+    if cfg!(target_os = "windows") {
         "llama-completion.exe"
     } else {
         "llama-completion"
-    };
-    let bin_dir = crate::utils::models_base_dir().join("bin");
-    let stored_path = bin_dir.join(bin_name);
+    }
+}
 
-    // Dev: prefer system binary — it has the right library paths for the dev environment
+// Dev: prefer system binary — it has the right library paths for the dev environment
+fn system_llama_bin() -> Option<std::path::PathBuf> {
     #[cfg(debug_assertions)]
     {
         #[cfg(target_os = "macos")]
         for prefix in &["/opt/homebrew/bin", "/usr/local/bin"] {
-            let p = std::path::PathBuf::from(prefix).join(bin_name);
+            let p = std::path::PathBuf::from(prefix).join(llama_bin_name());
             if p.exists() {
-                return Ok(p);
+                return Some(p);
             }
         }
         #[cfg(target_os = "linux")]
         for prefix in &["/usr/local/bin", "/usr/bin"] {
-            let p = std::path::PathBuf::from(prefix).join(bin_name);
+            let p = std::path::PathBuf::from(prefix).join(llama_bin_name());
             if p.exists() {
-                return Ok(p);
+                return Some(p);
             }
         }
     }
+    None
+}
+
+/// llama-completion in `PATH` (Homebrew, distro package, a manual build).
+fn path_llama_bin() -> Option<std::path::PathBuf> {
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths)
+        .map(|dir| dir.join(llama_bin_name()))
+        .find(|p| p.is_file())
+}
 
+/// llama-completion already on disk, without downloading anything: path and release.
+/// Order: managed install, the flat `bin/` layout of older versions, then `PATH`.
+pub fn installed_llama_bin() -> Option<(std::path::PathBuf, Option<String>)> {
+    if let Some(path) = system_llama_bin() {
+        return Some((path, None));
+    }
+    let bin_dir = crate::utils::models_base_dir().join("bin");
+    let install_dir = bin_dir.join(LLAMA_INSTALL_DIR);
+    let stored_path = install_dir.join(llama_bin_name());
     if stored_path.exists() {
+        return Some((stored_path, read_llama_install(&install_dir).map(|i| i.version)));
+    }
+    let legacy_path = bin_dir.join(llama_bin_name());
+    if legacy_path.exists() {
+        return Some((legacy_path, None));
+    }
+    path_llama_bin().map(|path| (path, None))
+}
+
+fn ensure_llama_bin(
+    emit: &dyn Fn(&str, &str, Option<u32>),
+) -> Result<std::path::PathBuf, Box<dyn std::error::Error>> {
+    let bin_name = llama_bin_name();
+    let bin_dir = crate::utils::models_base_dir().join("bin");
+    let install_dir = bin_dir.join(LLAMA_INSTALL_DIR);
+    let stored_path = install_dir.join(bin_name);
+
+    if let Some(path) = system_llama_bin() {
+        return Ok(path);
+    }
+
+    let _guard = LLAMA_INSTALL_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let installed = read_llama_install(&install_dir).filter(|_| stored_path.exists());
+    if installed.as_ref().is_some_and(|i| i.version == LLAMA_VERSION) {
         return Ok(stored_path);
     }
 
-    let url = llama_cli_archive_url();
-    if url.is_empty() {
-        return Err("Plataforma no soportada para descarga automática de llama-completion".into());
+    // Whatever is on disk keeps working when the upgrade can't happen (no pin, offline...)
+    let on_disk = installed_llama_bin();
+    match install_llama_release(emit, &bin_dir, &install_dir, installed.as_ref(), bin_name) {
+        Ok(()) => {
+            remove_legacy_llama_files(&bin_dir);
+            emit("summary_progress", "llama-completion listo", None);
+            Ok(stored_path)
+        }
+        Err(e) => match on_disk {
+            Some((path, version)) => {
+                log::warn!(
+                    "llama.cpp {} not installed, using {} ({}): {}",
+                    LLAMA_VERSION,
+                    path.display(),
+                    version.as_deref().unwrap_or("unknown version"),
+                    e
+                );
+                Ok(path)
+            }
+            None => Err(e),
+        },
     }
+}
 
-    std::fs::create_dir_all(&bin_dir)?;
-    emit(
-        "summary_progress",
-        &format!("Descargando llama.cpp {} (solo una vez, ~15MB)...", LLAMA_VERSION),
-        None,
-    );
+/// Downloads, verifies and extracts into `<install_dir>.staging`, then swaps it in with
+/// renames so a crash never leaves a half-extracted release in place.
+fn install_llama_release(
+    emit: &dyn Fn(&str, &str, Option<u32>),
+    bin_dir: &std::path::Path,
+    install_dir: &std::path::Path,
+    installed: Option<&LlamaInstall>,
+    bin_name: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let release = llama_release()
+        .ok_or("Plataforma no soportada para descarga automática de llama-completion")?;
+    let expected = release.sha256.ok_or_else(|| {
+        format!(
+            "llama.cpp {} ({}) no tiene SHA-256 fijado y no se descarga sin verificar; \
+             instala llama.cpp (llama-completion en el PATH), usa el backend llama-server u Ollama \
+             o importa un paquete de modelos",
+            LLAMA_VERSION, release.asset
+        )
+    })?;
+    let url = llama_release_url(release);
+
+    std::fs::create_dir_all(bin_dir)?;
+    let message = match installed {
+        Some(previous) => format!("Actualizando llama.cpp de {} a {}...", previous.version, LLAMA_VERSION),
+        None => format!("Descargando llama.cpp {} (solo una vez, ~15MB)...", LLAMA_VERSION),
+    };
+    emit("summary_progress", &message, None);
+
+    let archive_path = bin_dir.join(url.rsplit('/').next().unwrap_or("llama.cpp.archive"));
+    crate::download_manager::download(emit, &format!("llama.cpp {}", LLAMA_VERSION), &url, &archive_path)?;
+
+    let digest = crate::archive::sha256_file(&archive_path)?;
+    if !expected.eq_ignore_ascii_case(&digest) {
+        let _ = std::fs::remove_file(&archive_path);
+        return Err(format!(
+            "El archivo de llama.cpp {} no coincide con el SHA-256 esperado ({} != {})",
+            release.asset, digest, expected
+        )
+        .into());
+    }
+    log::info!("llama.cpp {} sha256 verified", release.asset);
 
-    let response = ureq::get(url).call()?.into_reader();
+    let staging_dir = bin_dir.join(format!("{}.staging", LLAMA_INSTALL_DIR));
+    let _ = std::fs::remove_dir_all(&staging_dir);
+    std::fs::create_dir_all(&staging_dir)?;
+    let archive = std::fs::File::open(&archive_path)?;
 
     #[cfg(not(target_os = "windows"))]
-    extract_tar_gz(response, &bin_dir)?;
+    let extracted = crate::archive::extract_tar_gz(archive, &staging_dir, 1);
 
     #[cfg(target_os = "windows")]
-    extract_zip(response, &bin_dir)?;
+    let extracted = crate::archive::extract_zip(archive, &staging_dir);
+
+    let _ = std::fs::remove_file(&archive_path);
+    if let Err(e) = extracted {
+        let _ = std::fs::remove_dir_all(&staging_dir);
+        return Err(e);
+    }
+
+    let staged_bin = staging_dir.join(bin_name);
+    if !staged_bin.exists() {
+        let _ = std::fs::remove_dir_all(&staging_dir);
+        return Err(format!("El archivo de llama.cpp {} no contiene {}", release.asset, bin_name).into());
+    }
 
     // Ensure the binary is executable on Unix
     #[cfg(unix)]
     {
         use std::os::unix::fs::PermissionsExt;
-        std::fs::set_permissions(&stored_path, std::fs::Permissions::from_mode(0o755))?;
+        std::fs::set_permissions(&staged_bin, std::fs::Permissions::from_mode(0o755))?;
     }
 
-    emit("summary_progress", "llama-completion listo", None);
-    Ok(stored_path)
-}
-
-// ─── Model management ─────────────────────────────────────────────────────────
+    let install = LlamaInstall {
+        version: LLAMA_VERSION.to_string(),
+        asset: release.asset.to_string(),
+        sha256: digest,
+    };
+    std::fs::write(staging_dir.join("install.json"), serde_json::to_string_pretty(&install)?)?;
 
-fn get_model_path(model_name: &str) -> std::path::PathBuf {
-    crate::utils::models_base_dir()
-        .join("llm_models")
-        .join(model_name)
+    crate::archive::swap_dirs(&staging_dir, install_dir)?;
+    log::info!("llama.cpp {} installed in {}", LLAMA_VERSION, install_dir.display());
+    Ok(())
 }
 
-fn ensure_model(
-    emit: &dyn Fn(&str, &str, Option<u32>),
-    model_name: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let model_path = get_model_path(model_name);
-    if let Some(parent) = model_path.parent() {
-        std::fs::create_dir_all(parent)?;
-    }
-    if !model_path.exists() {
-        let repo = if model_name.contains("phi-4") {
-            "microsoft/phi-4-gguf"
-        } else if model_name.contains("Phi") {
-            "bartowski/Phi-3.5-mini-instruct-GGUF"
-        } else if model_name.contains("Llama-3.2-3B") {
-            "bartowski/Llama-3.2-3B-Instruct-GGUF"
-        } else if model_name.contains("Meta-Llama-3.1-8B") {
-            "bartowski/Meta-Llama-3.1-8B-Instruct-GGUF"
-        } else if model_name.contains("gemma-2-9b-it") {
-            "bartowski/gemma-2-9b-it-GGUF"
-        } else if model_name.contains("google_gemma-4-E2B-it") {
-            "bartowski/google_gemma-4-E2B-it-GGUF"
-        } else if model_name.contains("Qwen2.5-14B") {
-            "bartowski/Qwen2.5-14B-Instruct-GGUF"
-        } else if model_name.contains("Qwen_Qwen3.5-4B") {
-            "bartowski/Qwen_Qwen3.5-4B-GGUF"
-        } else if model_name.contains("Ministral-8B") {
-            "bartowski/Ministral-8B-Instruct-2410-GGUF"
-        } else {
-            "Qwen/Qwen2.5-3B-Instruct-GGUF"
-        };
-
-        let model_url = format!(
-            "https://huggingface.co/{}/resolve/main/{}",
-            repo, model_name
-        );
-        let response = ureq::get(&model_url).call()?;
-        let total_bytes = response
-            .header("Content-Length")
-            .and_then(|v| v.parse::<u64>().ok());
-
-        emit(
-            "summary_progress",
-            &format!(
-                "Descargando modelo{}...",
-                total_bytes
-                    .map(|b| format!(" ({:.1} GB)", b as f64 / 1_073_741_824.0))
-                    .unwrap_or_default()
-            ),
-            Some(0),
-        );
-
-        let mut reader = response.into_reader();
-        let mut file = std::fs::File::create(&model_path)
-            .map_err(|e| format!("No se pudo crear archivo: {}", e))?;
-
-        let mut buf = vec![0u8; 512 * 1024]; // 512KB chunks
-        let mut downloaded: u64 = 0;
-        let mut last_pct = 0u32;
-
-        let result = (|| -> Result<(), Box<dyn std::error::Error>> {
-            loop {
-                let n = reader.read(&mut buf)?;
-                if n == 0 {
-                    break;
-                }
-                std::io::Write::write_all(&mut file, &buf[..n])?;
-                downloaded += n as u64;
-
-                if let Some(total) = total_bytes {
-                    let pct = ((downloaded as f64 / total as f64) * 100.0) as u32;
-                    if pct != last_pct {
-                        emit(
-                            "summary_progress",
-                            &format!(
-                                "Descargando modelo... {:.0}/{:.0} MB",
-                                downloaded as f64 / 1_048_576.0,
-                                total as f64 / 1_048_576.0
-                            ),
-                            Some(pct),
-                        );
-                        last_pct = pct;
-                    }
-                }
-            }
-            Ok(())
-        })();
-
-        if let Err(e) = result {
-            let _ = std::fs::remove_file(&model_path);
-            return Err(format!("Descarga interrumpida: {}", e).into());
+/// Releases before the versioned layout were extracted straight into `bin/`.
+fn remove_legacy_llama_files(bin_dir: &std::path::Path) {
+    let legacy_marker = bin_dir.join(llama_bin_name());
+    if !legacy_marker.exists() {
+        return;
+    }
+    let Ok(entries) = std::fs::read_dir(bin_dir) else {
+        return;
+    };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let ext = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+        let from_llama = name.starts_with("llama-")
+            || name.starts_with("libllama")
+            || name.starts_with("libggml")
+            || name.starts_with("libmtmd")
+            || name.starts_with("ggml")
+            || matches!(ext, "dll" | "metal");
+        if path.is_file() && from_llama {
+            let _ = std::fs::remove_file(&path);
         }
-
-        emit("summary_progress", "Modelo descargado", Some(100));
     }
-    Ok(())
+    log::info!("removed legacy llama.cpp files from {}", bin_dir.display());
 }
 
 // ─── Inference ────────────────────────────────────────────────────────────────
@@ -268,38 +310,38 @@ fn run_llama_cli(
     emit: &dyn Fn(&str, &str, Option<u32>),
     bin_path: &std::path::Path,
     model_path: &std::path::Path,
-    prompt: &str,
+    prompt_file: &std::path::Path,
     max_tokens: u32,
+    context: usize,
     stream: bool,
 ) -> Result<String, String> {
-    let prompt_file = std::env::temp_dir()
-        .join(format!("beautiful_stt_llm_{}.txt", std::process::id()));
-    std::fs::write(&prompt_file, prompt.as_bytes())
-        .map_err(|e| format!("Error escribiendo prompt: {}", e))?;
-
     let mut child = std::process::Command::new(bin_path)
         .arg("-m").arg(model_path)
-        .arg("-f").arg(&prompt_file)
+        .arg("-f").arg(prompt_file)
         .arg("-n").arg(max_tokens.to_string())
-        .arg("-ngl").arg("99")
-        .arg("-c").arg("8192")
+        .arg("-ngl").arg(crate::hardware::gpu_layers_for(model_path).to_string())
+        .arg("-c").arg(context.to_string())
         .arg("--temp").arg("0.3")
         .arg("--repeat-penalty").arg("1.1")
         .arg("--seed").arg("42")
         .arg("-no-cnv")            // disable conversation/interactive mode (llama-completion b9496+)
         .arg("--no-display-prompt")
-        // --log-disable suppresses stdout in b9496 — omit it; stderr is already nulled below
+        // --log-disable suppresses stdout in b9496 — omit it; stderr goes to the log file
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
-        .stderr(Stdio::null())
+        .stderr(Stdio::piped())
         .spawn()
         .map_err(|e| format!("Error ejecutando llama-cli ({:?}): {}", bin_path, e))?;
+    let stderr_thread = child
+        .stderr
+        .take()
+        .map(|stderr| crate::logging::capture_stderr(stderr, crate::logging::LLAMA_CPP));
 
     let mut stdout = child.stdout.take().expect("Failed to get stdout");
     let mut output = String::new();
     let mut byte_buf: Vec<u8> = Vec::new();
     let mut read_buf = [0u8; 256];
-    let mut read_count: u32 = 0;
+    let mut progress = StreamProgress::new(max_tokens);
 
     loop {
         match stdout.read(&mut read_buf) {
@@ -308,30 +350,14 @@ fn run_llama_cli(
                 byte_buf.extend_from_slice(&read_buf[..n]);
                 match std::str::from_utf8(&byte_buf) {
                     Ok(s) => {
-                        if !s.is_empty() {
-                            output.push_str(s);
-                            if stream {
-                                emit("summary_segment", s, None);
-                                read_count += 1;
-                                if read_count % 8 == 0 {
-                                    let progress = ((output.len() as f32
-                                        / (max_tokens as f32 * 3.5))
-                                        .min(0.95)
-                                        * 100.0) as u32;
-                                    emit("summary_progress", "Generando resumen", Some(progress));
-                                }
-                            }
-                        }
+                        progress.push(emit, &mut output, s, stream);
                         byte_buf.clear();
                     }
                     Err(e) => {
                         let valid_up_to = e.valid_up_to();
                         if valid_up_to > 0 {
                             let s = std::str::from_utf8(&byte_buf[..valid_up_to]).unwrap();
-                            output.push_str(s);
-                            if stream {
-                                emit("summary_segment", s, None);
-                            }
+                            progress.push(emit, &mut output, s, stream);
                             byte_buf.drain(..valid_up_to);
                         }
                         // Remaining bytes form an incomplete multi-byte char; wait for next read
@@ -341,22 +367,29 @@ fn run_llama_cli(
             Err(e) => {
                 let _ = child.kill();
                 let _ = child.wait();
-                let _ = std::fs::remove_file(&prompt_file);
                 return Err(format!("Error leyendo salida de llama-cli: {}", e));
             }
         }
     }
 
-    let _ = child.wait();
-    let _ = std::fs::remove_file(&prompt_file);
+    let status = child.wait();
+    if let Some(thread) = stderr_thread {
+        let _ = thread.join();
+    }
+    log::debug!("llama-completion exit status: {:?}", status);
 
+    clean_llm_output(&output)
+}
+
+/// Strips reasoning blocks and end-of-turn markers shared by every backend.
+fn clean_llm_output(output: &str) -> Result<String, String> {
     // return an error so the caller knows to retry with more tokens.
     let after_think = if let Some(end) = output.find("</think>") {
         &output[end + "</think>".len()..]
     } else if output.contains("<think>") {
         return Err("El modelo agotó los tokens durante el razonamiento. Usa /no_think o aumenta el límite de tokens.".into());
     } else {
-        &output
+        output
     };
 
     let cleaned = after_think
@@ -371,8 +404,120 @@ fn run_llama_cli(
     Ok(cleaned)
 }
 
+// ─── Backends ────────────────────────────────────────────────────────────────
+
+/// Bundled llama-completion binary running a GGUF from the data dir.
+struct LlamaCompletionBackend {
+    bin_path: std::path::PathBuf,
+    model_path: std::path::PathBuf,
+    model_name: String,
+    /// Leído de la cabecera GGUF y limitado a `MAX_CONTEXT`.
+    context: usize,
+    /// `None` si el GGUF no trae plantilla: se usa `format_chat_prompt` según el nombre.
+    chat_template: Option<chat_template::ChatTemplate>,
+    /// Prompts y textos a tokenizar de este trabajo; se borra con el backend.
+    workspace: crate::workspace::Workspace,
+}
+
+impl LlmBackend for LlamaCompletionBackend {
+    fn complete(
+        &self,
+        emit: &dyn Fn(&str, &str, Option<u32>),
+        prompt: &ChatPrompt,
+        max_tokens: u32,
+        stream: bool,
+    ) -> Result<String, String> {
+        let raw = match self.chat_template.as_ref().map(|t| t.render(&prompt.system, &prompt.user)) {
+            Some(Ok(raw)) => raw,
+            Some(Err(e)) => {
+                log::info!("{}; usando plantilla por nombre de modelo", e);
+                format_chat_prompt(&prompt.system, &prompt.user, "", &self.model_name)
+            }
+            None => format_chat_prompt(&prompt.system, &prompt.user, "", &self.model_name),
+        };
+        let prompt_file = self.workspace.file("prompt.txt");
+        std::fs::write(&prompt_file, raw.as_bytes())
+            .map_err(|e| format!("Error escribiendo prompt: {}", e))?;
+        run_llama_cli(emit, &self.bin_path, &self.model_path, &prompt_file, max_tokens, self.context, stream)
+    }
+
+    fn context_length(&self) -> Option<usize> {
+        Some(self.context)
+    }
+
+    /// `llama-tokenize` viene en el mismo paquete que llama-completion; sólo carga el vocabulario.
+    fn count_tokens(&self, text: &str) -> Option<usize> {
+        let bin_name = if cfg!(target_os = "windows") { "llama-tokenize.exe" } else { "llama-tokenize" };
+        let bin_path = self.bin_path.with_file_name(bin_name);
+        let input_file = self.workspace.file("tokenize.txt");
+        std::fs::write(&input_file, text.as_bytes()).ok()?;
+
+        let output = std::process::Command::new(&bin_path)
+            .arg("-m").arg(&self.model_path)
+            .arg("-f").arg(&input_file)
+            .arg("--ids")
+            .arg("--log-disable")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+        let output = output.ok()?;
+        crate::logging::log_output(crate::logging::LLAMA_CPP, &output.stderr);
+
+        // Salida: "[128000, 9906, 1917]"
+        let stdout = String::from_utf8(output.stdout).ok()?;
+        let ids = stdout.trim().strip_prefix('[')?.strip_suffix(']')?;
+        Some(ids.split(',').filter(|id| !id.trim().is_empty()).count())
+    }
+}
+
+fn prepare_backend(
+    emit: &dyn Fn(&str, &str, Option<u32>),
+    config: &LlmBackendConfig,
+    model_name: &str,
+) -> Result<Box<dyn LlmBackend>, String> {
+    if let Some(backend) = llm_backend::connect(config) {
+        emit("summary_progress", "Conectando con el servidor LLM", None);
+        return Ok(backend);
+    }
+
+    emit("summary_progress", &format!("Preparando modelo {}", model_name), None);
+    let model_path = models::ensure_model(emit, model_name)
+        .map_err(|e| format!("Error preparando modelo: {}", e))?;
+
+    emit("summary_progress", "Verificando llama-completion", None);
+    let bin_path = ensure_llama_bin(emit)
+        .map_err(|e| format!("Error preparando llama-completion: {}", e))?;
+    let (context, chat_template) = match gguf::GgufMetadata::read(&model_path) {
+        Ok(metadata) => (
+            metadata.context_length().unwrap_or(DEFAULT_CONTEXT).min(MAX_CONTEXT),
+            chat_template::ChatTemplate::from_metadata(&metadata),
+        ),
+        Err(e) => {
+            log::info!("{}", e);
+            (DEFAULT_CONTEXT, None)
+        }
+    };
+    log::info!(
+        "model={} context={} chat_template={}",
+        model_name,
+        context,
+        if chat_template.is_some() { "gguf" } else { "fallback" }
+    );
+    Ok(Box::new(LlamaCompletionBackend {
+        bin_path,
+        model_path,
+        model_name: model_name.to_string(),
+        context,
+        chat_template,
+        workspace: crate::workspace::Workspace::create("llm")?,
+    }))
+}
+
 // ─── Prompt builders ─────────────────────────────────────────────────────────
 
+/// Plantillas escritas a mano, elegidas por el nombre del archivo. Sólo se usan cuando
+/// el GGUF no trae `tokenizer.chat_template` o no se pudo renderizar.
 fn format_chat_prompt(system: &str, user: &str, assistant_prefix: &str, model_name: &str) -> String {
     let model_lower = model_name.to_lowercase();
     if model_lower.contains("phi") {
@@ -417,80 +562,300 @@ fn format_chat_prompt(system: &str, user: &str, assistant_prefix: &str, model_na
     }
 }
 
-fn build_summary_prompt(transcript: &str, model_name: &str) -> String {
-    let user = format!(
-        "Transcripción:\n{}\n\nEscribe un resumen claro y completo. \
-        Cubre el tema principal y todos los puntos importantes mencionados. \
-        Usa tantas oraciones como sea necesario para no omitir información relevante. Resumen:",
-        transcript
-    );
-    format_chat_prompt(SYSTEM_PROMPT, &user, "", model_name)
+fn build_system_prompt(template: &SummaryTemplate, language: &str) -> String {
+    render_template(&template.system, &[("language", language)])
 }
 
-fn build_chunk_extraction_prompt(chunk: &str, chunk_num: usize, total: usize, model_name: &str) -> String {
-    let user = format!(
-        "Sección {} de {}. Extrae los puntos más importantes: ideas, \
-        personas, cifras, fechas y eventos relevantes.\n\n{}\n\nPuntos clave:",
-        chunk_num, total, chunk
+fn build_summary_prompt(template: &SummaryTemplate, transcript: &str, language: &str) -> ChatPrompt {
+    let user = render_template(
+        &template.direct,
+        &[("transcript", transcript), ("language", language)],
     );
-    format_chat_prompt(SYSTEM_PROMPT, &user, "", model_name)
+    ChatPrompt { system: build_system_prompt(template, language), user }
 }
 
-fn build_final_summary_prompt(ideas: &str, model_name: &str) -> String {
-    let user = format!(
-        "Usando los puntos clave de cada sección, escribe un resumen cohesivo \
-        en 2-4 oraciones del audio completo:\n\n{}\n\nResumen:",
-        ideas
+fn build_chunk_extraction_prompt(
+    template: &SummaryTemplate,
+    chunk: &str,
+    chunk_num: usize,
+    total: usize,
+    language: &str,
+) -> ChatPrompt {
+    let user = render_template(
+        &template.extraction,
+        &[
+            ("transcript", chunk),
+            ("chunk_num", &chunk_num.to_string()),
+            ("total", &total.to_string()),
+            ("language", language),
+        ],
     );
-    format_chat_prompt(SYSTEM_PROMPT, &user, "", model_name)
+    ChatPrompt { system: build_system_prompt(template, language), user }
+}
+
+fn build_condense_prompt(template: &SummaryTemplate, notes: &str, language: &str) -> ChatPrompt {
+    let user = render_template(CONDENSE_PROMPT, &[("notes", notes)]);
+    ChatPrompt { system: build_system_prompt(template, language), user }
+}
+
+fn build_final_summary_prompt(template: &SummaryTemplate, ideas: &str, language: &str) -> ChatPrompt {
+    let user = render_template(&template.reduce, &[("notes", ideas), ("language", language)]);
+    ChatPrompt { system: build_system_prompt(template, language), user }
+}
+
+/// `mm:ss`, o `h:mm:ss` a partir de una hora: el formato que reconoce YouTube.
+fn format_timestamp(ms: u64) -> String {
+    let total = ms / 1000;
+    let (h, m, s) = (total / 3600, (total % 3600) / 60, total % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{:02}:{:02}", m, s)
+    }
 }
 
 // ─── Chunking ────────────────────────────────────────────────────────────────
 
-fn split_into_chunks(text: &str, chunk_size: usize) -> Vec<&str> {
-    let mut chunks = Vec::new();
+/// Presupuesto de tokens de un backend. Cuenta una muestra con el tokenizador real
+/// para calibrar caracteres por token, y con eso dimensiona secciones de cualquier largo
+/// sin tokenizar el texto completo en cada paso.
+struct TokenBudget {
+    context: usize,
+    chars_per_token: f32,
+}
+
+impl TokenBudget {
+    fn measure(backend: &dyn LlmBackend, sample: &str) -> TokenBudget {
+        let context = backend.context_length().unwrap_or(DEFAULT_CONTEXT);
+        let end = char_boundary(sample, CALIBRATION_SAMPLE_CHARS);
+        let sample = &sample[..end];
+        let chars_per_token = backend
+            .count_tokens(sample)
+            .filter(|tokens| *tokens > 0)
+            .map(|tokens| sample.chars().count() as f32 / tokens as f32)
+            .unwrap_or(DEFAULT_CHARS_PER_TOKEN);
+        log::info!("context={} chars_per_token={:.2}", context, chars_per_token);
+        TokenBudget { context, chars_per_token }
+    }
+
+    fn tokens(&self, text: &str) -> usize {
+        (text.chars().count() as f32 / self.chars_per_token).ceil() as usize
+    }
+
+    /// Caracteres de entrada que caben junto a una respuesta de `max_output` tokens.
+    fn input_chars(&self, max_output: u32) -> usize {
+        let tokens = self
+            .context
+            .saturating_sub(max_output as usize + PROMPT_OVERHEAD_TOKENS)
+            .max(256);
+        (tokens as f32 * self.chars_per_token) as usize
+    }
+
+    fn fits(&self, text: &str, max_output: u32) -> bool {
+        self.tokens(text) + max_output as usize + PROMPT_OVERHEAD_TOKENS <= self.context
+    }
+}
+
+/// Índice en bytes tras los primeros `chars` caracteres. Todo el dimensionado se hace en
+/// caracteres, igual que la calibración de `TokenBudget`: en bytes las tildes y eñes
+/// cuentan doble y las secciones saldrían más cortas de lo que cabe.
+fn char_boundary(text: &str, chars: usize) -> usize {
+    text.char_indices().nth(chars).map(|(idx, _)| idx).unwrap_or(text.len())
+}
+
+fn char_len(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Divide en oraciones conservando la puntuación final.
+fn split_sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
     let mut start = 0;
-    while start < text.len() {
-        let end = std::cmp::min(start + chunk_size, text.len());
-        let end = if end < text.len() {
-            text[start..end].rfind(' ').map(|i| start + i).unwrap_or(end)
-        } else {
-            end
-        };
-        chunks.push(text[start..end].trim());
-        start = end;
+    let mut chars = text.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let at_boundary = matches!(c, '.' | '?' | '!' | '…')
+            && chars.peek().is_none_or(|(_, next)| next.is_whitespace());
+        if at_boundary {
+            let end = idx + c.len_utf8();
+            sentences.push(text[start..end].trim());
+            start = end;
+        }
+    }
+    sentences.push(text[start..].trim());
+    sentences.into_iter().filter(|s| !s.is_empty()).collect()
+}
+
+/// Corta una oración más larga que una sección entera por el último espacio.
+fn split_long_sentence(sentence: &str, max_chars: usize) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = sentence;
+    while char_len(rest) > max_chars {
+        let limit = char_boundary(rest, max_chars);
+        let cut = rest[..limit].rfind(' ').filter(|i| *i > 0).unwrap_or(limit);
+        parts.push(rest[..cut].trim());
+        rest = rest[cut..].trim_start();
+    }
+    parts.push(rest);
+    parts.into_iter().filter(|p| !p.is_empty()).collect()
+}
+
+/// Secciones de hasta `max_chars` que terminan en fin de oración. Cada sección repite
+/// las últimas oraciones de la anterior (hasta `overlap_chars`).
+fn split_into_chunks(text: &str, max_chars: usize, overlap_chars: usize) -> Vec<String> {
+    let sentences: Vec<&str> = split_sentences(text)
+        .into_iter()
+        .flat_map(|s| split_long_sentence(s, max_chars))
+        .collect();
+
+    let mut chunks: Vec<String> = Vec::new();
+    let mut current: Vec<&str> = Vec::new();
+    let mut current_len = 0;
+    for sentence in sentences {
+        let sentence_len = char_len(sentence);
+        if current_len + sentence_len > max_chars && !current.is_empty() {
+            chunks.push(current.join(" "));
+            // Arrastrar oraciones finales como solapamiento
+            let mut overlap: Vec<&str> = Vec::new();
+            let mut overlap_len = 0;
+            for s in current.iter().rev() {
+                let len = char_len(s);
+                // `+ 1`: el espacio que une la oración arrastrada con la siguiente
+                if overlap_len + len > overlap_chars || overlap_len + len + 1 + sentence_len > max_chars {
+                    break;
+                }
+                overlap_len += len + 1;
+                overlap.insert(0, s);
+            }
+            current = overlap;
+            current_len = overlap_len;
+        }
+        current_len += sentence_len + 1;
+        current.push(sentence);
+    }
+    if !current.is_empty() {
+        chunks.push(current.join(" "));
+    }
+    chunks
+}
+
+/// Agrupa notas consecutivas en lotes que caben en una llamada.
+fn group_notes(notes: &[String], max_chars: usize) -> Vec<String> {
+    let mut groups: Vec<String> = Vec::new();
+    let mut current = String::new();
+    for note in notes {
+        if !current.is_empty() && char_len(&current) + char_len(note) > max_chars {
+            groups.push(std::mem::take(&mut current));
+        }
+        current.push_str(note);
+        current.push('\n');
+    }
+    if !current.is_empty() {
+        groups.push(current);
+    }
+    groups
+}
+
+/// Reduce jerárquico: si las notas no caben en la llamada final (audios de varias horas o
+/// modelos con poco contexto), se condensan por lotes con `condense(lote, nivel, índice,
+/// total)` hasta que quepan. Cada nivel tiene que dejar menos notas que el anterior; si
+/// no, se deja de condensar y, si aún no caben, es un error.
+fn reduce_notes(
+    mut notes: Vec<String>,
+    budget: &TokenBudget,
+    max_tokens: u32,
+    mut condense: impl FnMut(&str, usize, usize, usize) -> Result<String, String>,
+) -> Result<Vec<String>, String> {
+    let mut level = 1;
+    while !budget.fits(&notes.concat(), max_tokens) && notes.len() > 1 {
+        let groups = group_notes(&notes, budget.input_chars(CONDENSE_MAX_TOKENS));
+        if groups.len() >= notes.len() {
+            break;
+        }
+        let group_total = groups.len();
+        let mut condensed = Vec::with_capacity(group_total);
+        for (idx, group) in groups.iter().enumerate() {
+            let summary = condense(group, level, idx, group_total)?;
+            condensed.push(format!("### Parte {}\n{}\n", idx + 1, summary));
+        }
+        notes = condensed;
+        level += 1;
     }
-    chunks.into_iter().filter(|c| !c.is_empty()).collect()
+    if !budget.fits(&notes.concat(), max_tokens) {
+        return Err(format!(
+            "Las notas del audio no caben en el contexto del modelo ({} tokens) ni tras condensarlas; \
+             usa un modelo con más contexto o una plantilla con menos tokens de salida",
+            budget.context
+        ));
+    }
+    Ok(notes)
 }
 
 // ─── Entry point ─────────────────────────────────────────────────────────────
 
+#[derive(Serialize)]
+pub struct SummaryResult {
+    pub mode: OutputMode,
+    pub template: String,
+    /// Texto listo para mostrar; en modos estructurados se genera a partir de `structured`.
+    pub text: String,
+    /// JSON validado contra el esquema del modo. `None` en modos de prosa o si el
+    /// modelo no devolvió JSON válido (en ese caso `text` trae la salida cruda).
+    pub structured: Option<serde_json::Value>,
+}
+
+/// Modelo y backend de la configuración guardada cuando la llamada no los indica.
+fn resolve_llm(llm_model: Option<&str>, backend: Option<&LlmBackendConfig>) -> (String, LlmBackendConfig) {
+    let settings = crate::settings::load().summary;
+    (
+        llm_model.map(str::to_string).unwrap_or(settings.llm_model),
+        backend.cloned().unwrap_or(settings.llm_backend),
+    )
+}
+
 pub fn summarize_transcript(
     emit: EmitType,
     transcript: &str,
     llm_model: Option<&str>,
-    _output_mode: Option<&str>,
-) -> Result<String, String> {
-    let model_name = llm_model.unwrap_or(DEFAULT_LLM_MODEL);
-
-    emit("summary_progress", &format!("Preparando modelo {}", model_name), None);
-    if let Err(e) = ensure_model(&*emit, model_name) {
-        return Err(format!("Error descargando modelo: {}", e));
-    }
-
-    emit("summary_progress", "Verificando llama-completion", None);
-    let bin_path = ensure_llama_bin(&*emit)
-        .map_err(|e| format!("Error preparando llama-completion: {}", e))?;
-    let model_path = get_model_path(model_name);
-
-    let summary = if transcript.len() <= MAX_DIRECT_CHARS {
-        let prompt = build_summary_prompt(transcript, model_name);
+    output_mode: Option<&str>,
+    template_id: Option<&str>,
+    backend: Option<&LlmBackendConfig>,
+    language: Option<&str>,
+) -> Result<SummaryResult, String> {
+    let all_settings = crate::settings::load();
+    let settings = all_settings.summary;
+    // Nombre del idioma tal como se inserta en `{language}` ("español", "inglés"...)
+    let language = language
+        .map(str::to_string)
+        .or(settings.language)
+        .filter(|l| !l.trim().is_empty())
+        .unwrap_or_else(|| templates::language_name(&all_settings.transcription.language));
+    let language = language.as_str();
+    let output_mode = output_mode.or(settings.output_mode.as_deref());
+    let template_id = template_id.or(settings.template.as_deref());
+    // Una plantilla explícita manda sobre el modo; su `mode` decide cómo se valida la salida
+    let template = match template_id.map(str::trim).filter(|t| !t.is_empty()) {
+        Some(id) => templates::load(id)?,
+        None => templates::builtin(OutputMode::parse(output_mode)?),
+    };
+    let mode = template.mode;
+    let (model_name, backend_config) = resolve_llm(llm_model, backend);
+    let model_name = model_name.as_str();
+    let backend = prepare_backend(&*emit, &backend_config, model_name)?;
+    let started = std::time::Instant::now();
+    let max_tokens = template.max_tokens;
+    // El JSON a medio generar no sirve de vista previa
+    let stream = !mode.is_structured();
+
+    let budget = TokenBudget::measure(backend.as_ref(), transcript);
+
+    let output = if budget.fits(transcript, max_tokens) {
+        let prompt = build_summary_prompt(&template, transcript, language);
         emit("summary_progress", "Generando resumen", Some(0));
-        run_llama_cli(&*emit, &bin_path, &model_path, &prompt, 550, true)?
+        backend.complete(&*emit, &prompt, max_tokens, stream)?
     } else {
-        let chunks = split_into_chunks(transcript, CHUNK_SIZE);
+        let chunk_chars = budget.input_chars(EXTRACTION_MAX_TOKENS);
+        let chunks = split_into_chunks(transcript, chunk_chars, (chunk_chars as f32 * CHUNK_OVERLAP) as usize);
         let total = chunks.len();
-        let mut all_ideas = String::new();
 
         emit(
             "summary_progress",
@@ -498,6 +863,7 @@ pub fn summarize_transcript(
             Some(0),
         );
 
+        let mut notes: Vec<String> = Vec::with_capacity(total);
         for (idx, chunk) in chunks.iter().enumerate() {
             let chunk_num = idx + 1;
             emit(
@@ -505,23 +871,146 @@ pub fn summarize_transcript(
                 &format!("Extrayendo información: sección {}/{}", chunk_num, total),
                 Some(((idx as f32 / total as f32) * 70.0) as u32),
             );
-            let extraction_prompt = build_chunk_extraction_prompt(chunk, chunk_num, total, model_name);
-            let ideas = run_llama_cli(
-                &*emit,
-                &bin_path,
-                &model_path,
-                &extraction_prompt,
-                180,
-                false,
-            )?;
-            all_ideas.push_str(&format!("\n### Sección {}\n{}\n", chunk_num, ideas));
+            let extraction_prompt = build_chunk_extraction_prompt(&template, chunk, chunk_num, total, language);
+            let ideas = backend.complete(&*emit, &extraction_prompt, EXTRACTION_MAX_TOKENS, false)?;
+            notes.push(format!("### Sección {}\n{}\n", chunk_num, ideas));
         }
 
-        emit("summary_progress", "Generando resumen final...", Some(75));
-        let final_prompt = build_final_summary_prompt(&all_ideas, model_name);
-        run_llama_cli(&*emit, &bin_path, &model_path, &final_prompt, 550, true)?
+        let notes = reduce_notes(notes, &budget, max_tokens, |group, level, idx, group_total| {
+            emit(
+                "summary_progress",
+                &format!("Condensando notas (nivel {}): lote {}/{}", level, idx + 1, group_total),
+                Some(70 + ((idx as f32 / group_total as f32) * 15.0) as u32),
+            );
+            let prompt = build_condense_prompt(&template, group, language);
+            backend.complete(&*emit, &prompt, CONDENSE_MAX_TOKENS, false)
+        })?;
+
+        emit("summary_progress", "Generando resumen final...", Some(85));
+        let final_prompt = build_final_summary_prompt(&template, &notes.concat(), language);
+        backend.complete(&*emit, &final_prompt, max_tokens, stream)?
+    };
+
+    let result = match mode.parse_structured(&output) {
+        Some((structured, text)) => SummaryResult { mode, template: template.id, text, structured: Some(structured) },
+        None => {
+            if mode.is_structured() {
+                emit("summary_progress", "El modelo no devolvió JSON válido; se muestra la respuesta sin procesar", None);
+            }
+            SummaryResult { mode, template: template.id, text: output, structured: None }
+        }
     };
 
+    if matches!(backend_config, LlmBackendConfig::LlamaCompletion) {
+        if let Ok(spec) = models::resolve(model_name) {
+            let model_path = models::local_path(&spec);
+            crate::recommender::record_summary(model_name, &model_path, transcript.chars().count(), started.elapsed().as_secs_f64());
+        }
+    }
+
     emit("summary_progress", "Completado", Some(100));
-    Ok(summary)
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(context: usize) -> TokenBudget {
+        TokenBudget { context, chars_per_token: 1.0 }
+    }
+
+    #[test]
+    fn split_sentences_handles_multibyte_punctuation() {
+        assert_eq!(
+            split_sentences("¿Qué tal? Él está aquí… Sí. Versión 1.5 lista"),
+            vec!["¿Qué tal?", "Él está aquí…", "Sí.", "Versión 1.5 lista"]
+        );
+    }
+
+    #[test]
+    fn chunks_are_sized_in_chars_not_bytes() {
+        // 10 caracteres (19 bytes) por oración: dos oraciones y el espacio llenan 21 caracteres
+        let text = "ññññ ñññä. ".repeat(6);
+        let chunks = split_into_chunks(&text, 21, 0);
+        assert_eq!(chunks.len(), 3);
+        assert!(chunks.iter().all(|c| char_len(c) == 21), "{:?}", chunks);
+    }
+
+    #[test]
+    fn overlap_never_pushes_a_chunk_over_max_chars() {
+        let sentences = ["Uno.", "La reunión empezó tarde.", "Se habló del presupuesto anual.", "Sí.", "Próximos pasos: revisión técnica y entrega."];
+        let text = sentences.iter().cycle().take(40).copied().collect::<Vec<_>>().join(" ");
+        for max_chars in [45, 60, 120] {
+            let chunks = split_into_chunks(&text, max_chars, max_chars / 2);
+            assert!(chunks.len() > 1);
+            for chunk in &chunks {
+                assert!(char_len(chunk) <= max_chars, "{} > {}: {:?}", char_len(chunk), max_chars, chunk);
+            }
+            // Alguna sección empieza repitiendo las últimas oraciones de la anterior; con 45
+            // no cabe ninguna junto a la oración siguiente
+            let repeats_tail = |previous: &str, next: &str| {
+                let sentences = split_sentences(previous);
+                (1..sentences.len()).any(|k| next.starts_with(&sentences[sentences.len() - k..].join(" ")))
+            };
+            assert_eq!(chunks.windows(2).any(|w| repeats_tail(&w[0], &w[1])), max_chars > 45);
+        }
+    }
+
+    #[test]
+    fn sentence_longer_than_a_chunk_is_cut_at_spaces() {
+        let sentence = format!("{}.", "palabra árbol ".repeat(30).trim());
+        let chunks = split_into_chunks(&sentence, 40, 10);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| char_len(c) <= 40));
+        assert!(chunks.iter().all(|c| !c.starts_with(' ') && !c.ends_with(' ')));
+        let words: usize = chunks.iter().map(|c| c.split_whitespace().count()).sum();
+        assert!(words >= 60, "se perdieron palabras: {}", words);
+    }
+
+    #[test]
+    fn sentence_without_spaces_is_cut_on_char_boundaries() {
+        let sentence = "ñ".repeat(25);
+        let parts = split_long_sentence(&sentence, 10);
+        assert_eq!(parts.iter().map(|p| char_len(p)).collect::<Vec<_>>(), vec![10, 10, 5]);
+    }
+
+    #[test]
+    fn group_notes_counts_chars() {
+        let notes = vec!["é".repeat(10), "é".repeat(10), "é".repeat(10)];
+        assert_eq!(group_notes(&notes, 22).len(), 2);
+    }
+
+    #[test]
+    fn token_budget_counts_chars() {
+        let budget = TokenBudget { context: 1000, chars_per_token: 2.0 };
+        assert_eq!(budget.tokens("ñandú"), 3);
+        assert_eq!(budget.input_chars(88), 800);
+    }
+
+    #[test]
+    fn reduce_condenses_until_the_notes_fit() {
+        let notes = vec!["x".repeat(200); 20];
+        let mut calls = 0;
+        let reduced = reduce_notes(notes, &budget(2000), 100, |_, _, _, _| {
+            calls += 1;
+            Ok("resumen corto".to_string())
+        })
+        .unwrap();
+        assert_eq!(calls, 4);
+        assert_eq!(reduced.len(), 4);
+        assert!(budget(2000).fits(&reduced.concat(), 100));
+    }
+
+    #[test]
+    fn reduce_stops_when_condensing_does_not_shrink() {
+        let notes = vec!["x".repeat(200); 20];
+        let mut calls = 0;
+        let result = reduce_notes(notes, &budget(2000), 100, |group, _, _, _| {
+            calls += 1;
+            Ok(group.to_string())
+        });
+        assert!(result.unwrap_err().contains("no caben"));
+        assert_eq!(calls, 4);
+    }
 }
@@ -0,0 +1,350 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::audio_processor::{merge_segments, TranscriptSegment};
+use super::llm_backend::{ChatPrompt, LlmBackend};
+use super::output_mode::extract_json;
+use super::{format_timestamp, prepare_backend, resolve_llm, EmitType, LlmBackendConfig, TokenBudget};
+
+const CHAPTERS_SYSTEM_PROMPT: &str = "Eres un editor que divide transcripciones de audio en español en capítulos \
+    temáticos, como los de un video de YouTube. Cada línea de la transcripción empieza con su marca de tiempo. \
+    Responde únicamente con JSON válido, sin texto antes ni después, sin bloques de código.";
+
+const CHAPTERS_FORMAT: &str = "{\"chapters\": [{\"start\": \"mm:ss\", \"title\": \"...\", \"description\": \"...\"}]}";
+
+/// Bloques con marca de tiempo que ve el modelo; también son los puntos a los que se
+/// ajusta el inicio de cada capítulo.
+const BLOCK_MAX_MS: u64 = 60_000;
+const BLOCK_MAX_CHARS: usize = 800;
+const MAX_CHAPTERS: usize = 12;
+const SECTION_MAX_TOKENS: u32 = 500;
+/// YouTube ignora la lista si algún capítulo dura menos de 10 s.
+const MIN_CHAPTER_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Chapter {
+    pub start_ms: u64,
+    pub title: String,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChapterList {
+    pub chapters: Vec<Chapter>,
+    pub duration_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChapterFormat {
+    /// Líneas `mm:ss Título` para pegar en la descripción del video.
+    Youtube,
+    Webvtt,
+    /// Metadatos para `ffmpeg -i audio -i chapters.txt -map_metadata 1`.
+    Ffmetadata,
+}
+
+pub fn detect_chapters(
+    emit: EmitType,
+    segments: &[TranscriptSegment],
+    llm_model: Option<&str>,
+    backend: Option<&LlmBackendConfig>,
+) -> Result<ChapterList, String> {
+    let blocks = merge_segments(segments, BLOCK_MAX_MS, BLOCK_MAX_CHARS);
+    let Some(duration_ms) = blocks.last().map(|b| b.to_ms) else {
+        return Err("La transcripción no tiene segmentos".into());
+    };
+
+    let (model_name, backend_config) = resolve_llm(llm_model, backend);
+    let backend = prepare_backend(&*emit, &backend_config, &model_name)?;
+
+    let sample = blocks.iter().map(|b| b.text.as_str()).collect::<Vec<_>>().join(" ");
+    let budget = TokenBudget::measure(backend.as_ref(), &sample);
+    // Cada bloque suma su marca de tiempo "[mm:ss] " al texto que ve el modelo
+    let groups = group_blocks(&blocks, budget.input_chars(SECTION_MAX_TOKENS) * 9 / 10);
+    let total = groups.len();
+    let mut candidates: Vec<Chapter> = Vec::new();
+    for (idx, group) in groups.iter().enumerate() {
+        emit(
+            "summary_progress",
+            &format!("Detectando capítulos: sección {}/{}", idx + 1, total),
+            Some((idx as f32 / total as f32 * 80.0) as u32),
+        );
+        let prompt = build_section_prompt(group, idx + 1, total);
+        candidates.extend(request_chapters(backend.as_ref(), &*emit, &prompt, SECTION_MAX_TOKENS)?);
+    }
+
+    if candidates.len() > MAX_CHAPTERS {
+        emit("summary_progress", "Agrupando capítulos", Some(85));
+        let prompt = build_merge_prompt(&candidates);
+        let merged = request_chapters(backend.as_ref(), &*emit, &prompt, 800)?;
+        if !merged.is_empty() {
+            candidates = merged;
+        }
+    }
+
+    let chapters = normalize(candidates, &blocks, duration_ms);
+    if chapters.is_empty() {
+        return Err("El modelo no devolvió capítulos válidos".into());
+    }
+    emit("summary_progress", "Completado", Some(100));
+    Ok(ChapterList { chapters, duration_ms })
+}
+
+pub fn export_chapters(chapters: &[Chapter], duration_ms: u64, format: ChapterFormat) -> String {
+    let ends: Vec<u64> = chapters
+        .iter()
+        .skip(1)
+        .map(|c| c.start_ms)
+        .chain(std::iter::once(duration_ms))
+        .collect();
+
+    match format {
+        ChapterFormat::Youtube => chapters
+            .iter()
+            .map(|c| format!("{} {}", format_timestamp(c.start_ms), c.title))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        ChapterFormat::Webvtt => {
+            let mut out = String::from("WEBVTT\n");
+            for (idx, (chapter, end)) in chapters.iter().zip(&ends).enumerate() {
+                out.push_str(&format!(
+                    "\n{}\n{} --> {}\n{}\n",
+                    idx + 1,
+                    format_vtt_time(chapter.start_ms),
+                    format_vtt_time(*end),
+                    escape_vtt(&chapter.title)
+                ));
+            }
+            out
+        }
+        ChapterFormat::Ffmetadata => {
+            let mut out = String::from(";FFMETADATA1\n");
+            for (chapter, end) in chapters.iter().zip(&ends) {
+                out.push_str(&format!(
+                    "\n[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+                    chapter.start_ms,
+                    end,
+                    escape_ffmetadata(&chapter.title)
+                ));
+            }
+            out
+        }
+    }
+}
+
+fn format_vtt_time(ms: u64) -> String {
+    format!("{:02}:{:02}:{:02}.{:03}", ms / 3_600_000, (ms / 60_000) % 60, (ms / 1000) % 60, ms % 1000)
+}
+
+/// En el texto de un cue WebVTT `&` y `<` abren entidades y etiquetas, `-->` no puede
+/// aparecer y una línea en blanco termina el cue.
+fn escape_vtt(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' | '\n' => out.push(' '),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// ffmetadata reserva `=`, `;`, `#`, `\` y saltos de línea.
+fn escape_ffmetadata(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+// ─── Prompts ─────────────────────────────────────────────────────────────────
+
+/// Agrupa bloques consecutivos hasta `max_chars` caracteres por llamada al modelo.
+fn group_blocks(blocks: &[TranscriptSegment], max_chars: usize) -> Vec<&[TranscriptSegment]> {
+    let mut groups = Vec::new();
+    let mut start = 0;
+    let mut chars = 0;
+    for (idx, block) in blocks.iter().enumerate() {
+        let len = block.text.chars().count();
+        if chars > 0 && chars + len > max_chars {
+            groups.push(&blocks[start..idx]);
+            start = idx;
+            chars = 0;
+        }
+        chars += len;
+    }
+    groups.push(&blocks[start..]);
+    groups
+}
+
+fn build_section_prompt(blocks: &[TranscriptSegment], section: usize, total: usize) -> ChatPrompt {
+    let transcript = blocks
+        .iter()
+        .map(|b| format!("[{}] {}", format_timestamp(b.from_ms), b.text))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let user = format!(
+        "Sección {} de {} de la transcripción:\n{}\n\nIdentifica dónde empieza cada tema nuevo. \
+        Para cada capítulo usa como inicio una marca de tiempo de la transcripción, un título de \
+        máximo 6 palabras y una descripción de una oración. Formato:\n{}",
+        section, total, transcript, CHAPTERS_FORMAT
+    );
+    ChatPrompt { system: CHAPTERS_SYSTEM_PROMPT.to_string(), user }
+}
+
+fn build_merge_prompt(chapters: &[Chapter]) -> ChatPrompt {
+    let list = chapters
+        .iter()
+        .map(|c| format!("[{}] {}: {}", format_timestamp(c.start_ms), c.title, c.description))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let user = format!(
+        "Capítulos detectados en orden:\n{}\n\nFusiona capítulos contiguos del mismo tema hasta dejar \
+        como máximo {}. Conserva la marca de tiempo del primer capítulo de cada grupo. Formato:\n{}",
+        list, MAX_CHAPTERS, CHAPTERS_FORMAT
+    );
+    ChatPrompt { system: CHAPTERS_SYSTEM_PROMPT.to_string(), user }
+}
+
+fn request_chapters(
+    backend: &dyn LlmBackend,
+    emit: &dyn Fn(&str, &str, Option<u32>),
+    prompt: &ChatPrompt,
+    max_tokens: u32,
+) -> Result<Vec<Chapter>, String> {
+    let output = backend.complete(emit, prompt, max_tokens, false)?;
+    let Some(parsed) = extract_json(&output).and_then(|json| serde_json::from_str::<Value>(json).ok()) else {
+        log::warn!("chapters: respuesta sin JSON válido");
+        return Ok(Vec::new());
+    };
+    let items = parsed["chapters"].as_array().cloned().unwrap_or_default();
+    Ok(items
+        .iter()
+        .filter_map(|item| {
+            let title = item["title"].as_str()?.trim().to_string();
+            if title.is_empty() {
+                return None;
+            }
+            Some(Chapter {
+                start_ms: parse_start(&item["start"])?,
+                title,
+                description: item["description"].as_str().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect())
+}
+
+/// Acepta "mm:ss", "h:mm:ss" o segundos como número.
+fn parse_start(value: &Value) -> Option<u64> {
+    if let Some(seconds) = value.as_f64() {
+        return Some((seconds.max(0.0) * 1000.0) as u64);
+    }
+    let text = value.as_str()?.trim().trim_matches(|c| c == '[' || c == ']');
+    let mut seconds: u64 = 0;
+    for part in text.split(':') {
+        seconds = seconds * 60 + part.trim().parse::<u64>().ok()?;
+    }
+    Some(seconds * 1000)
+}
+
+/// Ajusta cada inicio al bloque real más cercano, ordena, fuerza el primero a 0:00
+/// y descarta capítulos más cortos que `MIN_CHAPTER_MS`.
+fn normalize(mut chapters: Vec<Chapter>, blocks: &[TranscriptSegment], duration_ms: u64) -> Vec<Chapter> {
+    for chapter in chapters.iter_mut() {
+        if let Some(block) = blocks.iter().min_by_key(|b| b.from_ms.abs_diff(chapter.start_ms)) {
+            chapter.start_ms = block.from_ms;
+        }
+    }
+    chapters.sort_by_key(|c| c.start_ms);
+
+    let mut result: Vec<Chapter> = Vec::new();
+    for chapter in chapters {
+        match result.last() {
+            Some(last) if chapter.start_ms < last.start_ms + MIN_CHAPTER_MS => continue,
+            _ if duration_ms.saturating_sub(chapter.start_ms) < MIN_CHAPTER_MS && !result.is_empty() => continue,
+            _ => result.push(chapter),
+        }
+    }
+    if let Some(first) = result.first_mut() {
+        first.start_ms = 0;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(from_ms: u64, text: &str) -> TranscriptSegment {
+        TranscriptSegment { from_ms, to_ms: from_ms + 1000, text: text.to_string() }
+    }
+
+    fn chapter(start_ms: u64, title: &str) -> Chapter {
+        Chapter { start_ms, title: title.to_string(), description: String::new() }
+    }
+
+    #[test]
+    fn group_blocks_counts_chars_not_bytes() {
+        // 10 caracteres y 20 bytes cada uno: dos caben en 20 caracteres
+        let blocks = vec![block(0, "áéíóúáéíóú"), block(1000, "ññññññññññ"), block(2000, "üüüüüüüüüü")];
+        let groups = group_blocks(&blocks, 20);
+        assert_eq!(groups.iter().map(|g| g.len()).collect::<Vec<_>>(), vec![2, 1]);
+    }
+
+    #[test]
+    fn group_blocks_keeps_an_oversized_block_alone() {
+        let blocks = vec![block(0, &"a".repeat(50)), block(1000, "b")];
+        let groups = group_blocks(&blocks, 10);
+        assert_eq!(groups.iter().map(|g| g.len()).collect::<Vec<_>>(), vec![1, 1]);
+    }
+
+    #[test]
+    fn parse_start_accepts_timestamps_and_seconds() {
+        assert_eq!(parse_start(&Value::from("01:30")), Some(90_000));
+        assert_eq!(parse_start(&Value::from("[1:02:03]")), Some(3_723_000));
+        assert_eq!(parse_start(&Value::from(12.5)), Some(12_500));
+        assert_eq!(parse_start(&Value::from(-3)), Some(0));
+        assert_eq!(parse_start(&Value::from("intro")), None);
+    }
+
+    #[test]
+    fn normalize_snaps_sorts_and_drops_short_chapters() {
+        let blocks = vec![block(0, "a"), block(60_000, "b"), block(120_000, "c"), block(125_000, "d")];
+        let chapters = vec![chapter(118_000, "tercero"), chapter(5_000, "primero"), chapter(61_000, "segundo"), chapter(125_000, "corto")];
+        let normalized = normalize(chapters, &blocks, 200_000);
+        let starts: Vec<(u64, &str)> = normalized.iter().map(|c| (c.start_ms, c.title.as_str())).collect();
+        assert_eq!(starts, vec![(0, "primero"), (60_000, "segundo"), (120_000, "tercero")]);
+    }
+
+    #[test]
+    fn normalize_drops_a_chapter_too_close_to_the_end() {
+        let blocks = vec![block(0, "a"), block(95_000, "b")];
+        let normalized = normalize(vec![chapter(0, "uno"), chapter(95_000, "dos")], &blocks, 100_000);
+        assert_eq!(normalized.len(), 1);
+    }
+
+    #[test]
+    fn escape_vtt_neutralizes_markup_and_cue_breaks() {
+        assert_eq!(escape_vtt("Q&A <b>final</b> --> fin\n\nsigue"), "Q&amp;A &lt;b&gt;final&lt;/b&gt; --&gt; fin  sigue");
+    }
+
+    #[test]
+    fn escape_ffmetadata_escapes_reserved_chars() {
+        assert_eq!(escape_ffmetadata("a=b;c#d\\e\nf"), "a\\=b\\;c\\#d\\\\e\\\nf");
+    }
+
+    #[test]
+    fn webvtt_export_escapes_titles() {
+        let vtt = export_chapters(&[chapter(0, "Intro <1>"), chapter(60_000, "Cierre")], 120_000, ChapterFormat::Webvtt);
+        assert!(vtt.contains("00:00:00.000 --> 00:01:00.000\nIntro &lt;1&gt;\n"));
+        assert!(vtt.contains("00:01:00.000 --> 00:02:00.000\nCierre\n"));
+    }
+}
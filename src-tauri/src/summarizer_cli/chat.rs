@@ -0,0 +1,296 @@
+use std::collections::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::audio_processor::{merge_segments, TranscriptSegment};
+use super::llm_backend::ChatPrompt;
+use super::{format_timestamp, prepare_backend, resolve_llm, EmitType, LlmBackendConfig};
+
+const CHAT_SYSTEM_PROMPT: &str = "Respondes preguntas sobre una transcripción de audio en español. \
+    Usa únicamente los fragmentos numerados que se te entregan. Cita cada afirmación con el número \
+    del fragmento entre corchetes, por ejemplo [2]. Si la respuesta no aparece en los fragmentos, \
+    dilo claramente en vez de inventarla. Responde en prosa breve, sin markdown.";
+
+/// Segmentos consecutivos se agrupan en pasajes de hasta este largo para dar contexto a cada cita.
+const PASSAGE_MAX_MS: u64 = 45_000;
+const PASSAGE_MAX_CHARS: usize = 600;
+const TOP_PASSAGES: usize = 6;
+/// Turnos previos que se incluyen en el prompt para resolver preguntas de seguimiento.
+const HISTORY_TURNS: usize = 4;
+const ANSWER_MAX_TOKENS: u32 = 400;
+
+const STOPWORDS: &[&str] = &[
+    "que", "qué", "los", "las", "del", "por", "para", "con", "una", "uno", "unos", "unas", "como", "cómo",
+    "pero", "sus", "esta", "este", "esto", "estos", "estas", "ese", "esa", "eso", "fue", "son", "hay",
+    "dijo", "dice", "sobre", "entre", "cuando", "cuándo", "donde", "dónde", "quien", "quién", "cual", "cuál",
+    "muy", "mas", "más", "también", "ya", "les", "nos", "todo", "todos", "hizo", "habla", "hablo", "habló",
+];
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Citation {
+    /// Número usado por el modelo en la respuesta, p. ej. `[2]`.
+    pub index: usize,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ChatTurn {
+    pub question: String,
+    pub answer: String,
+    pub citations: Vec<Citation>,
+    /// Segundos desde UNIX_EPOCH.
+    pub created_at: u64,
+}
+
+#[derive(Default, Deserialize, Serialize)]
+struct ChatHistory {
+    transcript_id: String,
+    turns: Vec<ChatTurn>,
+}
+
+struct Passage {
+    from_ms: u64,
+    to_ms: u64,
+    text: String,
+    terms: Vec<String>,
+}
+
+/// Responde una pregunta sobre la transcripción y la añade al historial de `transcript_id`
+/// (normalmente la ruta del audio).
+pub fn ask(
+    emit: EmitType,
+    transcript_id: &str,
+    segments: &[TranscriptSegment],
+    question: &str,
+    llm_model: Option<&str>,
+    backend: Option<&LlmBackendConfig>,
+) -> Result<ChatTurn, String> {
+    let question = question.trim();
+    if question.is_empty() {
+        return Err("La pregunta está vacía".into());
+    }
+    if segments.is_empty() {
+        return Err("La transcripción no tiene segmentos".into());
+    }
+
+    let mut history = load_history(transcript_id);
+    let passages = build_passages(segments);
+    let previous = history.turns.last().map(|t| t.question.as_str());
+    let selected = retrieve(&passages, question, previous);
+    log::info!("chat passages={} selected={}", passages.len(), selected.len());
+
+    let (model_name, backend_config) = resolve_llm(llm_model, backend);
+    let backend = prepare_backend(&*emit, &backend_config, &model_name)?;
+    let prompt = build_chat_prompt(&history.turns, &selected, question);
+
+    emit("summary_progress", "Buscando la respuesta en la transcripción", None);
+    let answer = backend.complete(&*emit, &prompt, ANSWER_MAX_TOKENS, false)?;
+    let citations = extract_citations(&answer, &selected);
+
+    let turn = ChatTurn {
+        question: question.to_string(),
+        answer,
+        citations,
+        created_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+    };
+    history.transcript_id = transcript_id.to_string();
+    history.turns.push(turn.clone());
+    save_history(&history)?;
+
+    emit("summary_progress", "Completado", Some(100));
+    Ok(turn)
+}
+
+pub fn history(transcript_id: &str) -> Vec<ChatTurn> {
+    load_history(transcript_id).turns
+}
+
+pub fn clear_history(transcript_id: &str) -> Result<(), String> {
+    let path = history_path(transcript_id);
+    if !path.exists() {
+        return Ok(());
+    }
+    std::fs::remove_file(path).map_err(|e| format!("No se pudo borrar el historial: {}", e))
+}
+
+// ─── History storage ─────────────────────────────────────────────────────────
+
+fn history_path(transcript_id: &str) -> std::path::PathBuf {
+    let hash = crate::utils::stable_hash(transcript_id);
+    crate::utils::models_base_dir().join("chats").join(format!("{:016x}.json", hash))
+}
+
+fn load_history(transcript_id: &str) -> ChatHistory {
+    std::fs::read_to_string(history_path(transcript_id))
+        .ok()
+        .and_then(|content| serde_json::from_str::<ChatHistory>(&content).ok())
+        .filter(|h| h.transcript_id == transcript_id)
+        .unwrap_or_default()
+}
+
+fn save_history(history: &ChatHistory) -> Result<(), String> {
+    let path = history_path(&history.transcript_id);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("No se pudo crear el directorio de chats: {}", e))?;
+    }
+    let json = serde_json::to_string_pretty(history).map_err(|e| e.to_string())?;
+    std::fs::write(&path, json).map_err(|e| format!("No se pudo guardar el historial: {}", e))
+}
+
+// ─── Retrieval ───────────────────────────────────────────────────────────────
+
+fn build_passages(segments: &[TranscriptSegment]) -> Vec<Passage> {
+    merge_segments(segments, PASSAGE_MAX_MS, PASSAGE_MAX_CHARS)
+        .into_iter()
+        .map(|s| Passage { terms: tokenize(&s.text), from_ms: s.from_ms, to_ms: s.to_ms, text: s.text })
+        .collect()
+}
+
+/// Minúsculas, sin tildes y sin palabras vacías: "Presupuesto" y "presupuestó" coinciden.
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|w| w.chars().count() >= 3)
+        .map(|w| w.to_lowercase())
+        .filter(|w| !STOPWORDS.contains(&w.as_str()))
+        .map(|w| {
+            w.chars()
+                .map(|c| match c {
+                    'á' => 'a',
+                    'é' => 'e',
+                    'í' => 'i',
+                    'ó' => 'o',
+                    'ú' | 'ü' => 'u',
+                    c => c,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// BM25 sobre los pasajes. La pregunta anterior suma con la mitad de peso para que
+/// "¿y qué más dijo?" siga apuntando al mismo tema. Sin coincidencias, se toman pasajes
+/// repartidos por todo el audio (preguntas generales como "¿de qué trata?").
+fn retrieve<'a>(passages: &'a [Passage], question: &str, previous: Option<&str>) -> Vec<(usize, &'a Passage)> {
+    const K1: f32 = 1.2;
+    const B: f32 = 0.75;
+
+    let mut query: HashMap<String, f32> = HashMap::new();
+    for term in previous.map(tokenize).unwrap_or_default() {
+        query.insert(term, 0.5);
+    }
+    for term in tokenize(question) {
+        query.insert(term, 1.0);
+    }
+
+    let n = passages.len() as f32;
+    let avg_len = passages.iter().map(|p| p.terms.len()).sum::<usize>() as f32 / n.max(1.0);
+    let mut scored: Vec<(f32, usize)> = passages
+        .iter()
+        .enumerate()
+        .map(|(idx, passage)| {
+            let len = passage.terms.len() as f32;
+            let score = query.iter().map(|(term, weight)| {
+                let tf = passage.terms.iter().filter(|t| *t == term).count() as f32;
+                if tf == 0.0 {
+                    return 0.0;
+                }
+                let df = passages.iter().filter(|p| p.terms.contains(term)).count() as f32;
+                let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                weight * idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * len / avg_len.max(1.0)))
+            }).sum::<f32>();
+            (score, idx)
+        })
+        .filter(|(score, _)| *score > 0.0)
+        .collect();
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+    let mut chosen: Vec<usize> = scored.into_iter().take(TOP_PASSAGES).map(|(_, idx)| idx).collect();
+    if chosen.is_empty() {
+        let step = (passages.len() / TOP_PASSAGES).max(1);
+        chosen = (0..passages.len()).step_by(step).take(TOP_PASSAGES).collect();
+    }
+    // En orden cronológico el modelo sigue mejor el hilo del audio
+    chosen.sort_unstable();
+    chosen.into_iter().enumerate().map(|(i, idx)| (i + 1, &passages[idx])).collect()
+}
+
+// ─── Prompt & citations ──────────────────────────────────────────────────────
+
+fn build_chat_prompt(turns: &[ChatTurn], passages: &[(usize, &Passage)], question: &str) -> ChatPrompt {
+    let mut user = String::new();
+    let recent = &turns[turns.len().saturating_sub(HISTORY_TURNS)..];
+    if !recent.is_empty() {
+        user.push_str("Conversación previa:\n");
+        for turn in recent {
+            user.push_str(&format!("Usuario: {}\nAsistente: {}\n", turn.question, history_answer(turn)));
+        }
+        user.push('\n');
+    }
+    user.push_str("Fragmentos de la transcripción:\n");
+    for (num, passage) in passages {
+        user.push_str(&format!("[{}] ({}) {}\n", num, format_timestamp(passage.from_ms), passage.text));
+    }
+    user.push_str(&format!("\nPregunta: {}\nRespuesta:", question));
+    ChatPrompt { system: CHAT_SYSTEM_PROMPT.to_string(), user }
+}
+
+/// Respuesta previa sin sus `[n]`: esa numeración era la de los fragmentos de aquel turno
+/// y en el prompt actual apuntaría a otros. Cada cita se reemplaza por su marca de tiempo.
+fn history_answer(turn: &ChatTurn) -> String {
+    let mut out = String::with_capacity(turn.answer.len());
+    let mut rest = turn.answer.as_str();
+    while let Some(open) = rest.find('[') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let Some(close) = after.find(']') else {
+            out.push_str(&rest[open..]);
+            return out;
+        };
+        let numbers: Option<Vec<usize>> = after[..close].split(',').map(|n| n.trim().parse().ok()).collect();
+        match numbers {
+            Some(numbers) => {
+                let times: Vec<String> = numbers
+                    .iter()
+                    .filter_map(|num| turn.citations.iter().find(|c| c.index == *num))
+                    .map(|c| format_timestamp(c.from_ms))
+                    .collect();
+                if !times.is_empty() {
+                    out.push_str(&format!("({})", times.join(", ")));
+                }
+            }
+            // Corchetes que no son citas se dejan como están
+            None => out.push_str(&rest[open..open + close + 2]),
+        }
+        rest = &after[close + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Convierte los `[n]` de la respuesta en citas con tiempos. Ignora números que no
+/// corresponden a ningún fragmento entregado.
+fn extract_citations(answer: &str, passages: &[(usize, &Passage)]) -> Vec<Citation> {
+    let mut seen: HashSet<usize> = HashSet::new();
+    let mut citations = Vec::new();
+    for (open, _) in answer.match_indices('[') {
+        let rest = &answer[open + 1..];
+        let Some(close) = rest.find(']') else { continue };
+        // "[1, 3]" y "[1][3]" son equivalentes
+        for num in rest[..close].split(',').filter_map(|n| n.trim().parse::<usize>().ok()) {
+            let Some((_, passage)) = passages.iter().find(|(i, _)| *i == num) else { continue };
+            if seen.insert(num) {
+                citations.push(Citation {
+                    index: num,
+                    from_ms: passage.from_ms,
+                    to_ms: passage.to_ms,
+                    text: passage.text.clone(),
+                });
+            }
+        }
+    }
+    citations
+}
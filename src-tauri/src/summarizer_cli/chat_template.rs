@@ -0,0 +1,71 @@
+use minijinja::{context, Environment, Error, ErrorKind};
+
+use super::gguf::GgufMetadata;
+
+/// Plantilla Jinja `tokenizer.chat_template` embebida en el GGUF, la misma que usan
+/// transformers y `llama-server --jinja`.
+pub struct ChatTemplate {
+    source: String,
+    bos_token: String,
+    eos_token: String,
+    /// llama-completion ya antepone BOS al tokenizar; no hay que duplicarlo en el texto.
+    strip_bos: bool,
+}
+
+impl ChatTemplate {
+    pub fn from_metadata(metadata: &GgufMetadata) -> Option<ChatTemplate> {
+        let source = metadata.get_str("tokenizer.chat_template")?.to_string();
+        Some(ChatTemplate {
+            source,
+            bos_token: metadata.token_text("tokenizer.ggml.bos_token_id").unwrap_or_default().to_string(),
+            eos_token: metadata.token_text("tokenizer.ggml.eos_token_id").unwrap_or_default().to_string(),
+            strip_bos: metadata.get_bool("tokenizer.ggml.add_bos_token").unwrap_or(true),
+        })
+    }
+
+    /// Renderiza system + user con el prompt de generación abierto. Plantillas que no
+    /// admiten rol de sistema (Gemma) lanzan `raise_exception`; en ese caso el prompt de
+    /// sistema se antepone al mensaje del usuario.
+    pub fn render(&self, system: &str, user: &str) -> Result<String, String> {
+        let with_system = [("system", system), ("user", user)];
+        let merged_user = format!("{}\n\n{}", system, user);
+        let merged = [("user", merged_user.as_str())];
+
+        let rendered = self
+            .render_messages(&with_system)
+            .or_else(|_| self.render_messages(&merged))
+            .map_err(|e| format!("Error aplicando la plantilla de chat del modelo: {}", e))?;
+
+        let rendered = if self.strip_bos && !self.bos_token.is_empty() {
+            rendered.strip_prefix(&self.bos_token).map(str::to_string).unwrap_or(rendered)
+        } else {
+            rendered
+        };
+        Ok(rendered)
+    }
+
+    fn render_messages(&self, messages: &[(&str, &str)]) -> Result<String, Error> {
+        let mut env = Environment::new();
+        // Mismas opciones que transformers al compilar chat templates
+        env.set_trim_blocks(true);
+        env.set_lstrip_blocks(true);
+        env.set_unknown_method_callback(minijinja_contrib::pycompat::unknown_method_callback);
+        env.add_function("raise_exception", |message: String| -> Result<String, Error> {
+            Err(Error::new(ErrorKind::InvalidOperation, message))
+        });
+        env.add_template("chat", &self.source)?;
+
+        let messages: Vec<_> = messages
+            .iter()
+            .map(|(role, content)| context! { role => role, content => content })
+            .collect();
+        env.get_template("chat")?.render(context! {
+            messages => messages,
+            add_generation_prompt => true,
+            bos_token => self.bos_token,
+            eos_token => self.eos_token,
+            // Qwen3 y similares: sin bloque de razonamiento, directo a la respuesta
+            enable_thinking => false,
+        })
+    }
+}
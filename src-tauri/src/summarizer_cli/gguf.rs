@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::io::{BufReader, Read};
+
+const GGUF_MAGIC: &[u8; 4] = b"GGUF";
+/// Protege contra cabeceras corruptas que anuncian cadenas o arrays gigantes.
+const MAX_STRING_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Vocabulario: se conserva para traducir ids de tokens especiales a texto.
+const TOKENS_KEY: &str = "tokenizer.ggml.tokens";
+
+/// Sólo se conservan enteros, booleanos, cadenas y el vocabulario; flotantes y el resto
+/// de arrays (merges, scores) se leen para avanzar pero no se guardan.
+#[derive(Debug, Clone)]
+pub enum GgufValue {
+    Int(i64),
+    Bool(bool),
+    String(String),
+    StringArray(Vec<String>),
+    Skipped,
+}
+
+/// Pares clave/valor de la cabecera GGUF (sin leer los tensores).
+pub struct GgufMetadata {
+    values: HashMap<String, GgufValue>,
+}
+
+impl GgufMetadata {
+    pub fn read(path: &std::path::Path) -> Result<GgufMetadata, String> {
+        let file = std::fs::File::open(path)
+            .map_err(|e| format!("No se pudo abrir {}: {}", path.display(), e))?;
+        let mut reader = BufReader::new(file);
+        Self::parse(&mut reader).map_err(|e| format!("Cabecera GGUF inválida en {}: {}", path.display(), e))
+    }
+
+    fn parse(reader: &mut impl Read) -> std::io::Result<GgufMetadata> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != GGUF_MAGIC {
+            return Err(invalid("no es un archivo GGUF"));
+        }
+        let version = read_u32(reader)?;
+        if version < 2 {
+            return Err(invalid("versión GGUF no soportada"));
+        }
+        let _tensor_count = read_u64(reader)?;
+        let kv_count = read_u64(reader)?;
+
+        let mut values = HashMap::new();
+        for _ in 0..kv_count {
+            let key = read_string(reader)?;
+            let value_type = read_u32(reader)?;
+            let value = if value_type == 9 {
+                read_array(reader, key == TOKENS_KEY)?
+            } else {
+                read_value(reader, value_type)?
+            };
+            values.insert(key, value);
+        }
+        Ok(GgufMetadata { values })
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.values.get(key)? {
+            GgufValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn get_int(&self, key: &str) -> Option<i64> {
+        match self.values.get(key)? {
+            GgufValue::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        match self.values.get(key)? {
+            GgufValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Texto de un token especial a partir de su id (`tokenizer.ggml.bos_token_id`, etc.).
+    pub fn token_text(&self, id_key: &str) -> Option<&str> {
+        let id = usize::try_from(self.get_int(id_key)?).ok()?;
+        match self.values.get(TOKENS_KEY)? {
+            GgufValue::StringArray(tokens) => tokens.get(id).map(String::as_str),
+            _ => None,
+        }
+    }
+
+    pub fn architecture(&self) -> Option<&str> {
+        self.get_str("general.architecture")
+    }
+
+    /// Contexto con el que se entrenó el modelo (`<arch>.context_length`).
+    pub fn context_length(&self) -> Option<usize> {
+        let key = format!("{}.context_length", self.architecture()?);
+        self.get_int(&key).and_then(|v| usize::try_from(v).ok())
+    }
+}
+
+fn invalid(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg.to_string())
+}
+
+fn read_u32(reader: &mut impl Read) -> std::io::Result<u32> {
+    let mut buf = [0u8; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(reader: &mut impl Read) -> std::io::Result<u64> {
+    let mut buf = [0u8; 8];
+    reader.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_string(reader: &mut impl Read) -> std::io::Result<String> {
+    let len = read_u64(reader)?;
+    if len > MAX_STRING_BYTES {
+        return Err(invalid("cadena demasiado larga"));
+    }
+    let mut buf = vec![0u8; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Tamaño en bytes de los tipos escalares, por id de tipo GGUF.
+fn scalar_size(value_type: u32) -> Option<u64> {
+    match value_type {
+        0 | 1 | 7 => Some(1),
+        2 | 3 => Some(2),
+        4..=6 => Some(4),
+        10..=12 => Some(8),
+        _ => None,
+    }
+}
+
+fn read_value(reader: &mut impl Read, value_type: u32) -> std::io::Result<GgufValue> {
+    let size = scalar_size(value_type);
+    match value_type {
+        8 => return read_string(reader).map(GgufValue::String),
+        9 => return read_array(reader, false),
+        _ if size.is_none() => return Err(invalid("tipo de valor desconocido")),
+        _ => {}
+    }
+
+    let mut buf = [0u8; 8];
+    let size = size.unwrap_or_default() as usize;
+    reader.read_exact(&mut buf[..size])?;
+    Ok(match value_type {
+        0 => GgufValue::Int(buf[0] as i64),
+        1 => GgufValue::Int(buf[0] as i8 as i64),
+        2 => GgufValue::Int(u16::from_le_bytes([buf[0], buf[1]]) as i64),
+        3 => GgufValue::Int(i16::from_le_bytes([buf[0], buf[1]]) as i64),
+        4 => GgufValue::Int(u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as i64),
+        5 => GgufValue::Int(i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as i64),
+        7 => GgufValue::Bool(buf[0] != 0),
+        10 => GgufValue::Int(u64::from_le_bytes(buf) as i64),
+        11 => GgufValue::Int(i64::from_le_bytes(buf)),
+        _ => GgufValue::Skipped,
+    })
+}
+
+fn read_array(reader: &mut impl Read, keep_strings: bool) -> std::io::Result<GgufValue> {
+    let item_type = read_u32(reader)?;
+    let len = read_u64(reader)?;
+    if item_type == 8 {
+        let mut items = Vec::new();
+        for _ in 0..len {
+            let item = read_string(reader)?;
+            if keep_strings {
+                items.push(item);
+            }
+        }
+        return Ok(if keep_strings { GgufValue::StringArray(items) } else { GgufValue::Skipped });
+    }
+    if item_type == 9 {
+        for _ in 0..len {
+            read_array(reader, false)?;
+        }
+        return Ok(GgufValue::Skipped);
+    }
+    let size = scalar_size(item_type).ok_or_else(|| invalid("tipo de array desconocido"))?;
+    // `len` viene del archivo: un valor absurdo no debe desbordar
+    let bytes = size.checked_mul(len).ok_or_else(|| invalid("array demasiado grande"))?;
+    let skipped = std::io::copy(&mut reader.take(bytes), &mut std::io::sink())?;
+    if skipped != bytes {
+        return Err(invalid("array truncado"));
+    }
+    Ok(GgufValue::Skipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Constructor mínimo de cabeceras GGUF v3.
+    struct Header {
+        bytes: Vec<u8>,
+        kv_count: u64,
+    }
+
+    impl Header {
+        fn new() -> Header {
+            Header { bytes: Vec::new(), kv_count: 0 }
+        }
+
+        fn string(bytes: &mut Vec<u8>, s: &str) {
+            bytes.extend((s.len() as u64).to_le_bytes());
+            bytes.extend(s.as_bytes());
+        }
+
+        fn key(&mut self, key: &str, value_type: u32) -> &mut Vec<u8> {
+            self.kv_count += 1;
+            Self::string(&mut self.bytes, key);
+            self.bytes.extend(value_type.to_le_bytes());
+            &mut self.bytes
+        }
+
+        fn str(mut self, key: &str, value: &str) -> Header {
+            Self::string(self.key(key, 8), value);
+            self
+        }
+
+        fn u32(mut self, key: &str, value: u32) -> Header {
+            self.key(key, 4).extend(value.to_le_bytes());
+            self
+        }
+
+        fn string_array(mut self, key: &str, items: &[&str]) -> Header {
+            let bytes = self.key(key, 9);
+            bytes.extend(8u32.to_le_bytes());
+            bytes.extend((items.len() as u64).to_le_bytes());
+            for item in items {
+                Self::string(bytes, item);
+            }
+            self
+        }
+
+        fn raw(mut self, key: &str, value_type: u32, value: &[u8]) -> Header {
+            self.key(key, value_type).extend(value);
+            self
+        }
+
+        fn build(&self) -> Vec<u8> {
+            let mut out = GGUF_MAGIC.to_vec();
+            out.extend(3u32.to_le_bytes());
+            out.extend(0u64.to_le_bytes());
+            out.extend(self.kv_count.to_le_bytes());
+            out.extend(&self.bytes);
+            out
+        }
+    }
+
+    fn parse(bytes: &[u8]) -> std::io::Result<GgufMetadata> {
+        GgufMetadata::parse(&mut std::io::Cursor::new(bytes))
+    }
+
+    #[test]
+    fn parses_a_minimal_header() {
+        let header = Header::new()
+            .str("general.architecture", "llama")
+            .u32("llama.context_length", 8192)
+            .raw("general.quantized", 7, &[1])
+            .build();
+        let metadata = parse(&header).unwrap();
+        assert_eq!(metadata.architecture(), Some("llama"));
+        assert_eq!(metadata.context_length(), Some(8192));
+        assert_eq!(metadata.get_bool("general.quantized"), Some(true));
+    }
+
+    #[test]
+    fn rejects_bad_magic_and_old_versions() {
+        assert!(parse(b"GGML\x03\x00\x00\x00").is_err());
+        let mut v1 = Header::new().build();
+        v1[4] = 1;
+        assert!(parse(&v1).is_err());
+    }
+
+    #[test]
+    fn skips_floats_and_unkept_arrays() {
+        // Array de 3 f32 y array de arrays de cadenas
+        let mut scores = 6u32.to_le_bytes().to_vec();
+        scores.extend(3u64.to_le_bytes());
+        scores.extend([0u8; 12]);
+        let mut nested = 9u32.to_le_bytes().to_vec();
+        nested.extend(1u64.to_le_bytes());
+        nested.extend(8u32.to_le_bytes());
+        nested.extend(1u64.to_le_bytes());
+        nested.extend(2u64.to_le_bytes());
+        nested.extend(b"ab");
+        let header = Header::new()
+            .raw("general.alignment_f", 6, &1.5f32.to_le_bytes())
+            .raw("tokenizer.ggml.scores", 9, &scores)
+            .raw("tokenizer.ggml.nested", 9, &nested)
+            .string_array("tokenizer.ggml.merges", &["a b", "c d"])
+            .str("general.name", "after")
+            .build();
+        let metadata = parse(&header).unwrap();
+        assert!(matches!(metadata.values.get("tokenizer.ggml.merges"), Some(GgufValue::Skipped)));
+        assert!(matches!(metadata.values.get("general.alignment_f"), Some(GgufValue::Skipped)));
+        assert_eq!(metadata.get_str("general.name"), Some("after"));
+    }
+
+    #[test]
+    fn token_text_looks_up_the_vocabulary() {
+        let header = Header::new()
+            .string_array(TOKENS_KEY, &["<unk>", "<s>", "</s>"])
+            .u32("tokenizer.ggml.bos_token_id", 1)
+            .u32("tokenizer.ggml.eos_token_id", 7)
+            .build();
+        let metadata = parse(&header).unwrap();
+        assert_eq!(metadata.token_text("tokenizer.ggml.bos_token_id"), Some("<s>"));
+        assert_eq!(metadata.token_text("tokenizer.ggml.eos_token_id"), None);
+        assert_eq!(metadata.token_text("tokenizer.ggml.missing_id"), None);
+    }
+
+    #[test]
+    fn rejects_oversized_strings_before_allocating() {
+        let mut value = (MAX_STRING_BYTES + 1).to_le_bytes().to_vec();
+        value.extend(b"x");
+        let header = Header::new().raw("general.name", 8, &value).build();
+        assert!(parse(&header).err().unwrap().to_string().contains("demasiado larga"));
+    }
+
+    #[test]
+    fn rejects_arrays_whose_size_overflows() {
+        let mut value = 12u32.to_le_bytes().to_vec();
+        value.extend(u64::MAX.to_le_bytes());
+        let header = Header::new().raw("tokenizer.ggml.scores", 9, &value).build();
+        assert!(parse(&header).err().unwrap().to_string().contains("demasiado grande"));
+    }
+
+    #[test]
+    fn rejects_truncated_arrays_and_unknown_types() {
+        let mut value = 6u32.to_le_bytes().to_vec();
+        value.extend(1000u64.to_le_bytes());
+        value.extend([0u8; 8]);
+        assert!(parse(&Header::new().raw("a", 9, &value).build()).is_err());
+        assert!(parse(&Header::new().raw("a", 42, &[0; 8]).build()).is_err());
+    }
+}
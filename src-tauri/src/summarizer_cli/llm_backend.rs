@@ -0,0 +1,287 @@
+use std::io::{BufRead, BufReader};
+use std::sync::OnceLock;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+const DEFAULT_LLAMA_SERVER_URL: &str = "http://127.0.0.1:8080";
+const DEFAULT_OLLAMA_URL: &str = "http://127.0.0.1:11434";
+const CONNECT_TIMEOUT_SECS: u64 = 5;
+// Un resumen largo en CPU puede tardar varios minutos
+const READ_TIMEOUT_SECS: u64 = 1800;
+
+/// Tope de contexto aunque el modelo admita más: el KV cache crece linealmente
+/// y 128k tokens no caben en la RAM de un portátil.
+pub const MAX_CONTEXT: usize = 16384;
+
+const TEMPERATURE: f32 = 0.3;
+const SEED: u32 = 42;
+
+/// Prompt independiente del formato: los backends HTTP lo envían como mensajes de chat,
+/// llama-completion lo renderiza con la plantilla del modelo.
+pub struct ChatPrompt {
+    pub system: String,
+    pub user: String,
+}
+
+pub trait LlmBackend {
+    fn complete(
+        &self,
+        emit: &dyn Fn(&str, &str, Option<u32>),
+        prompt: &ChatPrompt,
+        max_tokens: u32,
+        stream: bool,
+    ) -> Result<String, String>;
+
+    /// Ventana de contexto efectiva (prompt + respuesta), si se puede averiguar.
+    fn context_length(&self) -> Option<usize> {
+        None
+    }
+
+    /// Tokens exactos del texto con el tokenizador del modelo, si el backend lo expone.
+    fn count_tokens(&self, _text: &str) -> Option<usize> {
+        None
+    }
+}
+
+/// Motor LLM elegido por petición.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LlmBackendConfig {
+    /// Binario llama-completion descargado + modelo GGUF en el directorio de datos.
+    #[default]
+    LlamaCompletion,
+    /// `llama-server` ya corriendo con su propio modelo cargado.
+    LlamaServer { url: Option<String> },
+    Ollama { url: Option<String>, model: String },
+    /// Cualquier endpoint `/v1/chat/completions` compatible con OpenAI.
+    OpenAi { url: String, model: String, api_key: Option<String> },
+}
+
+/// Construye el backend HTTP. `LlamaCompletion` lo resuelve el summarizer porque
+/// necesita descargar binario y modelo.
+pub fn connect(config: &LlmBackendConfig) -> Option<Box<dyn LlmBackend>> {
+    match config {
+        LlmBackendConfig::LlamaCompletion => None,
+        LlmBackendConfig::LlamaServer { url } => Some(Box::new(OpenAiBackend {
+            url: url.clone().unwrap_or_else(|| DEFAULT_LLAMA_SERVER_URL.to_string()),
+            model: None,
+            api_key: None,
+            llama_server: true,
+        })),
+        LlmBackendConfig::Ollama { url, model } => Some(Box::new(OllamaBackend {
+            url: url.clone().unwrap_or_else(|| DEFAULT_OLLAMA_URL.to_string()),
+            model: model.clone(),
+            num_ctx: OnceLock::new(),
+        })),
+        LlmBackendConfig::OpenAi { url, model, api_key } => Some(Box::new(OpenAiBackend {
+            url: url.clone(),
+            model: Some(model.clone()),
+            api_key: api_key.clone(),
+            llama_server: false,
+        })),
+    }
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout_connect(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+        .timeout_read(Duration::from_secs(READ_TIMEOUT_SECS))
+        .build()
+}
+
+fn http_error(e: ureq::Error) -> String {
+    match e {
+        ureq::Error::Status(code, r) => {
+            let detail = r.into_string().unwrap_or_default();
+            format!("el servidor LLM respondió {}: {}", code, detail.trim())
+        }
+        e => format!("no se pudo contactar al servidor LLM: {}", e),
+    }
+}
+
+/// Acumula la salida en streaming y emite los mismos eventos que llama-completion.
+pub struct StreamProgress {
+    max_tokens: u32,
+    read_count: u32,
+}
+
+impl StreamProgress {
+    pub fn new(max_tokens: u32) -> Self {
+        StreamProgress { max_tokens, read_count: 0 }
+    }
+
+    pub fn push(&mut self, emit: &dyn Fn(&str, &str, Option<u32>), output: &mut String, piece: &str, stream: bool) {
+        if piece.is_empty() {
+            return;
+        }
+        output.push_str(piece);
+        if !stream {
+            return;
+        }
+        emit("summary_segment", piece, None);
+        self.read_count += 1;
+        if self.read_count % 8 == 0 {
+            let progress = ((output.len() as f32 / (self.max_tokens as f32 * 3.5)).min(0.95) * 100.0) as u32;
+            emit("summary_progress", "Generando resumen", Some(progress));
+        }
+    }
+}
+
+// ─── OpenAI-compatible (incluye llama-server) ─────────────────────────────────
+
+struct OpenAiBackend {
+    url: String,
+    model: Option<String>,
+    api_key: Option<String>,
+    /// llama-server expone además `/props` y `/tokenize`.
+    llama_server: bool,
+}
+
+impl LlmBackend for OpenAiBackend {
+    fn complete(
+        &self,
+        emit: &dyn Fn(&str, &str, Option<u32>),
+        prompt: &ChatPrompt,
+        max_tokens: u32,
+        stream: bool,
+    ) -> Result<String, String> {
+        let endpoint = format!("{}/v1/chat/completions", self.url.trim_end_matches('/'));
+        let mut body = json!({
+            "messages": [
+                { "role": "system", "content": prompt.system },
+                { "role": "user", "content": prompt.user },
+            ],
+            "max_tokens": max_tokens,
+            "temperature": TEMPERATURE,
+            "seed": SEED,
+            "stream": stream,
+        });
+        if let Some(model) = &self.model {
+            body["model"] = json!(model);
+        }
+
+        let mut request = agent().post(&endpoint);
+        if let Some(key) = self.api_key.as_deref().filter(|k| !k.is_empty()) {
+            request = request.set("Authorization", &format!("Bearer {}", key));
+        }
+        let response = request.send_json(body).map_err(http_error)?;
+
+        if !stream {
+            let value: Value = serde_json::from_reader(response.into_reader())
+                .map_err(|e| format!("respuesta inválida del servidor LLM: {}", e))?;
+            let content = value["choices"][0]["message"]["content"].as_str().unwrap_or_default();
+            return super::clean_llm_output(content);
+        }
+
+        // Server-sent events: "data: {...}" por línea, termina con "data: [DONE]"
+        let mut output = String::new();
+        let mut progress = StreamProgress::new(max_tokens);
+        for line in BufReader::new(response.into_reader()).lines() {
+            let line = line.map_err(|e| format!("Error leyendo salida del servidor LLM: {}", e))?;
+            let Some(data) = line.strip_prefix("data:").map(str::trim) else { continue };
+            if data == "[DONE]" {
+                break;
+            }
+            let Ok(chunk) = serde_json::from_str::<Value>(data) else { continue };
+            if let Some(piece) = chunk["choices"][0]["delta"]["content"].as_str() {
+                progress.push(emit, &mut output, piece, stream);
+            }
+        }
+        super::clean_llm_output(&output)
+    }
+
+    fn context_length(&self) -> Option<usize> {
+        if !self.llama_server {
+            return None;
+        }
+        let endpoint = format!("{}/props", self.url.trim_end_matches('/'));
+        let props: Value = agent().get(&endpoint).call().ok()?.into_json().ok()?;
+        props["default_generation_settings"]["n_ctx"].as_u64().map(|n| n as usize)
+    }
+
+    fn count_tokens(&self, text: &str) -> Option<usize> {
+        if !self.llama_server {
+            return None;
+        }
+        let endpoint = format!("{}/tokenize", self.url.trim_end_matches('/'));
+        let response: Value = agent().post(&endpoint).send_json(json!({ "content": text })).ok()?.into_json().ok()?;
+        response["tokens"].as_array().map(|t| t.len())
+    }
+}
+
+// ─── Ollama ───────────────────────────────────────────────────────────────────
+
+struct OllamaBackend {
+    url: String,
+    model: String,
+    /// Ollama usa 2048-4096 tokens si no se le pide otra cosa; se envía el contexto del modelo.
+    num_ctx: OnceLock<Option<usize>>,
+}
+
+impl OllamaBackend {
+    fn model_context(&self) -> Option<usize> {
+        let endpoint = format!("{}/api/show", self.url.trim_end_matches('/'));
+        let info: Value = agent().post(&endpoint).send_json(json!({ "model": self.model })).ok()?.into_json().ok()?;
+        let arch = info["model_info"]["general.architecture"].as_str()?;
+        let context = info["model_info"][format!("{}.context_length", arch)].as_u64()?;
+        Some((context as usize).min(MAX_CONTEXT))
+    }
+}
+
+impl LlmBackend for OllamaBackend {
+    fn complete(
+        &self,
+        emit: &dyn Fn(&str, &str, Option<u32>),
+        prompt: &ChatPrompt,
+        max_tokens: u32,
+        stream: bool,
+    ) -> Result<String, String> {
+        let endpoint = format!("{}/api/chat", self.url.trim_end_matches('/'));
+        let mut body = json!({
+            "model": self.model,
+            "messages": [
+                { "role": "system", "content": prompt.system },
+                { "role": "user", "content": prompt.user },
+            ],
+            "stream": stream,
+            "options": {
+                "num_predict": max_tokens,
+                "temperature": TEMPERATURE,
+                "repeat_penalty": 1.1,
+                "seed": SEED,
+            },
+        });
+        // Sin contexto conocido se deja el de Ollama; `null` no es un valor válido
+        if let Some(num_ctx) = self.context_length() {
+            body["options"]["num_ctx"] = json!(num_ctx);
+        }
+        let response = agent().post(&endpoint).send_json(body).map_err(http_error)?;
+
+        // Con stream=false Ollama devuelve un único objeto; con stream=true, un JSON por línea
+        let mut output = String::new();
+        let mut progress = StreamProgress::new(max_tokens);
+        for line in BufReader::new(response.into_reader()).lines() {
+            let line = line.map_err(|e| format!("Error leyendo salida de Ollama: {}", e))?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let chunk: Value = serde_json::from_str(&line)
+                .map_err(|e| format!("respuesta inválida de Ollama: {}", e))?;
+            if let Some(error) = chunk["error"].as_str() {
+                return Err(format!("Ollama: {}", error));
+            }
+            if let Some(piece) = chunk["message"]["content"].as_str() {
+                progress.push(emit, &mut output, piece, stream);
+            }
+            if chunk["done"].as_bool() == Some(true) {
+                break;
+            }
+        }
+        super::clean_llm_output(&output)
+    }
+
+    fn context_length(&self) -> Option<usize> {
+        *self.num_ctx.get_or_init(|| self.model_context())
+    }
+}
@@ -0,0 +1,308 @@
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use super::gguf::GgufMetadata;
+
+const HF_BASE_URL: &str = "https://huggingface.co";
+
+/// Repositorios de los modelos que la app ofrece por nombre de archivo.
+const KNOWN_REPOS: &[(&str, &str)] = &[
+    ("phi-4", "microsoft/phi-4-gguf"),
+    ("Phi", "bartowski/Phi-3.5-mini-instruct-GGUF"),
+    ("Llama-3.2-3B", "bartowski/Llama-3.2-3B-Instruct-GGUF"),
+    ("Meta-Llama-3.1-8B", "bartowski/Meta-Llama-3.1-8B-Instruct-GGUF"),
+    ("gemma-2-9b-it", "bartowski/gemma-2-9b-it-GGUF"),
+    ("google_gemma-4-E2B-it", "bartowski/google_gemma-4-E2B-it-GGUF"),
+    ("Qwen2.5-14B", "bartowski/Qwen2.5-14B-Instruct-GGUF"),
+    ("Qwen_Qwen3.5-4B", "bartowski/Qwen_Qwen3.5-4B-GGUF"),
+    ("Ministral-8B", "bartowski/Ministral-8B-Instruct-2410-GGUF"),
+    ("Qwen2.5-3B", "Qwen/Qwen2.5-3B-Instruct-GGUF"),
+    ("bge-m3", "gpustack/bge-m3-GGUF"),
+];
+
+/// Dónde vive un modelo LLM.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ModelSpec {
+    /// GGUF en disco (ruta directa o importado); nunca se copia ni se borra.
+    Local(PathBuf),
+    /// `owner/repo` + ruta del archivo dentro del repo.
+    HuggingFace { repo: String, file: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GgufFile {
+    pub file: String,
+    pub size: u64,
+    /// Etiqueta de cuantización deducida del nombre (Q4_K_M, IQ3_XS, F16...).
+    pub quantization: Option<String>,
+    /// Número de partes si el modelo está dividido (`-00001-of-00003.gguf`).
+    pub parts: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LocalModel {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+    /// true si se registró desde otra carpeta en vez de descargarse.
+    pub imported: bool,
+}
+
+/// Acepta, en este orden: ruta a un `.gguf`, nombre de un modelo importado,
+/// `owner/repo/archivo.gguf` (o `owner/repo:archivo.gguf`) y nombres de archivo conocidos.
+pub fn resolve(model_name: &str) -> Result<ModelSpec, String> {
+    let name = model_name.trim();
+    let path = Path::new(name);
+    if path.is_absolute() || path.exists() {
+        return Ok(ModelSpec::Local(path.to_path_buf()));
+    }
+    if let Some(imported) = load_imports().into_iter().find(|m| m.name == name) {
+        return Ok(ModelSpec::Local(PathBuf::from(imported.path)));
+    }
+
+    if let Some((repo, file)) = name.split_once(':') {
+        return hugging_face(repo, file);
+    }
+    let parts: Vec<&str> = name.splitn(3, '/').collect();
+    if parts.len() == 3 {
+        return hugging_face(&format!("{}/{}", parts[0], parts[1]), parts[2]);
+    }
+
+    let (_, repo) = KNOWN_REPOS
+        .iter()
+        .find(|(pattern, _)| name.contains(pattern))
+        .ok_or_else(|| format!("No se conoce el repositorio de '{}'; usa owner/repo/{}", name, name))?;
+    hugging_face(repo, name)
+}
+
+/// `repo` debe ser `owner/repo` y `file` una ruta relativa sin `..`: el modelo se guarda
+/// con esas mismas piezas bajo `llm_models/` y no puede terminar fuera.
+fn hugging_face(repo: &str, file: &str) -> Result<ModelSpec, String> {
+    let dir = models_dir();
+    let valid = repo.split('/').count() == 2
+        && crate::model_pack::safe_join(&dir, Path::new(repo)).is_some()
+        && crate::model_pack::safe_join(&dir, Path::new(file)).is_some();
+    if !valid {
+        return Err(format!("Modelo inválido: '{}:{}' (usa owner/repo/archivo.gguf)", repo, file));
+    }
+    Ok(ModelSpec::HuggingFace { repo: repo.to_string(), file: file.to_string() })
+}
+
+fn models_dir() -> PathBuf {
+    crate::utils::models_base_dir().join("llm_models")
+}
+
+/// Ruta local del modelo. Los de repos conocidos se guardan planos en `llm_models/`
+/// (como siempre); el resto, bajo `llm_models/<owner>--<repo>/`.
+pub fn local_path(spec: &ModelSpec) -> PathBuf {
+    match spec {
+        ModelSpec::Local(path) => path.clone(),
+        ModelSpec::HuggingFace { repo, file } => {
+            if KNOWN_REPOS.iter().any(|(_, known)| known == repo) && !file.contains('/') {
+                models_dir().join(file)
+            } else {
+                models_dir().join(repo.replace('/', "--")).join(file)
+            }
+        }
+    }
+}
+
+/// Deja el modelo listo en disco y devuelve su ruta.
+pub fn ensure_model(
+    emit: &dyn Fn(&str, &str, Option<u32>),
+    model_name: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let spec = resolve(model_name)?;
+    let model_path = local_path(&spec);
+
+    let (repo, file) = match &spec {
+        ModelSpec::Local(path) => {
+            if !path.exists() {
+                return Err(format!("No existe el modelo {}", path.display()).into());
+            }
+            GgufMetadata::read(path)?;
+            return Ok(model_path);
+        }
+        ModelSpec::HuggingFace { repo, file } => (repo, file),
+    };
+    for part_file in split_parts(file) {
+        let part_path = model_path.with_file_name(Path::new(&part_file).file_name().unwrap_or_default());
+        if part_path.exists() {
+            continue;
+        }
+        let url = format!("{}/{}/resolve/main/{}", HF_BASE_URL, repo, part_file);
+        crate::download_manager::download(emit, &part_file, &url, &part_path)?;
+    }
+    Ok(model_path)
+}
+
+/// Para `x-00001-of-00003.gguf` devuelve las tres partes (llama.cpp carga el resto
+/// desde la primera si están en la misma carpeta); si no, sólo el archivo.
+fn split_parts(file: &str) -> Vec<String> {
+    let Some((prefix, total)) = parse_split(file) else {
+        return vec![file.to_string()];
+    };
+    (1..=total).map(|n| format!("{}-{:05}-of-{:05}.gguf", prefix, n, total)).collect()
+}
+
+fn parse_split(file: &str) -> Option<(&str, u32)> {
+    let stem = file.strip_suffix(".gguf")?;
+    let (rest, total) = stem.rsplit_once("-of-")?;
+    let (prefix, _part) = rest.rsplit_once('-')?;
+    Some((prefix, total.parse().ok()?))
+}
+
+// ─── Repository listing ──────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+struct HfTreeEntry {
+    #[serde(rename = "type")]
+    kind: String,
+    path: String,
+    #[serde(default)]
+    size: u64,
+}
+
+/// Archivos GGUF de un repo de Hugging Face, agrupando los modelos divididos en partes.
+pub fn list_quantizations(repo: &str) -> Result<Vec<GgufFile>, String> {
+    let repo = repo.trim().trim_matches('/');
+    if repo.split('/').count() != 2 {
+        return Err(format!("Repositorio inválido: '{}' (formato owner/repo)", repo));
+    }
+    let url = format!("{}/api/models/{}/tree/main?recursive=true", HF_BASE_URL, repo);
+    let body = crate::network::get_string(&url)
+        .map_err(|e| format!("No se pudo consultar {}: {}", repo, e))?;
+    let entries: Vec<HfTreeEntry> = serde_json::from_str(&body)
+        .map_err(|e| format!("Respuesta inválida de Hugging Face: {}", e))?;
+
+    let mut files: Vec<GgufFile> = Vec::new();
+    for entry in entries.iter().filter(|e| e.kind == "file" && e.path.ends_with(".gguf")) {
+        match parse_split(&entry.path) {
+            Some((prefix, total)) => {
+                let first = format!("{}-{:05}-of-{:05}.gguf", prefix, 1, total);
+                match files.iter_mut().find(|f| f.file == first) {
+                    Some(existing) => existing.size += entry.size,
+                    None => files.push(GgufFile {
+                        quantization: quantization_label(&entry.path),
+                        file: first,
+                        size: entry.size,
+                        parts: total,
+                    }),
+                }
+            }
+            None => files.push(GgufFile {
+                file: entry.path.clone(),
+                size: entry.size,
+                quantization: quantization_label(&entry.path),
+                parts: 1,
+            }),
+        }
+    }
+    files.sort_by_key(|f| f.size);
+    Ok(files)
+}
+
+/// Busca en el nombre un bloque tipo `Q4_K_M`, `IQ2_XXS`, `Q8_0`, `F16` o `BF16`.
+fn quantization_label(path: &str) -> Option<String> {
+    let name = Path::new(path).file_stem()?.to_str()?;
+    name.split(['-', '.'])
+        .rev()
+        .find(|part| {
+            let upper = part.to_ascii_uppercase();
+            let is_quant = (upper.starts_with('Q') || upper.starts_with("IQ"))
+                && upper.trim_start_matches('I').chars().nth(1).is_some_and(|c| c.is_ascii_digit());
+            is_quant || matches!(upper.as_str(), "F16" | "BF16" | "F32")
+        })
+        .map(|part| part.to_ascii_uppercase())
+}
+
+// ─── Local models ────────────────────────────────────────────────────────────
+
+fn imports_path() -> PathBuf {
+    models_dir().join("imported.json")
+}
+
+fn load_imports() -> Vec<LocalModel> {
+    std::fs::read_to_string(imports_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_imports(models: &[LocalModel]) -> Result<(), String> {
+    std::fs::create_dir_all(models_dir()).map_err(|e| e.to_string())?;
+    let json = serde_json::to_string_pretty(models).map_err(|e| e.to_string())?;
+    std::fs::write(imports_path(), json).map_err(|e| format!("No se pudo guardar el registro de modelos: {}", e))
+}
+
+/// Registra un GGUF que ya está en disco. Se valida la cabecera y se guarda sólo la ruta.
+pub fn import_model(path: &str, name: Option<&str>) -> Result<LocalModel, String> {
+    let path = Path::new(path.trim());
+    let metadata = GgufMetadata::read(path)?;
+    let name = name
+        .map(str::trim)
+        .filter(|n| !n.is_empty())
+        .map(str::to_string)
+        .or_else(|| path.file_name().and_then(|n| n.to_str()).map(str::to_string))
+        .ok_or("Nombre de modelo inválido")?;
+    log::info!("import model={} arch={:?}", name, metadata.architecture());
+
+    let model = LocalModel {
+        name: name.clone(),
+        path: path.to_string_lossy().into_owned(),
+        size: std::fs::metadata(path).map(|m| m.len()).unwrap_or_default(),
+        imported: true,
+    };
+    let mut imports = load_imports();
+    imports.retain(|m| m.name != name);
+    imports.push(model.clone());
+    save_imports(&imports)?;
+    Ok(model)
+}
+
+/// Quita un modelo importado del registro; el archivo original no se toca.
+pub fn forget_model(name: &str) -> Result<(), String> {
+    let mut imports = load_imports();
+    let before = imports.len();
+    imports.retain(|m| m.name != name);
+    if imports.len() == before {
+        return Err(format!("No hay ningún modelo importado llamado '{}'", name));
+    }
+    save_imports(&imports)
+}
+
+/// Modelos descargados en `llm_models/` más los importados.
+pub fn list_models() -> Vec<LocalModel> {
+    let mut models = Vec::new();
+    collect_downloaded(&models_dir(), &mut models);
+    models.sort_by(|a, b| a.name.cmp(&b.name));
+    models.extend(load_imports());
+    models
+}
+
+fn collect_downloaded(dir: &Path, models: &mut Vec<LocalModel>) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            collect_downloaded(&path, models);
+            continue;
+        }
+        let Some(file) = path.file_name().and_then(|n| n.to_str()) else { continue };
+        // Las partes 2..N de un modelo dividido no se cargan por separado
+        let is_extra_part = parse_split(file).is_some() && !file.contains("-00001-of-");
+        if !file.ends_with(".gguf") || is_extra_part {
+            continue;
+        }
+        // Nombre con el que `resolve` lo vuelve a encontrar
+        let name = match path.parent().and_then(|p| p.file_name()).and_then(|n| n.to_str()) {
+            Some(repo_dir) if repo_dir.contains("--") => format!("{}/{}", repo_dir.replacen("--", "/", 1), file),
+            _ => file.to_string(),
+        };
+        models.push(LocalModel {
+            name,
+            path: path.to_string_lossy().into_owned(),
+            size: std::fs::metadata(&path).map(|m| m.len()).unwrap_or_default(),
+            imported: false,
+        });
+    }
+}
@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    /// Resumen en prosa continua (comportamiento original).
+    #[default]
+    Prose,
+    ExecutiveSummary,
+    BulletPoints,
+    ActionItems,
+    MeetingMinutes,
+    ChapterOutline,
+    Qa,
+}
+
+impl OutputMode {
+    pub fn parse(value: Option<&str>) -> Result<OutputMode, String> {
+        let Some(value) = value.map(str::trim).filter(|v| !v.is_empty()) else {
+            return Ok(OutputMode::default());
+        };
+        serde_json::from_value(Value::String(value.to_string()))
+            .map_err(|_| format!("Modo de salida desconocido: {}", value))
+    }
+
+    /// Los modos estructurados piden JSON al modelo y devuelven `structured`.
+    pub fn is_structured(&self) -> bool {
+        !matches!(self, OutputMode::Prose | OutputMode::ExecutiveSummary)
+    }
+
+    pub const ALL: [OutputMode; 7] = [
+        OutputMode::Prose,
+        OutputMode::ExecutiveSummary,
+        OutputMode::BulletPoints,
+        OutputMode::ActionItems,
+        OutputMode::MeetingMinutes,
+        OutputMode::ChapterOutline,
+        OutputMode::Qa,
+    ];
+
+    /// Identificador snake_case, igual al usado por serde.
+    pub fn id(&self) -> &'static str {
+        match self {
+            OutputMode::Prose => "prose",
+            OutputMode::ExecutiveSummary => "executive_summary",
+            OutputMode::BulletPoints => "bullet_points",
+            OutputMode::ActionItems => "action_items",
+            OutputMode::MeetingMinutes => "meeting_minutes",
+            OutputMode::ChapterOutline => "chapter_outline",
+            OutputMode::Qa => "qa",
+        }
+    }
+
+    /// Valida la respuesta JSON del modelo contra el esquema del modo y la
+    /// devuelve junto a una versión en texto plano para mostrar o copiar.
+    pub fn parse_structured(&self, output: &str) -> Option<(Value, String)> {
+        let json = extract_json(output)?;
+        match self {
+            OutputMode::Prose | OutputMode::ExecutiveSummary => None,
+            OutputMode::BulletPoints => render::<BulletPoints>(json),
+            OutputMode::ActionItems => render::<ActionItems>(json),
+            OutputMode::MeetingMinutes => render::<MeetingMinutes>(json),
+            OutputMode::ChapterOutline => render::<ChapterOutline>(json),
+            OutputMode::Qa => render::<QaList>(json),
+        }
+    }
+}
+
+/// Recorta texto alrededor del JSON: los modelos pequeños a veces añaden
+/// frases o bloques ```json aunque se les pida lo contrario.
+pub fn extract_json(output: &str) -> Option<&str> {
+    let start = output.find(['{', '['])?;
+    let end = output.rfind(['}', ']'])?;
+    (end > start).then(|| &output[start..=end])
+}
+
+trait PlainText {
+    fn to_plain_text(&self) -> String;
+}
+
+fn render<T>(json: &str) -> Option<(Value, String)>
+where
+    T: for<'de> Deserialize<'de> + Serialize + PlainText,
+{
+    let parsed: T = serde_json::from_str(json)
+        .map_err(|e| log::warn!("JSON estructurado inválido: {}", e))
+        .ok()?;
+    let text = parsed.to_plain_text();
+    Some((serde_json::to_value(&parsed).ok()?, text))
+}
+
+#[derive(Deserialize, Serialize)]
+struct BulletPoints {
+    #[serde(default)]
+    points: Vec<String>,
+}
+
+impl PlainText for BulletPoints {
+    fn to_plain_text(&self) -> String {
+        self.points.iter().map(|p| format!("• {}", p)).collect::<Vec<_>>().join("\n")
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ActionItem {
+    pub task: String,
+    #[serde(default)]
+    pub owner: Option<String>,
+    #[serde(default)]
+    pub deadline: Option<String>,
+}
+
+impl PlainText for ActionItem {
+    fn to_plain_text(&self) -> String {
+        let mut line = format!("• {}", self.task);
+        let details: Vec<String> = [("Responsable", &self.owner), ("Fecha límite", &self.deadline)]
+            .into_iter()
+            .filter_map(|(label, v)| v.as_deref().filter(|v| !v.is_empty()).map(|v| format!("{}: {}", label, v)))
+            .collect();
+        if !details.is_empty() {
+            line.push_str(&format!(" ({})", details.join(", ")));
+        }
+        line
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct ActionItems {
+    #[serde(default)]
+    action_items: Vec<ActionItem>,
+}
+
+impl PlainText for ActionItems {
+    fn to_plain_text(&self) -> String {
+        self.action_items.iter().map(|a| a.to_plain_text()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct MinutesTopic {
+    topic: String,
+    #[serde(default)]
+    summary: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct MeetingMinutes {
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    date: Option<String>,
+    #[serde(default)]
+    attendees: Vec<String>,
+    #[serde(default)]
+    topics: Vec<MinutesTopic>,
+    #[serde(default)]
+    decisions: Vec<String>,
+    #[serde(default)]
+    action_items: Vec<ActionItem>,
+}
+
+impl PlainText for MeetingMinutes {
+    fn to_plain_text(&self) -> String {
+        let mut sections: Vec<String> = Vec::new();
+        if let Some(title) = self.title.as_deref().filter(|t| !t.is_empty()) {
+            sections.push(title.to_string());
+        }
+        if let Some(date) = self.date.as_deref().filter(|d| !d.is_empty()) {
+            sections.push(format!("Fecha: {}", date));
+        }
+        if !self.attendees.is_empty() {
+            sections.push(format!("Asistentes: {}", self.attendees.join(", ")));
+        }
+        if !self.topics.is_empty() {
+            let topics = self.topics.iter()
+                .map(|t| format!("• {}: {}", t.topic, t.summary))
+                .collect::<Vec<_>>().join("\n");
+            sections.push(format!("Temas tratados:\n{}", topics));
+        }
+        if !self.decisions.is_empty() {
+            let decisions = self.decisions.iter().map(|d| format!("• {}", d)).collect::<Vec<_>>().join("\n");
+            sections.push(format!("Decisiones:\n{}", decisions));
+        }
+        if !self.action_items.is_empty() {
+            let items = self.action_items.iter().map(|a| a.to_plain_text()).collect::<Vec<_>>().join("\n");
+            sections.push(format!("Tareas:\n{}", items));
+        }
+        sections.join("\n\n")
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct OutlineChapter {
+    title: String,
+    #[serde(default)]
+    summary: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct ChapterOutline {
+    #[serde(default)]
+    chapters: Vec<OutlineChapter>,
+}
+
+impl PlainText for ChapterOutline {
+    fn to_plain_text(&self) -> String {
+        self.chapters.iter().enumerate()
+            .map(|(i, c)| format!("{}. {} — {}", i + 1, c.title, c.summary))
+            .collect::<Vec<_>>().join("\n")
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+struct QaPair {
+    question: String,
+    #[serde(default)]
+    answer: String,
+}
+
+#[derive(Deserialize, Serialize)]
+struct QaList {
+    #[serde(default)]
+    qa: Vec<QaPair>,
+}
+
+impl PlainText for QaList {
+    fn to_plain_text(&self) -> String {
+        self.qa.iter()
+            .map(|q| format!("P: {}\nR: {}", q.question, q.answer))
+            .collect::<Vec<_>>().join("\n\n")
+    }
+}
@@ -0,0 +1,270 @@
+use std::io::Write;
+use std::process::Stdio;
+use serde::{Deserialize, Serialize};
+
+use crate::audio_processor::{merge_segments, TranscriptSegment};
+use super::{ensure_llama_bin, models, EmitType};
+
+/// Multilingüe (incluye español), 1024 dimensiones, ~440 MB en Q4_K_M.
+pub const DEFAULT_EMBEDDING_MODEL: &str = "bge-m3-Q4_K_M.gguf";
+
+/// Bloques que se indexan: más largos que un segmento de whisper, pero cortos para
+/// que cada resultado apunte a un momento concreto del audio.
+const PASSAGE_MAX_MS: u64 = 30_000;
+const PASSAGE_MAX_CHARS: usize = 400;
+/// Textos por invocación de llama-embedding; el modelo se carga una vez por lote.
+const EMBED_BATCH: usize = 64;
+const EMBD_SEPARATOR: &str = "<#beautiful_stt#>";
+const DEFAULT_LIMIT: usize = 20;
+
+/// Metadatos de una transcripción indexada. Los vectores van aparte en `<hash>.vec`
+/// (f32 little-endian, `dim` por entrada) para no inflar el JSON.
+#[derive(Deserialize, Serialize)]
+struct IndexedSource {
+    source: String,
+    model: String,
+    dim: usize,
+    indexed_at: u64,
+    entries: Vec<TranscriptSegment>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    /// Ruta del audio (o identificador) usado al indexar.
+    pub source: String,
+    pub from_ms: u64,
+    pub to_ms: u64,
+    pub text: String,
+    /// Similitud coseno, de -1 a 1.
+    pub score: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexStats {
+    pub source: String,
+    pub passages: usize,
+    pub model: String,
+}
+
+/// Calcula embeddings de los segmentos y reemplaza la entrada previa de `source`.
+pub fn index_transcript(
+    emit: EmitType,
+    source: &str,
+    segments: &[TranscriptSegment],
+    embedding_model: Option<&str>,
+) -> Result<IndexStats, String> {
+    let model_name = embedding_model.unwrap_or(DEFAULT_EMBEDDING_MODEL);
+    let entries = merge_segments(segments, PASSAGE_MAX_MS, PASSAGE_MAX_CHARS);
+    if entries.is_empty() {
+        return Err("La transcripción no tiene segmentos para indexar".into());
+    }
+
+    let embedder = Embedder::prepare(&*emit, model_name)?;
+    let total_batches = entries.len().div_ceil(EMBED_BATCH);
+    let mut vectors: Vec<Vec<f32>> = Vec::with_capacity(entries.len());
+    for (idx, batch) in entries.chunks(EMBED_BATCH).enumerate() {
+        emit(
+            "summary_progress",
+            &format!("Indexando transcripción: lote {}/{}", idx + 1, total_batches),
+            Some((idx as f32 / total_batches as f32 * 100.0) as u32),
+        );
+        let texts: Vec<&str> = batch.iter().map(|e| e.text.as_str()).collect();
+        vectors.extend(embedder.embed(&texts)?);
+    }
+
+    let dim = vectors.first().map(|v| v.len()).unwrap_or_default();
+    if dim == 0 || vectors.len() != entries.len() || vectors.iter().any(|v| v.len() != dim) {
+        return Err(format!(
+            "llama-embedding devolvió {} vectores para {} textos",
+            vectors.len(),
+            entries.len()
+        ));
+    }
+
+    let indexed = IndexedSource {
+        source: source.to_string(),
+        model: model_name.to_string(),
+        dim,
+        indexed_at: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default(),
+        entries,
+    };
+    write_source(&indexed, &vectors)?;
+    log::info!("indexed source={} passages={} dim={}", source, indexed.entries.len(), dim);
+
+    emit("summary_progress", "Indexación completada", Some(100));
+    Ok(IndexStats { source: source.to_string(), passages: indexed.entries.len(), model: model_name.to_string() })
+}
+
+/// Busca en todas las transcripciones indexadas con el mismo modelo de embeddings.
+pub fn semantic_search(
+    emit: EmitType,
+    query: &str,
+    limit: Option<usize>,
+    embedding_model: Option<&str>,
+) -> Result<Vec<SearchHit>, String> {
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let model_name = embedding_model.unwrap_or(DEFAULT_EMBEDDING_MODEL);
+    let embedder = Embedder::prepare(&*emit, model_name)?;
+    let query_vec = embedder
+        .embed(&[query])?
+        .pop()
+        .ok_or("llama-embedding no devolvió el vector de la consulta")?;
+
+    let mut hits: Vec<SearchHit> = Vec::new();
+    for (indexed, vectors) in read_sources() {
+        if indexed.model != model_name || indexed.dim != query_vec.len() {
+            log::debug!("search skip source={} model={}", indexed.source, indexed.model);
+            continue;
+        }
+        for (entry, vector) in indexed.entries.iter().zip(vectors.chunks(indexed.dim)) {
+            hits.push(SearchHit {
+                source: indexed.source.clone(),
+                from_ms: entry.from_ms,
+                to_ms: entry.to_ms,
+                text: entry.text.clone(),
+                score: cosine(&query_vec, vector),
+            });
+        }
+    }
+
+    hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+    hits.truncate(limit.unwrap_or(DEFAULT_LIMIT));
+    Ok(hits)
+}
+
+pub fn remove_from_index(source: &str) -> Result<(), String> {
+    let (meta_path, vec_path) = source_paths(source);
+    for path in [meta_path, vec_path] {
+        if path.exists() {
+            std::fs::remove_file(&path).map_err(|e| format!("No se pudo borrar {}: {}", path.display(), e))?;
+        }
+    }
+    Ok(())
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = a.iter().map(|x| x * x).sum::<f32>().sqrt() * b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm > 0.0 { dot / norm } else { 0.0 }
+}
+
+// ─── Vector store ────────────────────────────────────────────────────────────
+
+fn index_dir() -> std::path::PathBuf {
+    crate::utils::models_base_dir().join("search_index")
+}
+
+fn source_paths(source: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+    let stem = format!("{:016x}", crate::utils::stable_hash(source));
+    let dir = index_dir();
+    (dir.join(format!("{}.json", stem)), dir.join(format!("{}.vec", stem)))
+}
+
+fn write_source(indexed: &IndexedSource, vectors: &[Vec<f32>]) -> Result<(), String> {
+    std::fs::create_dir_all(index_dir()).map_err(|e| format!("No se pudo crear el índice: {}", e))?;
+    let (meta_path, vec_path) = source_paths(&indexed.source);
+
+    let bytes: Vec<u8> = vectors.iter().flatten().flat_map(|v| v.to_le_bytes()).collect();
+    std::fs::write(&vec_path, bytes).map_err(|e| format!("No se pudo guardar el índice: {}", e))?;
+    let json = serde_json::to_string(indexed).map_err(|e| e.to_string())?;
+    std::fs::write(&meta_path, json).map_err(|e| format!("No se pudo guardar el índice: {}", e))
+}
+
+/// Entradas incompletas o corruptas se ignoran; se regeneran al volver a indexar.
+fn read_sources() -> Vec<(IndexedSource, Vec<f32>)> {
+    let Ok(entries) = std::fs::read_dir(index_dir()) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("json"))
+        .filter_map(|meta_path| {
+            let indexed: IndexedSource = serde_json::from_str(&std::fs::read_to_string(&meta_path).ok()?).ok()?;
+            let bytes = std::fs::read(meta_path.with_extension("vec")).ok()?;
+            let vectors: Vec<f32> = bytes
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect();
+            (indexed.dim > 0 && vectors.len() == indexed.dim * indexed.entries.len()).then_some((indexed, vectors))
+        })
+        .collect()
+}
+
+// ─── Embeddings ──────────────────────────────────────────────────────────────
+
+/// `llama-embedding` viene en el mismo paquete de llama.cpp que llama-completion.
+struct Embedder {
+    bin_path: std::path::PathBuf,
+    model_path: std::path::PathBuf,
+    workspace: crate::workspace::Workspace,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingOutput {
+    data: Vec<EmbeddingItem>,
+}
+#[derive(Deserialize)]
+struct EmbeddingItem {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl Embedder {
+    fn prepare(emit: &dyn Fn(&str, &str, Option<u32>), model_name: &str) -> Result<Self, String> {
+        emit("summary_progress", &format!("Preparando modelo de embeddings {}", model_name), None);
+        let model_path = models::ensure_model(emit, model_name)
+            .map_err(|e| format!("Error preparando modelo: {}", e))?;
+        let completion_bin = ensure_llama_bin(emit)
+            .map_err(|e| format!("Error preparando llama.cpp: {}", e))?;
+        let bin_name = if cfg!(target_os = "windows") { "llama-embedding.exe" } else { "llama-embedding" };
+        let bin_path = completion_bin.with_file_name(bin_name);
+        if !bin_path.exists() {
+            return Err(format!("No se encontró {} junto a llama-completion", bin_name));
+        }
+        Ok(Embedder { bin_path, model_path, workspace: crate::workspace::Workspace::create("embed")? })
+    }
+
+    fn embed(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>, String> {
+        let input_file = self.workspace.file("input.txt");
+        // El separador por defecto es "\n"; los textos pueden traer saltos de línea
+        let input = texts.iter().map(|t| t.replace(EMBD_SEPARATOR, " ")).collect::<Vec<_>>().join(EMBD_SEPARATOR);
+        std::fs::File::create(&input_file)
+            .and_then(|mut f| f.write_all(input.as_bytes()))
+            .map_err(|e| format!("Error escribiendo textos a indexar: {}", e))?;
+
+        let output = std::process::Command::new(&self.bin_path)
+            .arg("-m").arg(&self.model_path)
+            .arg("-f").arg(&input_file)
+            .arg("-ngl").arg(crate::hardware::gpu_layers_for(&self.model_path).to_string())
+            .arg("-c").arg("8192")
+            .arg("-b").arg("8192")
+            .arg("-ub").arg("8192")
+            .arg("--pooling").arg("mean")
+            .arg("--embd-normalize").arg("2")
+            .arg("--embd-separator").arg(EMBD_SEPARATOR)
+            .arg("--embd-output-format").arg("json")
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output();
+        let output = output.map_err(|e| format!("Error ejecutando llama-embedding ({:?}): {}", self.bin_path, e))?;
+        crate::logging::log_output(crate::logging::LLAMA_CPP, &output.stderr);
+        if !output.status.success() {
+            return Err(format!("llama-embedding terminó con {}", output.status));
+        }
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let json = stdout.find('{').map(|start| &stdout[start..]).unwrap_or_default();
+        let mut parsed: EmbeddingOutput = serde_json::from_str(json.trim())
+            .map_err(|e| format!("Salida inválida de llama-embedding: {}", e))?;
+        parsed.data.sort_by_key(|item| item.index);
+        Ok(parsed.data.into_iter().map(|item| item.embedding).collect())
+    }
+}
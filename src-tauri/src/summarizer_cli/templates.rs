@@ -0,0 +1,355 @@
+use serde::{Deserialize, Serialize};
+
+use super::output_mode::OutputMode;
+
+const BASE_SYSTEM_PROMPT: &str = "Eres un experto en analizar transcripciones de audio. Responde siempre en {language}. \
+    Captura el tema central, los puntos más importantes, y cualquier dato relevante \
+    como nombres propios, cifras, fechas o lugares. \
+    Corrige implícitamente errores fonéticos de Whisper usando el contexto del texto.";
+
+const JSON_RULES: &str = "Responde únicamente con JSON válido, sin texto antes ni después, \
+    sin bloques de código. Usa null cuando un dato no se mencione. No inventes información.";
+
+/// Marcadores permitidos en cada campo. El primero de cada lista es obligatorio
+/// (salvo en `system`, donde todos son opcionales).
+const SYSTEM_PLACEHOLDERS: &[&str] = &["language"];
+const DIRECT_PLACEHOLDERS: &[&str] = &["transcript", "language"];
+const EXTRACTION_PLACEHOLDERS: &[&str] = &["transcript", "chunk_num", "total", "language"];
+const REDUCE_PLACEHOLDERS: &[&str] = &["notes", "language"];
+
+/// Nombre del idioma para `{language}` a partir del código de whisper (`es`, `en`...).
+/// Con `auto` o un código desconocido se pide responder en el idioma de la transcripción.
+pub fn language_name(code: &str) -> String {
+    let name = match code.trim() {
+        "es" => "español",
+        "en" => "inglés",
+        "pt" => "portugués",
+        "fr" => "francés",
+        "de" => "alemán",
+        "it" => "italiano",
+        "ca" => "catalán",
+        "gl" => "gallego",
+        "eu" => "euskera",
+        "nl" => "neerlandés",
+        _ => "el mismo idioma de la transcripción",
+    };
+    name.to_string()
+}
+
+fn default_max_tokens() -> u32 {
+    550
+}
+
+/// Plantilla de resumen. Las integradas salen de cada `OutputMode`; las del usuario
+/// viven como `<id>.json` en `templates/` dentro del directorio de datos.
+///
+/// Marcadores: `{transcript}` (texto completo en `direct`, sección en `extraction`),
+/// `{chunk_num}` y `{total}` en `extraction`, `{notes}` en `reduce`, `{language}` en cualquiera.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SummaryTemplate {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    /// Determina el esquema JSON esperado en la salida.
+    #[serde(default)]
+    pub mode: OutputMode,
+    pub system: String,
+    pub direct: String,
+    pub extraction: String,
+    pub reduce: String,
+    #[serde(default = "default_max_tokens")]
+    pub max_tokens: u32,
+    #[serde(default, skip_deserializing)]
+    pub builtin: bool,
+}
+
+struct BuiltinPrompts {
+    system: String,
+    direct: &'static str,
+    extraction: &'static str,
+    reduce: &'static str,
+    max_tokens: u32,
+}
+
+fn builtin_name(mode: OutputMode) -> &'static str {
+    match mode {
+        OutputMode::Prose => "Resumen en prosa",
+        OutputMode::ExecutiveSummary => "Resumen ejecutivo",
+        OutputMode::BulletPoints => "Viñetas",
+        OutputMode::ActionItems => "Tareas y responsables",
+        OutputMode::MeetingMinutes => "Acta de reunión",
+        OutputMode::ChapterOutline => "Índice por capítulos",
+        OutputMode::Qa => "Preguntas y respuestas",
+    }
+}
+
+fn builtin_prompts(mode: OutputMode) -> BuiltinPrompts {
+    match mode {
+        OutputMode::Prose => BuiltinPrompts {
+            system: format!(
+                "{} Escribe en {{language}} claro y natural, en prosa continua. \
+                Sin encabezados, sin viñetas, sin listas, sin markdown. Solo párrafos de texto.",
+                BASE_SYSTEM_PROMPT
+            ),
+            direct: "Transcripción:\n{transcript}\n\nEscribe un resumen claro y completo. \
+                Cubre el tema principal y todos los puntos importantes mencionados. \
+                Usa tantas oraciones como sea necesario para no omitir información relevante. Resumen:",
+            extraction: "Sección {chunk_num} de {total}. Extrae los puntos más importantes: ideas, \
+                personas, cifras, fechas y eventos relevantes.\n\n{transcript}\n\nPuntos clave:",
+            reduce: "Usando los puntos clave de cada sección, escribe un resumen cohesivo \
+                en 2-4 oraciones del audio completo:\n\n{notes}\n\nResumen:",
+            max_tokens: 550,
+        },
+        OutputMode::ExecutiveSummary => BuiltinPrompts {
+            system: format!(
+                "{} Escribe para alguien con poco tiempo: conciso, directo, sin relleno, en prosa y sin markdown.",
+                BASE_SYSTEM_PROMPT
+            ),
+            direct: "Transcripción:\n{transcript}\n\nEscribe un resumen ejecutivo de máximo 5 oraciones: \
+                propósito, conclusiones clave, decisiones tomadas y próximos pasos. Resumen ejecutivo:",
+            extraction: "Sección {chunk_num} de {total}. Extrae sólo conclusiones, decisiones, \
+                cifras clave y próximos pasos.\n\n{transcript}\n\nPuntos clave:",
+            reduce: "A partir de las notas de cada sección, escribe un resumen ejecutivo de máximo \
+                5 oraciones: propósito, conclusiones clave, decisiones y próximos pasos.\n\n{notes}\n\nResumen ejecutivo:",
+            max_tokens: 300,
+        },
+        OutputMode::BulletPoints => BuiltinPrompts {
+            system: format!("{} {}", BASE_SYSTEM_PROMPT, JSON_RULES),
+            direct: "Transcripción:\n{transcript}\n\nResume la transcripción en viñetas breves, \
+                una idea por viñeta, en orden de aparición. Formato:\n\
+                {\"points\": [\"...\"]}",
+            extraction: "Sección {chunk_num} de {total}. Extrae las ideas principales como \
+                frases cortas, una por línea.\n\n{transcript}\n\nIdeas:",
+            reduce: "Notas de cada sección:\n\n{notes}\n\nUne las notas en viñetas breves, sin repetir ideas, \
+                en orden de aparición. Formato:\n{\"points\": [\"...\"]}",
+            max_tokens: 500,
+        },
+        OutputMode::ActionItems => BuiltinPrompts {
+            system: format!("{} {}", BASE_SYSTEM_PROMPT, JSON_RULES),
+            direct: "Transcripción:\n{transcript}\n\nLista todas las tareas, compromisos y pendientes \
+                acordados, con su responsable y fecha límite si se mencionan. Formato:\n\
+                {\"action_items\": [{\"task\": \"...\", \"owner\": \"...\", \"deadline\": \"...\"}]}",
+            extraction: "Sección {chunk_num} de {total}. Enumera las tareas, compromisos y pendientes \
+                mencionados, indicando responsable y fecha límite si aparecen.\n\n{transcript}\n\nTareas:",
+            reduce: "Tareas detectadas en cada sección:\n\n{notes}\n\nUnifica la lista sin duplicados. Formato:\n\
+                {\"action_items\": [{\"task\": \"...\", \"owner\": \"...\", \"deadline\": \"...\"}]}",
+            max_tokens: 600,
+        },
+        OutputMode::MeetingMinutes => BuiltinPrompts {
+            system: format!("{} {}", BASE_SYSTEM_PROMPT, JSON_RULES),
+            direct: "Transcripción:\n{transcript}\n\nRedacta el acta de la reunión. Formato:\n\
+                {\"title\": \"...\", \"date\": \"...\", \"attendees\": [\"...\"], \
+                \"topics\": [{\"topic\": \"...\", \"summary\": \"...\"}], \"decisions\": [\"...\"], \
+                \"action_items\": [{\"task\": \"...\", \"owner\": \"...\", \"deadline\": \"...\"}]}",
+            extraction: "Sección {chunk_num} de {total}. Anota asistentes mencionados, temas tratados, \
+                decisiones y tareas con responsable y fecha.\n\n{transcript}\n\nNotas:",
+            reduce: "Notas de cada sección de la reunión:\n\n{notes}\n\nRedacta el acta completa. Formato:\n\
+                {\"title\": \"...\", \"date\": \"...\", \"attendees\": [\"...\"], \
+                \"topics\": [{\"topic\": \"...\", \"summary\": \"...\"}], \"decisions\": [\"...\"], \
+                \"action_items\": [{\"task\": \"...\", \"owner\": \"...\", \"deadline\": \"...\"}]}",
+            max_tokens: 900,
+        },
+        OutputMode::ChapterOutline => BuiltinPrompts {
+            system: format!("{} {}", BASE_SYSTEM_PROMPT, JSON_RULES),
+            direct: "Transcripción:\n{transcript}\n\nDivide el contenido en capítulos temáticos en orden. \
+                Cada capítulo con un título corto y un resumen de una oración. Formato:\n\
+                {\"chapters\": [{\"title\": \"...\", \"summary\": \"...\"}]}",
+            extraction: "Sección {chunk_num} de {total}. Indica los temas tratados en orden, \
+                cada uno con un título corto y una oración de resumen.\n\n{transcript}\n\nTemas:",
+            reduce: "Temas de cada sección, en orden:\n\n{notes}\n\nAgrúpalos en capítulos temáticos, \
+                fusionando temas contiguos. Formato:\n\
+                {\"chapters\": [{\"title\": \"...\", \"summary\": \"...\"}]}",
+            max_tokens: 600,
+        },
+        OutputMode::Qa => BuiltinPrompts {
+            system: format!("{} {}", BASE_SYSTEM_PROMPT, JSON_RULES),
+            direct: "Transcripción:\n{transcript}\n\nGenera las preguntas más relevantes que responde \
+                el audio, cada una con su respuesta según la transcripción. Formato:\n\
+                {\"qa\": [{\"question\": \"...\", \"answer\": \"...\"}]}",
+            extraction: "Sección {chunk_num} de {total}. Anota las preguntas que esta sección responde \
+                y sus respuestas.\n\n{transcript}\n\nPreguntas y respuestas:",
+            reduce: "Preguntas y respuestas de cada sección:\n\n{notes}\n\nUnifícalas sin duplicados, \
+                conservando las más relevantes. Formato:\n\
+                {\"qa\": [{\"question\": \"...\", \"answer\": \"...\"}]}",
+            max_tokens: 800,
+        },
+    }
+}
+
+pub fn builtin(mode: OutputMode) -> SummaryTemplate {
+    let prompts = builtin_prompts(mode);
+    SummaryTemplate {
+        id: mode.id().to_string(),
+        name: builtin_name(mode).to_string(),
+        description: String::new(),
+        mode,
+        system: prompts.system,
+        direct: prompts.direct.to_string(),
+        extraction: prompts.extraction.to_string(),
+        reduce: prompts.reduce.to_string(),
+        max_tokens: prompts.max_tokens,
+        builtin: true,
+    }
+}
+
+// ─── Storage ─────────────────────────────────────────────────────────────────
+
+fn templates_dir() -> std::path::PathBuf {
+    crate::utils::models_base_dir().join("templates")
+}
+
+fn template_path(id: &str) -> std::path::PathBuf {
+    templates_dir().join(format!("{}.json", id))
+}
+
+/// Integradas primero, luego las del usuario ordenadas por nombre.
+/// Un archivo inválido se omite en vez de romper el listado completo.
+pub fn list() -> Vec<SummaryTemplate> {
+    let mut templates: Vec<SummaryTemplate> = OutputMode::ALL.iter().map(|m| builtin(*m)).collect();
+    let mut user: Vec<SummaryTemplate> = std::fs::read_dir(templates_dir())
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().and_then(|e| e.to_str()) == Some("json"))
+                .filter_map(|p| match read_template_file(&p) {
+                    Ok(t) => Some(t),
+                    Err(e) => {
+                        log::warn!("plantilla ignorada {}: {}", p.display(), e);
+                        None
+                    }
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    user.sort_by_key(|t| t.name.to_lowercase());
+    templates.extend(user);
+    templates
+}
+
+pub fn load(id: &str) -> Result<SummaryTemplate, String> {
+    if let Some(mode) = OutputMode::ALL.iter().find(|m| m.id() == id) {
+        return Ok(builtin(*mode));
+    }
+    validate_id(id)?;
+    read_template_file(&template_path(id))
+}
+
+pub fn save(template: &SummaryTemplate) -> Result<SummaryTemplate, String> {
+    validate(template)?;
+    let mut template = template.clone();
+    template.builtin = false;
+    std::fs::create_dir_all(templates_dir())
+        .map_err(|e| format!("No se pudo crear el directorio de plantillas: {}", e))?;
+    let json = serde_json::to_string_pretty(&template).map_err(|e| e.to_string())?;
+    std::fs::write(template_path(&template.id), json)
+        .map_err(|e| format!("No se pudo guardar la plantilla: {}", e))?;
+    Ok(template)
+}
+
+pub fn delete(id: &str) -> Result<(), String> {
+    validate_id(id)?;
+    std::fs::remove_file(template_path(id)).map_err(|e| format!("No se pudo eliminar la plantilla: {}", e))
+}
+
+/// Importa una plantilla compartida (archivo JSON) copiándola al directorio de datos.
+pub fn import(path: &str) -> Result<SummaryTemplate, String> {
+    let template = read_template_file(std::path::Path::new(path))?;
+    save(&template)
+}
+
+fn read_template_file(path: &std::path::Path) -> Result<SummaryTemplate, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("No se pudo leer {}: {}", path.display(), e))?;
+    let template: SummaryTemplate = serde_json::from_str(&content)
+        .map_err(|e| format!("Plantilla inválida: {}", e))?;
+    validate(&template)?;
+    Ok(template)
+}
+
+// ─── Validation ──────────────────────────────────────────────────────────────
+
+fn validate_id(id: &str) -> Result<(), String> {
+    let valid = !id.is_empty()
+        && id.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+    if !valid {
+        return Err(format!("Identificador de plantilla inválido: '{}' (usa minúsculas, números, '-' o '_')", id));
+    }
+    Ok(())
+}
+
+pub fn validate(template: &SummaryTemplate) -> Result<(), String> {
+    validate_id(&template.id)?;
+    if OutputMode::ALL.iter().any(|m| m.id() == template.id) {
+        return Err(format!("'{}' es una plantilla integrada; usa otro identificador", template.id));
+    }
+
+    let mut errors: Vec<String> = Vec::new();
+    if template.name.trim().is_empty() {
+        errors.push("el nombre no puede estar vacío".into());
+    }
+    if template.system.trim().is_empty() {
+        errors.push("el prompt de sistema no puede estar vacío".into());
+    }
+    if template.max_tokens == 0 {
+        errors.push("max_tokens debe ser mayor que 0".into());
+    }
+    check_placeholders("system", &template.system, SYSTEM_PLACEHOLDERS, false, &mut errors);
+    check_placeholders("direct", &template.direct, DIRECT_PLACEHOLDERS, true, &mut errors);
+    check_placeholders("extraction", &template.extraction, EXTRACTION_PLACEHOLDERS, true, &mut errors);
+    check_placeholders("reduce", &template.reduce, REDUCE_PLACEHOLDERS, true, &mut errors);
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Plantilla '{}' inválida: {}", template.id, errors.join("; ")))
+    }
+}
+
+fn check_placeholders(field: &str, text: &str, allowed: &[&str], first_required: bool, errors: &mut Vec<String>) {
+    if first_required && !placeholders(text).any(|p| p == allowed[0]) {
+        errors.push(format!("'{}' debe contener {{{}}}", field, allowed[0]));
+    }
+    for unknown in placeholders(text).filter(|p| !allowed.contains(p)) {
+        errors.push(format!("'{}' usa un marcador desconocido {{{}}}", field, unknown));
+    }
+}
+
+/// Marcadores `{identificador}` presentes en el texto. Ignora llaves que no
+/// encierran un identificador, como los ejemplos de JSON en las plantillas.
+fn placeholders(text: &str) -> impl Iterator<Item = &str> {
+    text.match_indices('{').filter_map(move |(open, _)| {
+        let rest = &text[open + 1..];
+        let close = rest.find('}')?;
+        let name = &rest[..close];
+        let is_ident = !name.is_empty() && name.chars().all(|c| c.is_ascii_lowercase() || c == '_');
+        is_ident.then_some(name)
+    })
+}
+
+/// Sustituye `{clave}` por su valor en una sola pasada, para que un `{...}`
+/// dentro de la transcripción no se vuelva a interpretar como marcador.
+pub fn render_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let matched = values.iter().find(|(key, _)| {
+            after.starts_with(key) && after[key.len()..].starts_with('}')
+        });
+        match matched {
+            Some((key, value)) => {
+                out.push_str(value);
+                rest = &after[key.len() + 1..];
+            }
+            None => {
+                out.push('{');
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
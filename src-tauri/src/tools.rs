@@ -0,0 +1,410 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use serde::{Deserialize, Serialize};
+
+/// whisper-cli con `--vad` (v1.7.6): sin eso `process()` falla al pasar el modelo de VAD.
+const WHISPER_MIN_VERSION: &str = "1.7.6";
+const WHISPER_RELEASE: &str = "v1.7.6";
+/// yt-dlp deja de funcionar con YouTube a las pocas semanas de cada cambio del sitio.
+const YTDLP_MIN_VERSION: &str = "2025.06.01";
+const YTDLP_STALE_DAYS: u64 = 60;
+
+/// Un job de transcripción y una descarga pueden pedir la misma herramienta a la vez.
+static PROVISION_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Tool {
+    WhisperCli,
+    YtDlp,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolSource {
+    /// Copiado en `models_base_dir()/bin` por la app.
+    Managed,
+    /// Junto al ejecutable de la app.
+    Bundled,
+    /// Homebrew o PATH.
+    System,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolStatus {
+    pub tool: Tool,
+    pub path: Option<String>,
+    pub source: Option<ToolSource>,
+    pub version: Option<String>,
+    pub minimum_version: &'static str,
+    pub meets_minimum: bool,
+    /// yt-dlp con más de `YTDLP_STALE_DAYS` días: conviene actualizar aunque cumpla el mínimo.
+    pub stale: bool,
+    /// La app puede descargarlo en esta plataforma.
+    pub provisionable: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct ToolInstall {
+    version: String,
+    /// Ruta del binario relativa a la carpeta de la herramienta.
+    bin: String,
+}
+
+impl Tool {
+    fn bin_name(self) -> &'static str {
+        match (self, cfg!(target_os = "windows")) {
+            (Tool::WhisperCli, true) => "whisper-cli.exe",
+            (Tool::WhisperCli, false) => "whisper-cli",
+            (Tool::YtDlp, true) => "yt-dlp.exe",
+            (Tool::YtDlp, false) => "yt-dlp",
+        }
+    }
+
+    fn id(self) -> &'static str {
+        match self {
+            Tool::WhisperCli => "whisper.cpp",
+            Tool::YtDlp => "yt-dlp",
+        }
+    }
+
+    fn minimum_version(self) -> &'static str {
+        match self {
+            Tool::WhisperCli => WHISPER_MIN_VERSION,
+            Tool::YtDlp => YTDLP_MIN_VERSION,
+        }
+    }
+
+    fn install_hint(self) -> &'static str {
+        match self {
+            Tool::WhisperCli if cfg!(target_os = "macos") => "instálalo con `brew install whisper-cpp`",
+            Tool::WhisperCli => "compílalo desde github.com/ggml-org/whisper.cpp y déjalo en el PATH",
+            Tool::YtDlp => "instálalo desde github.com/yt-dlp/yt-dlp",
+        }
+    }
+}
+
+/// Devuelve un binario utilizable, descargándolo si falta o si es más viejo que el mínimo.
+pub fn ensure(emit: &dyn Fn(&str, &str, Option<u32>), tool: Tool) -> Result<PathBuf, String> {
+    let status = status(tool);
+    if let (Some(path), true) = (&status.path, status.meets_minimum) {
+        return Ok(PathBuf::from(path));
+    }
+    if status.provisionable {
+        match provision(emit, tool) {
+            Ok(path) => return Ok(path),
+            Err(e) if status.path.is_some() => {
+                log::warn!("{} provisioning failed, using {:?}: {}", tool.id(), status.path, e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    match status.path {
+        Some(path) => {
+            log::warn!(
+                "{} {:?} is older than {}, continuing anyway",
+                tool.id(),
+                status.version,
+                tool.minimum_version()
+            );
+            Ok(PathBuf::from(path))
+        }
+        None => Err(format!("No se encontró {}: {}", tool.bin_name(), tool.install_hint())),
+    }
+}
+
+pub fn status(tool: Tool) -> ToolStatus {
+    let located = locate(tool);
+    let version = located.as_ref().and_then(|(path, source)| detect_version(tool, path, *source));
+    let meets_minimum = match tool {
+        // Sin versión legible, basta con que acepte las opciones que usamos
+        Tool::WhisperCli => located.as_ref().is_some_and(|(path, _)| {
+            version
+                .as_deref()
+                .map(|v| version_at_least(v, WHISPER_MIN_VERSION))
+                .unwrap_or_else(|| whisper_supports_vad(path))
+        }),
+        Tool::YtDlp => version.as_deref().is_some_and(|v| version_at_least(v, YTDLP_MIN_VERSION)),
+    };
+    let stale = tool == Tool::YtDlp && version.as_deref().is_some_and(ytdlp_is_stale);
+
+    ToolStatus {
+        tool,
+        path: located.as_ref().map(|(p, _)| p.to_string_lossy().into_owned()),
+        source: located.map(|(_, source)| source),
+        version,
+        minimum_version: tool.minimum_version(),
+        meets_minimum,
+        stale,
+        provisionable: release_url(tool).is_some(),
+    }
+}
+
+pub fn status_all() -> Vec<ToolStatus> {
+    [Tool::WhisperCli, Tool::YtDlp].into_iter().map(status).collect()
+}
+
+/// Descarga la última versión de yt-dlp en `bin/yt-dlp`, que pasa a tener prioridad
+/// sobre la copia incluida en la app.
+pub fn update_ytdlp(emit: &dyn Fn(&str, &str, Option<u32>)) -> Result<ToolStatus, String> {
+    provision(emit, Tool::YtDlp)?;
+    Ok(status(Tool::YtDlp))
+}
+
+// ─── Lookup ──────────────────────────────────────────────────────────────────
+
+fn tool_dir(tool: Tool) -> PathBuf {
+    crate::utils::models_base_dir().join("bin").join(tool.id())
+}
+
+fn read_install(tool: Tool) -> Option<ToolInstall> {
+    let content = std::fs::read_to_string(tool_dir(tool).join("install.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Orden: copia gestionada (sólo existe si la app la descargó), binario incluido,
+/// Homebrew y PATH.
+fn locate(tool: Tool) -> Option<(PathBuf, ToolSource)> {
+    if let Some(install) = read_install(tool) {
+        let managed = tool_dir(tool).join(install.bin);
+        if managed.exists() {
+            return Some((managed, ToolSource::Managed));
+        }
+    }
+
+    let bin_name = tool.bin_name();
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|p| p.parent().map(|p| p.to_path_buf()))
+        .unwrap_or_default();
+
+    // En dev NO usamos el whisper-cli copiado por Tauri en target/debug/: sus rpaths
+    // apuntan a ../Frameworks/, que no existe en modo dev
+    let bundled_first = tool == Tool::YtDlp || cfg!(not(debug_assertions));
+    let next_to_exe = exe_dir.join(bin_name);
+    if bundled_first && next_to_exe.exists() {
+        return Some((next_to_exe, ToolSource::Bundled));
+    }
+
+    // macOS: homebrew (ARM y Intel)
+    #[cfg(target_os = "macos")]
+    for prefix in &["/opt/homebrew/bin", "/usr/local/bin"] {
+        let p = PathBuf::from(prefix).join(bin_name);
+        if p.exists() {
+            return Some((p, ToolSource::System));
+        }
+    }
+
+    if next_to_exe.exists() {
+        return Some((next_to_exe, ToolSource::Bundled));
+    }
+    std::env::var_os("PATH")
+        .map(|paths| std::env::split_paths(&paths).map(|dir| dir.join(bin_name)).collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .find(|p| p.is_file())
+        .map(|p| (p, ToolSource::System))
+}
+
+/// Binario que se usaría, sin ejecutarlo ni descargarlo.
+pub fn path(tool: Tool) -> Option<PathBuf> {
+    locate(tool).map(|(path, _)| path)
+}
+
+fn detect_version(tool: Tool, path: &Path, source: ToolSource) -> Option<String> {
+    if source == ToolSource::Managed {
+        if let Some(install) = read_install(tool) {
+            return Some(install.version);
+        }
+    }
+    match tool {
+        Tool::YtDlp => {
+            let output = Command::new(path).arg("--version").stdin(Stdio::null()).output().ok()?;
+            let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+            (output.status.success() && !version.is_empty()).then_some(version)
+        }
+        // whisper-cli no tiene `--version`
+        Tool::WhisperCli => None,
+    }
+}
+
+fn whisper_supports_vad(path: &Path) -> bool {
+    Command::new(path)
+        .arg("--help")
+        .stdin(Stdio::null())
+        .output()
+        .map(|o| {
+            let text = format!("{}{}", String::from_utf8_lossy(&o.stdout), String::from_utf8_lossy(&o.stderr));
+            text.contains("--vad")
+        })
+        .unwrap_or(false)
+}
+
+/// Compara versiones numéricas separadas por puntos (`1.7.6`, `2025.09.26`); ignora la `v`.
+fn version_at_least(version: &str, minimum: &str) -> bool {
+    let parse = |v: &str| -> Vec<u64> {
+        v.trim_start_matches('v').split(['.', '-']).map_while(|p| p.parse().ok()).collect()
+    };
+    parse(version) >= parse(minimum)
+}
+
+fn ytdlp_is_stale(version: &str) -> bool {
+    let parts: Vec<u64> = version.split('.').map_while(|p| p.parse().ok()).collect();
+    let [year @ 1970..=9999, month @ 1..=12, day, ..] = parts[..] else {
+        return false;
+    };
+    let now_days = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86_400)
+        .unwrap_or_default();
+    now_days.saturating_sub(days_from_civil(year, month, day)) > YTDLP_STALE_DAYS
+}
+
+/// Días desde 1970-01-01 para una fecha del calendario gregoriano.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let (y, m) = if month <= 2 { (year - 1, month + 9) } else { (year, month - 3) };
+    let era = y / 400;
+    let yoe = y - era * 400;
+    let doy = (153 * m + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    (era * 146_097 + doe).saturating_sub(719_468)
+}
+
+// ─── Provisioning ────────────────────────────────────────────────────────────
+
+fn release_url(tool: Tool) -> Option<String> {
+    let platform = format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH);
+    match tool {
+        Tool::YtDlp => {
+            let asset = match platform.as_str() {
+                "macos-aarch64" | "macos-x86_64" => "yt-dlp_macos",
+                "linux-x86_64" => "yt-dlp_linux",
+                "linux-aarch64" => "yt-dlp_linux_aarch64",
+                "windows-x86_64" => "yt-dlp.exe",
+                _ => return None,
+            };
+            Some(format!("https://github.com/yt-dlp/yt-dlp/releases/latest/download/{}", asset))
+        }
+        // whisper.cpp sólo publica binarios de whisper-cli para Windows
+        Tool::WhisperCli => {
+            let asset = match platform.as_str() {
+                "windows-x86_64" if crate::hardware::detect().backend == crate::hardware::GpuBackend::Cuda => "whisper-cublas-12.4.0-bin-x64.zip",
+                "windows-x86_64" => "whisper-bin-x64.zip",
+                _ => return None,
+            };
+            Some(format!(
+                "https://github.com/ggml-org/whisper.cpp/releases/download/{}/{}",
+                WHISPER_RELEASE, asset
+            ))
+        }
+    }
+}
+
+/// Descarga en `<herramienta>.staging` y la intercambia con la instalación anterior,
+/// igual que llama.cpp.
+fn provision(emit: &dyn Fn(&str, &str, Option<u32>), tool: Tool) -> Result<PathBuf, String> {
+    let _guard = PROVISION_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let url = release_url(tool)
+        .ok_or_else(|| format!("No hay descarga automática de {} para esta plataforma: {}", tool.id(), tool.install_hint()))?;
+    let install_dir = tool_dir(tool);
+    let staging_dir = install_dir.with_file_name(format!("{}.staging", tool.id()));
+    let _ = std::fs::remove_dir_all(&staging_dir);
+    std::fs::create_dir_all(&staging_dir).map_err(|e| e.to_string())?;
+    emit("process_download_assets", &format!("Descargando {}...", tool.id()), None);
+
+    let result = (|| -> Result<ToolInstall, String> {
+        let download = staging_dir.join(url.rsplit('/').next().unwrap_or(tool.bin_name()));
+        crate::download_manager::download(emit, tool.id(), &url, &download)?;
+
+        let bin = if url.ends_with(".zip") {
+            let archive = std::fs::File::open(&download).map_err(|e| e.to_string())?;
+            crate::archive::extract_zip(archive, &staging_dir).map_err(|e| e.to_string())?;
+            let _ = std::fs::remove_file(&download);
+            find_file(&staging_dir, tool.bin_name())
+                .ok_or_else(|| format!("El archivo descargado no contiene {}", tool.bin_name()))?
+        } else {
+            let bin = staging_dir.join(tool.bin_name());
+            std::fs::rename(&download, &bin).map_err(|e| e.to_string())?;
+            bin
+        };
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&bin, std::fs::Permissions::from_mode(0o755)).map_err(|e| e.to_string())?;
+        }
+
+        let version = match tool {
+            Tool::WhisperCli => WHISPER_RELEASE.trim_start_matches('v').to_string(),
+            Tool::YtDlp => detect_version(tool, &bin, ToolSource::System)
+                .ok_or("El yt-dlp descargado no se pudo ejecutar")?,
+        };
+        let rel = bin.strip_prefix(&staging_dir).unwrap_or(&bin);
+        Ok(ToolInstall {
+            version,
+            bin: rel.components().map(|c| c.as_os_str().to_string_lossy()).collect::<Vec<_>>().join("/"),
+        })
+    })();
+
+    let install = match result {
+        Ok(install) => install,
+        Err(e) => {
+            let _ = std::fs::remove_dir_all(&staging_dir);
+            return Err(e);
+        }
+    };
+    let json = serde_json::to_string_pretty(&install).map_err(|e| e.to_string())?;
+    std::fs::write(staging_dir.join("install.json"), json).map_err(|e| e.to_string())?;
+    crate::archive::swap_dirs(&staging_dir, &install_dir).map_err(|e| e.to_string())?;
+
+    log::info!("{} {} installed in {}", tool.id(), install.version, install_dir.display());
+    emit("process_download_assets", &format!("{} {} listo", tool.id(), install.version), Some(100));
+    Ok(install_dir.join(install.bin))
+}
+
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    let entries = std::fs::read_dir(dir).ok()?;
+    let mut subdirs = Vec::new();
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if path.is_dir() {
+            subdirs.push(path);
+        } else if path.file_name().and_then(|n| n.to_str()) == Some(name) {
+            return Some(path);
+        }
+    }
+    subdirs.into_iter().find_map(|sub| find_file(&sub, name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert!(version_at_least("1.7.6", "1.7.6"));
+        assert!(version_at_least("v1.8.0", "1.7.6"));
+        assert!(version_at_least("1.10.0", "1.9.9"));
+        assert!(!version_at_least("1.7.5", "1.7.6"));
+        assert!(version_at_least("2025.06.30", YTDLP_MIN_VERSION));
+        assert!(!version_at_least("2025.05.31", YTDLP_MIN_VERSION));
+        assert!(version_at_least("1.7.6-rc1", "1.7.6"));
+    }
+
+    #[test]
+    fn civil_dates_count_days_since_epoch() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(1970, 3, 1), 59);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        assert_eq!(days_from_civil(2024, 12, 31) - days_from_civil(2024, 1, 1), 365);
+        assert_eq!(days_from_civil(2025, 6, 1), 20_240);
+    }
+
+    #[test]
+    fn ytdlp_staleness_uses_the_release_date() {
+        assert!(ytdlp_is_stale("2020.01.01"));
+        assert!(!ytdlp_is_stale("2999.01.01"));
+        assert!(!ytdlp_is_stale("nightly"));
+        assert!(!ytdlp_is_stale("0.0.1"));
+        assert!(!ytdlp_is_stale("2025.13.01"));
+    }
+}
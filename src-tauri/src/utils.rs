@@ -12,27 +12,10 @@ pub fn models_base_dir() -> std::path::PathBuf {
     base
 }
 
-pub fn detect_gpu() -> &'static str {
-    #[cfg(target_os = "macos")]
-    {
-        "Metal"
-    }
-
-    #[cfg(target_os = "windows")]
-    {
-        let nvidia = std::process::Command::new("nvidia-smi")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false);
-        if nvidia { "CUDA" } else { "CPU" }
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        let nvidia = std::process::Command::new("nvidia-smi")
-            .output()
-            .map(|o| o.status.success())
-            .unwrap_or(false);
-        if nvidia { "CUDA" } else { "CPU" }
-    }
+/// FNV-1a de 64 bits: estable entre versiones de Rust, útil para nombres de archivo
+/// derivados de rutas o identificadores arbitrarios.
+pub fn stable_hash(value: &str) -> u64 {
+    value
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |h, b| (h ^ b as u64).wrapping_mul(0x100000001b3))
 }
@@ -0,0 +1,387 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+
+use crate::audio_processor::{AudioProcessor, EmitType};
+use crate::settings::{WatchFolder, WatchSettings};
+
+const LEDGER_FILE: &str = "watch_ledger.json";
+/// Audio y video que whisper puede transcribir tras extraer la pista de audio.
+const MEDIA_EXTENSIONS: &[&str] = &[
+    "wav", "mp3", "m4a", "flac", "ogg", "opus", "aac", "wma", "mp4", "m4v", "mov", "mkv", "webm", "avi",
+];
+/// Eventos de descarga de modelos que sí se reenvían a la interfaz durante un trabajo automático.
+const FORWARDED_EVENTS: &[&str] = &["download_progress", "process_download_assets"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryState {
+    Done,
+    Failed,
+    /// Ya estaba en la carpeta cuando se agregó y no se pidió procesar lo existente.
+    Skipped,
+}
+
+/// Un archivo ya visto. Se vuelve a procesar sólo si cambian tamaño o fecha de modificación.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LedgerEntry {
+    pub source: String,
+    pub size: u64,
+    pub modified: u64,
+    pub state: EntryState,
+    pub transcript: Option<String>,
+    pub summary: Option<String>,
+    pub error: Option<String>,
+    pub processed_at: u64,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Ledger {
+    /// Carpetas cuyo contenido inicial ya quedó registrado.
+    baselined: Vec<String>,
+    entries: BTreeMap<String, LedgerEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchStatus {
+    pub running: bool,
+    /// Archivo que se está transcribiendo ahora.
+    pub current: Option<String>,
+    /// Archivos detectados que esperan a que termine la copia.
+    pub pending: Vec<String>,
+    pub entries: Vec<LedgerEntry>,
+}
+
+/// Evento `watch_folder` para la interfaz.
+#[derive(Debug, Clone, Serialize)]
+struct WatchEvent<'a> {
+    source: &'a str,
+    state: &'a str,
+    message: &'a str,
+}
+
+#[derive(Default)]
+struct SharedState {
+    current: Option<String>,
+    pending: Vec<String>,
+}
+
+static RUNNING: AtomicBool = AtomicBool::new(false);
+static STATE: Mutex<SharedState> = Mutex::new(SharedState { current: None, pending: Vec::new() });
+
+/// Arranca el hilo que revisa las carpetas configuradas. La configuración se relee en
+/// cada pasada, así que agregar o quitar carpetas no requiere reiniciar.
+pub fn start(emit: EmitType) {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    std::thread::spawn(move || {
+        let mut pending: HashMap<PathBuf, (u64, u64, Instant)> = HashMap::new();
+        loop {
+            let settings = crate::settings::load();
+            let watch = settings.watch;
+            if watch.folders.iter().any(|f| f.enabled) {
+                scan(&emit, &watch, &settings.transcription.whisper_model, &mut pending);
+            } else {
+                pending.clear();
+            }
+            std::thread::sleep(Duration::from_secs(watch.poll_interval_secs.max(1)));
+        }
+    });
+}
+
+pub fn status() -> WatchStatus {
+    let state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    let mut entries: Vec<LedgerEntry> = load_ledger().entries.into_values().collect();
+    entries.sort_by_key(|e| std::cmp::Reverse(e.processed_at));
+    WatchStatus {
+        running: RUNNING.load(Ordering::SeqCst),
+        current: state.current.clone(),
+        pending: state.pending.clone(),
+        entries,
+    }
+}
+
+/// Quita un archivo del registro para que se procese en la próxima pasada.
+pub fn forget(source: &str) -> Result<(), String> {
+    let mut ledger = load_ledger();
+    if ledger.entries.remove(source).is_none() {
+        return Err(format!("No está en el registro: {}", source));
+    }
+    save_ledger(&ledger)
+}
+
+// ─── Detección ───────────────────────────────────────────────────────────────
+
+fn scan(
+    emit: &EmitType,
+    watch: &WatchSettings,
+    whisper_model: &str,
+    pending: &mut HashMap<PathBuf, (u64, u64, Instant)>,
+) {
+    let mut ledger = load_ledger();
+    let mut seen: Vec<PathBuf> = Vec::new();
+
+    for folder in watch.folders.iter().filter(|f| f.enabled) {
+        let root = Path::new(&folder.path);
+        let files = media_files(root, folder.recursive);
+
+        if !ledger.baselined.contains(&folder.path) {
+            if !folder.process_existing {
+                for (path, size, modified) in &files {
+                    ledger.entries.insert(
+                        path.to_string_lossy().into_owned(),
+                        entry(path, *size, *modified, EntryState::Skipped),
+                    );
+                }
+                log::info!("watch: {} archivos existentes en {} se omiten", files.len(), folder.path);
+            }
+            ledger.baselined.push(folder.path.clone());
+            if let Err(e) = save_ledger(&ledger) {
+                log::warn!("watch: {}", e);
+            }
+        }
+
+        for (path, size, modified) in files {
+            let key = path.to_string_lossy().into_owned();
+            if ledger.entries.get(&key).is_some_and(|e| e.size == size && e.modified == modified) {
+                continue;
+            }
+            seen.push(path.clone());
+            // Mientras el archivo siga creciendo (copia o sincronización en curso) se espera
+            let stable = match pending.get(&path) {
+                Some((s, m, since)) if *s == size && *m == modified => {
+                    since.elapsed() >= Duration::from_secs(watch.stable_secs)
+                }
+                _ => {
+                    pending.insert(path.clone(), (size, modified, Instant::now()));
+                    false
+                }
+            };
+            if !stable || size == 0 {
+                continue;
+            }
+
+            pending.remove(&path);
+            let processed = process_file(emit, folder, root, &path, size, modified, whisper_model);
+            ledger = load_ledger();
+            ledger.entries.insert(key, processed);
+            if let Err(e) = save_ledger(&ledger) {
+                log::warn!("watch: {}", e);
+            }
+        }
+    }
+
+    pending.retain(|path, _| seen.contains(path));
+    let mut state = STATE.lock().unwrap_or_else(|e| e.into_inner());
+    state.pending = pending.keys().map(|p| p.to_string_lossy().into_owned()).collect();
+}
+
+/// (ruta, tamaño, fecha de modificación en segundos) de los archivos de audio/video.
+fn media_files(dir: &Path, recursive: bool) -> Vec<(PathBuf, u64, u64)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files = Vec::new();
+    for entry in entries.filter_map(|e| e.ok()) {
+        let path = entry.path();
+        // Ocultos: temporales de apps de sincronización y de sistema
+        if entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let Ok(metadata) = entry.metadata() else { continue };
+        if metadata.is_dir() {
+            if recursive {
+                files.extend(media_files(&path, true));
+            }
+            continue;
+        }
+        let is_media = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|ext| MEDIA_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        if is_media {
+            files.push((path, metadata.len(), unix_secs(metadata.modified().ok())));
+        }
+    }
+    files
+}
+
+// ─── Procesamiento ───────────────────────────────────────────────────────────
+
+fn process_file(
+    emit: &EmitType,
+    folder: &WatchFolder,
+    root: &Path,
+    path: &Path,
+    size: u64,
+    modified: u64,
+    whisper_model: &str,
+) -> LedgerEntry {
+    let source = path.to_string_lossy().into_owned();
+    log::info!("watch: procesando {}", source);
+    STATE.lock().unwrap_or_else(|e| e.into_inner()).current = Some(source.clone());
+    notify(emit, &source, "processing", "Transcribiendo");
+
+    let result = transcribe_and_write(emit, folder, root, path, whisper_model);
+    STATE.lock().unwrap_or_else(|e| e.into_inner()).current = None;
+
+    let mut processed = entry(path, size, modified, EntryState::Done);
+    match result {
+        Ok((transcript, summary, summary_error)) => {
+            notify(emit, &source, "done", summary_error.as_deref().unwrap_or("Completado"));
+            processed.transcript = Some(transcript.to_string_lossy().into_owned());
+            processed.summary = summary.map(|p| p.to_string_lossy().into_owned());
+            processed.error = summary_error;
+        }
+        Err(e) => {
+            log::warn!("watch: {} falló: {}", source, e);
+            notify(emit, &source, "failed", &e);
+            processed.state = EntryState::Failed;
+            processed.error = Some(e);
+        }
+    }
+    processed
+}
+
+/// Devuelve la ruta de la transcripción, la del resumen y, si el resumen falló, el motivo.
+fn transcribe_and_write(
+    emit: &EmitType,
+    folder: &WatchFolder,
+    root: &Path,
+    path: &Path,
+    whisper_model: &str,
+) -> Result<(PathBuf, Option<PathBuf>, Option<String>), String> {
+    let job_emit = job_emit(emit);
+    let transcription = AudioProcessor::new(job_emit.clone(), path.to_string_lossy().into_owned(), whisper_model.to_string())
+        .run()?;
+    if transcription.text.trim().is_empty() {
+        return Err("La transcripción salió vacía".into());
+    }
+
+    let output = |suffix: &str| output_path(folder, root, path, suffix);
+    let transcript_path = output(".txt");
+    if let Some(parent) = transcript_path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("No se pudo crear {}: {}", parent.display(), e))?;
+    }
+    std::fs::write(&transcript_path, &transcription.text)
+        .map_err(|e| format!("No se pudo escribir {}: {}", transcript_path.display(), e))?;
+    if let Ok(json) = serde_json::to_string_pretty(&transcription.segments) {
+        let _ = std::fs::write(output(".segments.json"), json);
+    }
+
+    if !folder.summarize {
+        return Ok((transcript_path, None, None));
+    }
+    notify(emit, &path.to_string_lossy(), "summarizing", "Resumiendo");
+    match crate::summarizer_cli::summarize_transcript(job_emit, &transcription.text, None, None, None, None, None) {
+        Ok(summary) => {
+            let summary_path = output(".summary.md");
+            std::fs::write(&summary_path, summary.text)
+                .map_err(|e| format!("No se pudo escribir {}: {}", summary_path.display(), e))?;
+            Ok((transcript_path, Some(summary_path), None))
+        }
+        // La transcripción ya está escrita; el resumen se puede pedir a mano después
+        Err(e) => Ok((transcript_path, None, Some(format!("resumen: {}", e)))),
+    }
+}
+
+/// `<salida>/<subcarpeta relativa>/<nombre sin extensión><suffix>`, o junto al original. El
+/// sufijo se agrega al nombre: `with_extension` recortaría `llamada.1` y `llamada.2` a `llamada`.
+fn output_path(folder: &WatchFolder, root: &Path, path: &Path, suffix: &str) -> PathBuf {
+    let mut stem = path.file_stem().unwrap_or_default().to_os_string();
+    stem.push(suffix);
+    match &folder.output_dir {
+        Some(dir) => {
+            let relative_parent = path
+                .parent()
+                .and_then(|p| p.strip_prefix(root).ok())
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            Path::new(dir).join(relative_parent).join(stem)
+        }
+        None => path.with_file_name(stem),
+    }
+}
+
+/// Los trabajos automáticos no deben pisar el progreso de lo que el usuario hace a mano:
+/// sus eventos van al log, salvo el progreso de descarga de modelos.
+fn job_emit(emit: &EmitType) -> EmitType {
+    let emit = emit.clone();
+    Arc::new(move |event: &str, step: &str, count: Option<u32>| {
+        if FORWARDED_EVENTS.contains(&event) {
+            emit(event, step, count);
+        } else {
+            log::debug!("watch [{}] {}", event, step);
+        }
+    })
+}
+
+fn notify(emit: &EmitType, source: &str, state: &str, message: &str) {
+    if let Ok(json) = serde_json::to_string(&WatchEvent { source, state, message }) {
+        emit("watch_folder", &json, None);
+    }
+}
+
+// ─── Registro ────────────────────────────────────────────────────────────────
+
+fn entry(path: &Path, size: u64, modified: u64, state: EntryState) -> LedgerEntry {
+    LedgerEntry {
+        source: path.to_string_lossy().into_owned(),
+        size,
+        modified,
+        state,
+        transcript: None,
+        summary: None,
+        error: None,
+        processed_at: unix_secs(Some(SystemTime::now())),
+    }
+}
+
+fn unix_secs(time: Option<SystemTime>) -> u64 {
+    time.and_then(|t| t.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+fn ledger_path() -> PathBuf {
+    crate::utils::models_base_dir().join(LEDGER_FILE)
+}
+
+fn load_ledger() -> Ledger {
+    std::fs::read_to_string(ledger_path())
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+fn save_ledger(ledger: &Ledger) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(ledger).map_err(|e| e.to_string())?;
+    std::fs::write(ledger_path(), json).map_err(|e| format!("No se pudo guardar {}: {}", LEDGER_FILE, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn output_names_keep_inner_dots() {
+        let root = Path::new("/grabaciones");
+        let beside = WatchFolder { path: "/grabaciones".into(), ..WatchFolder::default() };
+        assert_eq!(
+            output_path(&beside, root, Path::new("/grabaciones/call.1.mp3"), ".txt"),
+            Path::new("/grabaciones/call.1.txt")
+        );
+        assert_eq!(
+            output_path(&beside, root, Path::new("/grabaciones/call.2.mp3"), ".summary.md"),
+            Path::new("/grabaciones/call.2.summary.md")
+        );
+
+        let elsewhere = WatchFolder { output_dir: Some("/textos".into()), ..beside };
+        assert_eq!(
+            output_path(&elsewhere, root, Path::new("/grabaciones/lunes/call.1.mp3"), ".segments.json"),
+            Path::new("/textos/lunes/call.1.segments.json")
+        );
+    }
+}
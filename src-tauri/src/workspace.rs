@@ -0,0 +1,166 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const ROOT_DIR: &str = "beautiful-stt-jobs";
+/// Si no se puede saber si el proceso dueño sigue vivo, se borra pasado este tiempo.
+const MAX_ORPHAN_AGE: Duration = Duration::from_secs(7 * 24 * 3600);
+/// Archivos sueltos que versiones anteriores dejaban directamente en la carpeta temporal.
+const LEGACY_PREFIXES: &[&str] = &[
+    "beautiful_stt_input",
+    "beautiful_stt_out_",
+    "beautiful_stt_llm_",
+    "beautiful_stt_tokenize_",
+    "beautiful_stt_embed_",
+    "beautiful_stt_smoke_",
+    "beautiful-stt-download.",
+];
+/// Audios de yt-dlp en la carpeta temporal: `<prefijo><id del workspace>.<ext>`. Viven más que
+/// su trabajo porque se transcriben después, así que se borran cuando su proceso ya no existe.
+pub const DOWNLOAD_PREFIX: &str = "beautiful-stt-download-";
+
+static NEXT_JOB: AtomicU64 = AtomicU64::new(1);
+
+/// Carpeta temporal propia de un trabajo (transcripción, resumen, descarga...).
+/// Se borra con todo su contenido al soltarse, también si el trabajo termina con
+/// error, con un panic o porque se abandonó la tarea que lo contenía.
+pub struct Workspace {
+    dir: PathBuf,
+}
+
+impl Workspace {
+    /// `kind` identifica el trabajo en el nombre de la carpeta (`transcribe`, `llm`...).
+    pub fn create(kind: &str) -> Result<Workspace, String> {
+        let millis = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_millis();
+        let name = format!(
+            "{}-{}-{}-{}",
+            std::process::id(),
+            millis,
+            NEXT_JOB.fetch_add(1, Ordering::Relaxed),
+            kind
+        );
+        let dir = root().join(name);
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("No se pudo crear la carpeta temporal {}: {}", dir.display(), e))?;
+        log::debug!("workspace {} creado", dir.display());
+        Ok(Workspace { dir })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// Nombre único de la carpeta, útil para derivar nombres de archivo fuera de ella.
+    pub fn id(&self) -> String {
+        self.dir.file_name().unwrap_or_default().to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        if let Err(e) = std::fs::remove_dir_all(&self.dir) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("no se pudo borrar {}: {}", self.dir.display(), e);
+            }
+        }
+    }
+}
+
+fn root() -> PathBuf {
+    std::env::temp_dir().join(ROOT_DIR)
+}
+
+/// Borra las carpetas de trabajos cuyo proceso ya no existe (cierres inesperados), los audios
+/// descargados en sesiones anteriores y los archivos temporales sueltos de versiones
+/// anteriores. Se llama una vez al arrancar.
+pub fn sweep_orphans() {
+    let mut removed = 0;
+    if let Ok(entries) = std::fs::read_dir(root()) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if is_orphan(&name, &path) && std::fs::remove_dir_all(&path).is_ok() {
+                removed += 1;
+            }
+        }
+    }
+    if let Ok(entries) = std::fs::read_dir(std::env::temp_dir()) {
+        for entry in entries.filter_map(|e| e.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let stale = match name.strip_prefix(DOWNLOAD_PREFIX) {
+                Some(id) => is_orphan(id, &entry.path()),
+                None => LEGACY_PREFIXES.iter().any(|prefix| name.starts_with(prefix)),
+            };
+            if stale && std::fs::remove_file(entry.path()).is_ok() {
+                removed += 1;
+            }
+        }
+    }
+    if removed > 0 {
+        log::info!("temporales huérfanos eliminados: {}", removed);
+    }
+}
+
+/// `id` empieza por el pid del proceso que lo creó (ver `Workspace::id`).
+fn is_orphan(id: &str, path: &Path) -> bool {
+    match id.split('-').next().and_then(|p| p.parse::<u32>().ok()) {
+        Some(pid) if pid == std::process::id() => false,
+        Some(pid) => !process_alive(pid).unwrap_or_else(|| older_than(path, MAX_ORPHAN_AGE)),
+        None => older_than(path, MAX_ORPHAN_AGE),
+    }
+}
+
+fn older_than(path: &Path, age: Duration) -> bool {
+    std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|elapsed| elapsed > age)
+}
+
+/// `None` si no hay forma de averiguarlo en esta plataforma.
+fn process_alive(pid: u32) -> Option<bool> {
+    if cfg!(target_os = "linux") {
+        return Some(Path::new(&format!("/proc/{}", pid)).exists());
+    }
+    if cfg!(target_os = "windows") {
+        let output = std::process::Command::new("tasklist")
+            .args(["/FI", &format!("PID eq {}", pid), "/NH", "/FO", "CSV"])
+            .output()
+            .ok()?;
+        return Some(String::from_utf8_lossy(&output.stdout).contains(&format!("\"{}\"", pid)));
+    }
+    let status = std::process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(std::process::Stdio::null())
+        .status()
+        .ok()?;
+    Some(status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downloads_of_this_process_are_kept() {
+        let id = Workspace::create("download").unwrap().id();
+        let path = std::env::temp_dir().join(format!("{}{}.m4a", DOWNLOAD_PREFIX, id));
+        std::fs::write(&path, b"audio").unwrap();
+        sweep_orphans();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn downloads_of_dead_processes_are_swept() {
+        let path = std::env::temp_dir().join(format!("{}{}-1-1-download.m4a", DOWNLOAD_PREFIX, u32::MAX));
+        std::fs::write(&path, b"audio").unwrap();
+        sweep_orphans();
+        assert!(!path.exists());
+    }
+}